@@ -0,0 +1,257 @@
+use crate::cube::check_axis;
+use crate::matrix::Matrix;
+use crate::ray::Ray;
+use crate::tuple::*;
+
+// Axis aligned bounding box, empty when `min` is greater than `max`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct BoundingBox {
+    pub min: Tuple,
+    pub max: Tuple,
+}
+
+impl Default for BoundingBox {
+    fn default() -> Self {
+        BoundingBox::empty()
+    }
+}
+
+impl BoundingBox {
+    pub fn new(min: Tuple, max: Tuple) -> BoundingBox {
+        BoundingBox { min, max }
+    }
+
+    pub fn empty() -> BoundingBox {
+        BoundingBox {
+            min: point(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: point(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+
+    pub fn infinite() -> BoundingBox {
+        BoundingBox {
+            min: point(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+            max: point(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.0 > self.max.0 || self.min.1 > self.max.1 || self.min.2 > self.max.2
+    }
+
+    pub fn is_finite(&self) -> bool {
+        [self.min, self.max]
+            .iter()
+            .all(|p| p.0.is_finite() && p.1.is_finite() && p.2.is_finite())
+    }
+
+    pub fn add_point(self, p: &Tuple) -> BoundingBox {
+        BoundingBox {
+            min: point(
+                self.min.0.min(p.0),
+                self.min.1.min(p.1),
+                self.min.2.min(p.2),
+            ),
+            max: point(
+                self.max.0.max(p.0),
+                self.max.1.max(p.1),
+                self.max.2.max(p.2),
+            ),
+        }
+    }
+
+    pub fn merge(&self, other: &BoundingBox) -> BoundingBox {
+        // the corners of an empty box would span the whole space
+        if other.is_empty() {
+            return *self;
+        }
+        self.add_point(&other.min).add_point(&other.max)
+    }
+
+    pub fn contains_point(&self, p: &Tuple) -> bool {
+        (self.min.0..=self.max.0).contains(&p.0)
+            && (self.min.1..=self.max.1).contains(&p.1)
+            && (self.min.2..=self.max.2).contains(&p.2)
+    }
+
+    pub fn contains_box(&self, other: &BoundingBox) -> bool {
+        self.contains_point(&other.min) && self.contains_point(&other.max)
+    }
+
+    // halves of the box split in the middle of its longest axis
+    pub fn split(&self) -> (BoundingBox, BoundingBox) {
+        let dx = self.max.0 - self.min.0;
+        let dy = self.max.1 - self.min.1;
+        let dz = self.max.2 - self.min.2;
+        let greatest = dx.max(dy).max(dz);
+        let (mut x0, mut y0, mut z0) = (self.min.0, self.min.1, self.min.2);
+        let (mut x1, mut y1, mut z1) = (self.max.0, self.max.1, self.max.2);
+        if greatest == dx {
+            x0 += dx / 2.0;
+            x1 = x0;
+        } else if greatest == dy {
+            y0 += dy / 2.0;
+            y1 = y0;
+        } else {
+            z0 += dz / 2.0;
+            z1 = z0;
+        }
+        let left = BoundingBox::new(self.min, point(x1, y1, z1));
+        let right = BoundingBox::new(point(x0, y0, z0), self.max);
+        (left, right)
+    }
+
+    // box containing the eight transformed corners
+    pub fn transform(&self, matrix: &Matrix) -> BoundingBox {
+        // an empty box stays empty wherever it is moved
+        if self.is_empty() {
+            return *self;
+        }
+        // infinite coordinates cannot go through the matrix without producing NaN
+        if !self.is_finite() {
+            return BoundingBox::infinite();
+        }
+        let (min, max) = (self.min, self.max);
+        [
+            point(min.0, min.1, min.2),
+            point(min.0, min.1, max.2),
+            point(min.0, max.1, min.2),
+            point(min.0, max.1, max.2),
+            point(max.0, min.1, min.2),
+            point(max.0, min.1, max.2),
+            point(max.0, max.1, min.2),
+            point(max.0, max.1, max.2),
+        ]
+        .iter()
+        .fold(BoundingBox::empty(), |bounds, corner| {
+            bounds.add_point(&matrix.multiply_tuple(corner))
+        })
+    }
+
+    // slab test on the three axis
+    pub fn intersects(&self, ray: &Ray) -> bool {
+        let (xtmin, xtmax) = check_axis(ray.origin.0, ray.direction.0, self.min.0, self.max.0);
+        let (ytmin, ytmax) = check_axis(ray.origin.1, ray.direction.1, self.min.1, self.max.1);
+        let (ztmin, ztmax) = check_axis(ray.origin.2, ray.direction.2, self.min.2, self.max.2);
+        let tmin = xtmin.max(ytmin).max(ztmin);
+        let tmax = xtmax.min(ytmax).min(ztmax);
+        tmin <= tmax
+    }
+}
+
+#[cfg(test)]
+mod bounds_tests {
+    use crate::bounds::BoundingBox;
+    use crate::cube::Cube;
+    use crate::group::Group;
+    use crate::matrix::Matrix;
+    use crate::plane::Plane;
+    use crate::ray::Ray;
+    use crate::shape::Shape;
+    use crate::sphere::Sphere;
+    use crate::tuple::*;
+    use std::f64::consts::{FRAC_PI_4, SQRT_2};
+
+    #[test]
+    fn sphere_has_unit_bounds() {
+        let s = Sphere::new(1);
+        assert_eq!(
+            s.bounds(),
+            BoundingBox::new(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn plane_is_infinite_in_x_and_z() {
+        let b = Plane::new(1).bounds();
+        assert_eq!(b.min, point(f64::NEG_INFINITY, 0.0, f64::NEG_INFINITY));
+        assert_eq!(b.max, point(f64::INFINITY, 0.0, f64::INFINITY));
+    }
+
+    #[test]
+    fn merging_bounding_boxes() {
+        let b1 = BoundingBox::new(point(-5.0, -2.0, 0.0), point(7.0, 4.0, 4.0));
+        let b2 = BoundingBox::new(point(8.0, -7.0, -2.0), point(14.0, 2.0, 8.0));
+        let merged = b1.merge(&b2);
+        assert_eq!(merged.min, point(-5.0, -7.0, -2.0));
+        assert_eq!(merged.max, point(14.0, 4.0, 8.0));
+        assert_eq!(BoundingBox::empty().merge(&b1), b1);
+        assert_eq!(b1.merge(&BoundingBox::empty()), b1);
+    }
+
+    #[test]
+    fn group_merges_transformed_cube_bounds() {
+        let c1 = Cube::new(2).set_transform(Matrix::translation(2.0, 0.0, 0.0));
+        let c2 = Cube::new(3).set_transform(
+            Matrix::translation(0.0, 3.0, 0.0).multiply(&Matrix::scaling(0.5, 2.0, 0.5)),
+        );
+        let g = Group::new(1)
+            .add_child(Box::new(c1))
+            .add_child(Box::new(c2));
+        let b = g.bounds();
+        assert_eq!(b.min, point(-0.5, -1.0, -1.0));
+        assert_eq!(b.max, point(3.0, 5.0, 1.0));
+    }
+
+    #[test]
+    fn transforming_bounding_box() {
+        let b = BoundingBox::new(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0));
+        let m = Matrix::rotate_x(FRAC_PI_4).multiply(&Matrix::rotate_y(FRAC_PI_4));
+        let t = b.transform(&m);
+        assert!((t.min.0 + SQRT_2).abs() < 0.0001);
+        assert!((t.min.1 - -1.707106).abs() < 0.0001);
+        assert!((t.min.2 - -1.707106).abs() < 0.0001);
+        assert!((t.max.0 - SQRT_2).abs() < 0.0001);
+        assert!((t.max.1 - 1.707106).abs() < 0.0001);
+        assert!((t.max.2 - 1.707106).abs() < 0.0001);
+    }
+
+    #[test]
+    fn transforming_empty_bounding_box_keeps_it_empty() {
+        let t = BoundingBox::empty().transform(&Matrix::translation(1.0, 2.0, 3.0));
+        assert!(t.is_empty());
+        // an empty child group does not make its parent infinite
+        let g = Group::new(1)
+            .add_child(Box::new(Group::new(2)))
+            .add_child(Box::new(Cube::new(3)));
+        assert_eq!(
+            g.bounds(),
+            BoundingBox::new(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn splitting_box_along_longest_axis() {
+        let b = BoundingBox::new(point(-1.0, -2.0, -3.0), point(9.0, 5.5, 3.0));
+        let (left, right) = b.split();
+        assert_eq!(left.min, point(-1.0, -2.0, -3.0));
+        assert_eq!(left.max, point(4.0, 5.5, 3.0));
+        assert_eq!(right.min, point(4.0, -2.0, -3.0));
+        assert_eq!(right.max, point(9.0, 5.5, 3.0));
+
+        let b = BoundingBox::new(point(-1.0, -2.0, -3.0), point(5.0, 3.0, 7.0));
+        let (left, right) = b.split();
+        assert_eq!(left.max, point(5.0, 3.0, 2.0));
+        assert_eq!(right.min, point(-1.0, -2.0, 2.0));
+    }
+
+    #[test]
+    fn box_contains_point() {
+        let b = BoundingBox::new(point(5.0, -2.0, 0.0), point(11.0, 4.0, 7.0));
+        assert!(b.contains_point(&point(5.0, -2.0, 0.0)));
+        assert!(b.contains_point(&point(8.0, 1.0, 3.0)));
+        assert!(!b.contains_point(&point(3.0, 0.0, 3.0)));
+        assert!(!b.contains_point(&point(8.0, 1.0, 8.0)));
+    }
+
+    #[test]
+    fn ray_misses_bounding_box() {
+        let b = BoundingBox::new(point(5.0, -2.0, 0.0), point(11.0, 4.0, 7.0));
+        assert!(b.intersects(&Ray::new(point(15.0, 1.0, 2.0), vector(-1.0, 0.0, 0.0))));
+        assert!(!b.intersects(&Ray::new(point(-5.0, 6.0, 4.0), vector(1.0, 0.0, 0.0))));
+        assert!(!b.intersects(&Ray::new(
+            point(9.0, -1.0, -8.0),
+            vector_normalize(&vector(2.0, 4.0, 6.0))
+        )));
+    }
+}
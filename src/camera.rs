@@ -1,8 +1,14 @@
 use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::integrator::{Integrator, WhittedIntegrator, MAX_DEPTH};
 use crate::matrix::Matrix;
 use crate::ray::Ray;
+use crate::sampler::{RandomSampler, Sampler};
+use crate::stats::RenderStats;
+use crate::transformation::view_transform;
 use crate::tuple::*;
-use crate::world::World;
+use crate::world::{World, WorldObject};
+use std::f64::consts::PI;
 
 pub struct Camera {
     hsize: usize,
@@ -14,6 +20,9 @@ pub struct Camera {
     pixel_size: f64,
     half_width: f64,
     half_height: f64,
+    aperture: f64,       // diameter of the lens, 0 for a pinhole camera
+    focal_distance: f64, // distance from the eye to the plane in perfect focus
+    time_samples: usize, // rays cast at random times per pixel, 1 renders the shapes at time 0
 }
 
 impl Camera {
@@ -39,9 +48,33 @@ impl Camera {
             half_width,
             half_height,
             origin,
+            aperture: 0.0,
+            focal_distance: 1.0,
+            time_samples: 1,
         }
     }
 
+    pub fn hsize(&self) -> usize {
+        self.hsize
+    }
+
+    pub fn vsize(&self) -> usize {
+        self.vsize
+    }
+
+    pub fn field_of_view(&self) -> f64 {
+        self.field_of_view
+    }
+
+    pub fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    // size of a pixel on the canvas one unit in front of the camera
+    pub fn pixel_size(&self) -> f64 {
+        self.pixel_size
+    }
+
     pub fn set_transform(self, transform: Matrix) -> Camera {
         let transform_inverse = Matrix::inverse(&transform);
         let origin = transform_inverse.multiply_tuple(&point_zero());
@@ -53,7 +86,40 @@ impl Camera {
         }
     }
 
-    fn ray_for_pixel(&self, px: usize, py: usize) -> Ray {
+    // looks at the center of the world bounds from `direction` (pointing from the scene to the eye),
+    // far enough for the sphere enclosing the bounds to fit in the narrowest side of the view
+    // the camera is left untouched when the world has no finite object
+    pub fn fit_to<O: WorldObject>(self, world: &World<O>, direction: &Tuple, up: &Tuple) -> Camera {
+        let bounds = world.bounds();
+        if !bounds.is_finite() {
+            return self;
+        }
+        let to = lerp_tuple(&bounds.min, &bounds.max, 0.5);
+        let radius = tuple_distance(&bounds.min, &bounds.max) / 2.0;
+        let half_angle = self.half_width.min(self.half_height).atan();
+        let distance = radius / half_angle.sin();
+        let from = add_tuple(&to, &scale_tuple(&vector_normalize(direction), distance));
+        self.set_transform(view_transform(&from, &to, up))
+    }
+
+    // simulates a thin lens, objects away from the focal plane get blurry as the aperture grows
+    pub fn set_lens(self, aperture: f64, focal_distance: f64) -> Camera {
+        Camera {
+            aperture,
+            focal_distance,
+            ..self
+        }
+    }
+
+    // averages `samples` rays per pixel spread over the exposure to blur the moving shapes
+    pub fn set_motion_blur(self, samples: usize) -> Camera {
+        Camera {
+            time_samples: samples.max(1),
+            ..self
+        }
+    }
+
+    fn ray_for_pixel(&self, px: usize, py: usize, sampler: &mut dyn Sampler) -> Ray {
         // offset from the edge of the canvas of the pixel's center
         let x_offset = (px as f64 + 0.5) * self.pixel_size;
         let y_offset = (py as f64 + 0.5) * self.pixel_size;
@@ -64,6 +130,9 @@ impl Camera {
         // using the camera matrix, transform the canvas point and the origin
         // and then compute the ray's direction vector
         // (remember that the canvas is at z=-1)
+        if self.aperture > 0.0 {
+            return self.lens_ray(world_x, world_y, sampler);
+        }
         let pixel = self
             .transform_inverse
             .multiply_tuple(&point(world_x, world_y, -1.0));
@@ -72,29 +141,201 @@ impl Camera {
         Ray::new(origin, direction)
     }
 
-    pub fn render(&self, world: &World) -> Canvas {
-        let mut canvas = Canvas::make(self.hsize, self.vsize);
-        for y in 0..self.vsize {
-            for x in 0..self.hsize {
-                let ray = self.ray_for_pixel(x, y);
-                let color = world.color_at(&ray);
-                canvas.write(x, y, color);
+    fn lens_ray(&self, world_x: f64, world_y: f64, sampler: &mut dyn Sampler) -> Ray {
+        // all rays through the lens converge on the focal plane,
+        // found by extending the pinhole ray from the canvas at z=-1 to z=-focal_distance
+        let focal_point = self.transform_inverse.multiply_tuple(&point(
+            world_x * self.focal_distance,
+            world_y * self.focal_distance,
+            -self.focal_distance,
+        ));
+        // uniformly sample a point on the lens disk
+        let radius = self.aperture / 2.0 * sampler.next_f64().sqrt();
+        let theta = 2.0 * PI * sampler.next_f64();
+        let lens_point = point(radius * theta.cos(), radius * theta.sin(), 0.0);
+        let origin = self.transform_inverse.multiply_tuple(&lens_point);
+        let direction = vector_normalize(&subtract_tuple(&focal_point, &origin));
+        Ray::new(origin, direction)
+    }
+
+    pub fn render<O: WorldObject>(&self, world: &World<O>) -> Canvas {
+        Canvas {
+            width: self.hsize,
+            height: self.vsize,
+            content: self.render_raw(world),
+            alpha: None,
+        }
+    }
+
+    // the counters of the world are reset before rendering
+    pub fn render_with_stats<O: WorldObject>(&self, world: &World<O>) -> (Canvas, RenderStats) {
+        world.counters.reset();
+        let canvas = self.render(world);
+        (canvas, world.counters.snapshot())
+    }
+
+    // row-major pixel buffer laid out like `Canvas::content`, at index x + y * hsize
+    pub fn render_raw<O: WorldObject>(&self, world: &World<O>) -> Vec<Color> {
+        (0..self.vsize)
+            .flat_map(|y| (0..self.hsize).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                self.color_for_pixel(world, &WhittedIntegrator, x, y, false)
+                    .0
+            })
+            .collect()
+    }
+
+    // distance to the first hit for each pixel with the `render_raw` layout, infinite on misses
+    pub fn render_depth<O: WorldObject>(&self, world: &World<O>) -> Vec<f64> {
+        (0..self.vsize)
+            .flat_map(|y| (0..self.hsize).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let ray = self.ray_for_pixel(x, y, &mut RandomSampler);
+                // the intersections are sorted and the direction is normalized
+                let scale = vector_magnitude(&ray.direction);
+                world
+                    .intersect_with_ray(&ray)
+                    .first()
+                    .map_or(f64::INFINITY, |i| i.distance * scale)
+            })
+            .collect()
+    }
+
+    pub fn render_with<O: WorldObject>(
+        &self,
+        world: &World<O>,
+        integrator: &dyn Integrator<O>,
+    ) -> Canvas {
+        self.render_rows(world, integrator, self.full_region(), false, &|_, _| {})
+    }
+
+    // `progress` is called with (rows_done, total_rows) after each completed row
+    pub fn render_with_progress<O: WorldObject>(
+        &self,
+        world: &World<O>,
+        progress: impl Fn(usize, usize),
+    ) -> Canvas {
+        self.render_rows(
+            world,
+            &WhittedIntegrator,
+            self.full_region(),
+            false,
+            &progress,
+        )
+    }
+
+    // renders the pixels from (x0, y0) included to (x1, y1) excluded into a canvas of the region's size,
+    // the region is clamped to the camera's canvas
+    pub fn render_region<O: WorldObject>(
+        &self,
+        world: &World<O>,
+        x0: usize,
+        y0: usize,
+        x1: usize,
+        y1: usize,
+    ) -> Canvas {
+        let region = (
+            x0,
+            y0,
+            x1.min(self.hsize).max(x0),
+            y1.min(self.vsize).max(y0),
+        );
+        self.render_rows(world, &WhittedIntegrator, region, false, &|_, _| {})
+    }
+
+    // the color of the pixel and the fraction of its rays which hit an object,
+    // `premultiplied` colors are black where rays miss instead of showing the background
+    fn color_for_pixel<O: WorldObject>(
+        &self,
+        world: &World<O>,
+        integrator: &dyn Integrator<O>,
+        px: usize,
+        py: usize,
+        premultiplied: bool,
+    ) -> (Color, f64) {
+        let mut sampler = RandomSampler;
+        let sample = |time: f64, sampler: &mut dyn Sampler| {
+            world.counters.add_ray();
+            let ray = self.ray_for_pixel(px, py, sampler).set_time(time);
+            let (color, hit) = integrator.color_and_coverage(world, &ray, MAX_DEPTH);
+            match (hit, premultiplied) {
+                (false, true) => (Color::default(), false),
+                _ => (color, hit),
             }
+        };
+        if self.time_samples == 1 {
+            let (color, hit) = sample(0.0, &mut sampler);
+            return (color, if hit { 1.0 } else { 0.0 });
+        }
+        // samples are averaged in f64, the canvas output is the only place clamping colors
+        let samples: Vec<(Color, bool)> = (0..self.time_samples)
+            .map(|_| {
+                let time = sampler.next_f64();
+                sample(time, &mut sampler)
+            })
+            .collect();
+        let hits = samples.iter().filter(|(_, hit)| *hit).count();
+        (
+            Color::average(samples.into_iter().map(|(color, _)| color)),
+            hits as f64 / self.time_samples as f64,
+        )
+    }
+
+    fn full_region(&self) -> (usize, usize, usize, usize) {
+        (0, 0, self.hsize, self.vsize)
+    }
+
+    fn render_rows<O: WorldObject>(
+        &self,
+        world: &World<O>,
+        integrator: &dyn Integrator<O>,
+        (x0, y0, x1, y1): (usize, usize, usize, usize),
+        with_alpha: bool,
+        progress: &dyn Fn(usize, usize),
+    ) -> Canvas {
+        let mut canvas = Canvas::make(x1 - x0, y1 - y0);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                // rays use the absolute pixel coordinates
+                let (color, coverage) = self.color_for_pixel(world, integrator, x, y, with_alpha);
+                canvas.write(x - x0, y - y0, color);
+                if with_alpha {
+                    canvas.write_alpha(x - x0, y - y0, coverage);
+                }
+            }
+            progress(y - y0 + 1, y1 - y0);
         }
         canvas
     }
+
+    // same as `render_with` but also fills the canvas alpha channel with the ray coverage,
+    // the colors are premultiplied by the alpha as expected by `Canvas::composite_over`
+    pub fn render_with_alpha<O: WorldObject>(
+        &self,
+        world: &World<O>,
+        integrator: &dyn Integrator<O>,
+    ) -> Canvas {
+        self.render_rows(world, integrator, self.full_region(), true, &|_, _| {})
+    }
 }
 
 #[cfg(test)]
 mod camera_tests {
     use crate::camera::Camera;
-    use crate::color::Color;
+    use crate::canvas::Canvas;
+    use crate::color::{Color, WHITE};
+    use crate::epsilon::EPSILON;
+    use crate::integrator::{NormalsIntegrator, WhittedIntegrator};
+    use crate::light::Light;
     use crate::matrix::Matrix;
+    use crate::sampler::{RandomSampler, SequenceSampler};
+    use crate::sphere::Sphere;
     use crate::transformation::*;
     use crate::tuple::*;
     use crate::world::World;
+    use std::cell::RefCell;
     use std::f32::consts::FRAC_PI_2;
-    use std::f64::consts::FRAC_PI_4;
+    use std::f64::consts::{FRAC_PI_4, PI};
 
     #[test]
     fn constructing_a_camera() {
@@ -105,6 +346,19 @@ mod camera_tests {
         assert_eq!(c.transform, Matrix::identity());
     }
 
+    #[test]
+    fn camera_getters_expose_its_settings() {
+        let c = Camera::new(160, 120, FRAC_PI_2 as f64);
+        assert_eq!(c.hsize(), 160);
+        assert_eq!(c.vsize(), 120);
+        assert_eq!(c.field_of_view(), FRAC_PI_2 as f64);
+        assert_eq!(c.transform(), &Matrix::identity());
+        assert_eq!(c.pixel_size(), c.pixel_size);
+        let transform = Matrix::translation(0.0, -2.0, 5.0);
+        let c = c.set_transform(transform.clone());
+        assert_eq!(c.transform(), &transform);
+    }
+
     #[test]
     fn pixel_size_horizontal_canvas() {
         let c = Camera::new(200, 125, FRAC_PI_2 as f64);
@@ -117,10 +371,50 @@ mod camera_tests {
         assert_eq!(c.pixel_size, 0.01000000043711391);
     }
 
+    #[test]
+    fn fitting_camera_to_a_unit_sphere() {
+        let w = World::empty()
+            .add_object(Box::new(
+                Sphere::new(1).set_transform(Matrix::translation(1.0, 2.0, 3.0)),
+            ))
+            .unwrap();
+        let c = Camera::new(100, 50, PI / 3.0);
+        let c = c.fit_to(&w, &vector(0.0, 0.0, -2.0), &vector(0.0, 1.0, 0.0));
+        // the unit box around the sphere has a radius of sqrt(3)
+        let distance = 3.0_f64.sqrt() / c.half_height.atan().sin();
+        let from = point(1.0, 2.0, 3.0 - distance);
+        let expected = view_transform(&from, &point(1.0, 2.0, 3.0), &vector(0.0, 1.0, 0.0));
+        assert_eq!(c.transform(), &expected);
+        // the middle of the canvas sees the sphere
+        let r = c.ray_for_pixel(50, 25, &mut RandomSampler);
+        assert!(tuples_are_equal(&r.origin, &from));
+        assert_eq!(w.intersect_with_ray(&r).len(), 2);
+        // the top and bottom rows miss the sphere
+        for px in 0..100 {
+            for py in [0, 49] {
+                let r = c.ray_for_pixel(px, py, &mut RandomSampler);
+                assert!(w.intersect_with_ray(&r).is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn fitting_camera_to_an_empty_world_keeps_its_transform() {
+        let transform = Matrix::translation(0.0, 0.0, -5.0);
+        let c = Camera::new(100, 50, PI / 3.0)
+            .set_transform(transform.clone())
+            .fit_to(
+                &World::empty(),
+                &vector(0.0, 0.0, -1.0),
+                &vector(0.0, 1.0, 0.0),
+            );
+        assert_eq!(c.transform(), &transform);
+    }
+
     #[test]
     fn ray_through_center_canvas_untransformed_one() {
         let c = Camera::new(201, 101, FRAC_PI_2 as f64);
-        let r = c.ray_for_pixel(100, 50);
+        let r = c.ray_for_pixel(100, 50, &mut RandomSampler);
         assert_eq!(r.origin, point(0.0, 0.0, 0.0));
         assert_eq!(
             r.direction,
@@ -131,7 +425,7 @@ mod camera_tests {
     #[test]
     fn ray_through_center_canvas_untransformed_two() {
         let c = Camera::new(201, 101, FRAC_PI_2 as f64);
-        let r = c.ray_for_pixel(0, 0);
+        let r = c.ray_for_pixel(0, 0, &mut RandomSampler);
         assert_eq!(r.origin, point(0.0, 0.0, 0.0));
         assert_eq!(
             r.direction,
@@ -144,7 +438,7 @@ mod camera_tests {
         let transformation =
             Matrix::rotate_y(FRAC_PI_4).multiply(&Matrix::translation(0.0, -2.0, 5.0));
         let c = Camera::new(201, 101, FRAC_PI_2 as f64).set_transform(transformation);
-        let r = c.ray_for_pixel(100, 50);
+        let r = c.ray_for_pixel(100, 50, &mut RandomSampler);
         assert_eq!(r.origin, point(0.0, 2.0, -5.0));
         assert_eq!(
             r.direction,
@@ -152,6 +446,39 @@ mod camera_tests {
         );
     }
 
+    #[test]
+    fn ray_through_center_with_closed_aperture_is_unchanged() {
+        let transformation =
+            Matrix::rotate_y(FRAC_PI_4).multiply(&Matrix::translation(0.0, -2.0, 5.0));
+        let pinhole = Camera::new(201, 101, FRAC_PI_2 as f64).set_transform(transformation.clone());
+        let lens = Camera::new(201, 101, FRAC_PI_2 as f64)
+            .set_transform(transformation)
+            .set_lens(0.0, 3.0);
+        let r1 = pinhole.ray_for_pixel(100, 50, &mut RandomSampler);
+        let r2 = lens.ray_for_pixel(100, 50, &mut RandomSampler);
+        assert_eq!(r1.origin, r2.origin);
+        assert_eq!(r1.direction, r2.direction);
+    }
+
+    #[test]
+    fn lens_rays_converge_on_the_focal_plane() {
+        let c = Camera::new(201, 101, FRAC_PI_2 as f64).set_lens(0.5, 4.0);
+        for _ in 0..10 {
+            let r = c.ray_for_pixel(100, 50, &mut RandomSampler);
+            // the origin lies on the lens disk
+            assert_eq!(r.origin.2, 0.0);
+            assert!(r.origin.0.powi(2) + r.origin.1.powi(2) <= 0.25_f64.powi(2));
+            // the ray reaches the pixel's focal point on the plane z = -4
+            let t = -4.0 / r.direction.2;
+            let focus = r.position_at(t);
+            assert!(focus.0.abs() < 1e-9);
+            assert!(focus.1.abs() < 1e-9);
+        }
+        // a quarter turn on the rim of the lens
+        let r = c.ray_for_pixel(100, 50, &mut SequenceSampler::new(vec![1.0, 0.25]));
+        assert!(tuples_are_equal(&r.origin, &point(0.0, 0.25, 0.0)));
+    }
+
     #[test]
     fn rendering_world_with_camera() {
         let w = World::default();
@@ -164,7 +491,181 @@ mod camera_tests {
         let color_at = canvas.color_at(5, 5);
         assert_eq!(
             color_at.unwrap(),
-            Color::make(0.380661169303951945, 0.4758264616299399, 0.2854958769779639)
+            Color::make(0.38066116930395194, 0.4758264616299399, 0.2854958769779639)
+        );
+    }
+
+    #[test]
+    fn rendering_raw_pixels_matches_canvas_content() {
+        let w = World::default();
+        let from = point(0.0, 0.0, -5.0);
+        let to = point(0.0, 0.0, 0.0);
+        let up = vector(0.0, 1.0, 0.0);
+        let c = Camera::new(7, 5, FRAC_PI_2 as f64).set_transform(view_transform(&from, &to, &up));
+        let raw = c.render_raw(&w);
+        assert_eq!(raw.len(), 7 * 5);
+        assert_eq!(raw, c.render(&w).content);
+        assert_eq!(raw, c.render_with(&w, &WhittedIntegrator).content);
+    }
+
+    #[test]
+    fn rendering_depth_buffer() {
+        let w = World::default();
+        let from = point(0.0, 0.0, -5.0);
+        let to = point(0.0, 0.0, 0.0);
+        let up = vector(0.0, 1.0, 0.0);
+        let c =
+            Camera::new(11, 11, FRAC_PI_2 as f64).set_transform(view_transform(&from, &to, &up));
+        let depth = c.render_depth(&w);
+        assert_eq!(depth.len(), 11 * 11);
+        // the center pixel hits the outer sphere of radius 1 straight ahead
+        assert!((depth[5 + 5 * 11] - 4.0).abs() < EPSILON);
+        // the corner pixel misses everything
+        assert_eq!(depth[0], f64::INFINITY);
+    }
+
+    #[test]
+    fn rendering_with_stats_counts_the_work() {
+        let w = World::default();
+        let from = point(0.0, 0.0, -5.0);
+        let to = point(0.0, 0.0, 0.0);
+        let up = vector(0.0, 1.0, 0.0);
+        let c = Camera::new(7, 5, FRAC_PI_2 as f64).set_transform(view_transform(&from, &to, &up));
+        let (canvas, stats) = c.render_with_stats(&w);
+        assert_eq!(canvas, c.render(&w));
+        assert_eq!(stats.rays, 7 * 5);
+        assert!(stats.intersections > 0);
+        assert!(stats.shadow_rays > 0);
+        // the counters start over for each render
+        assert_eq!(c.render_with_stats(&w).1, stats);
+    }
+
+    #[test]
+    fn rendering_reports_progress_after_each_row() {
+        let w = World::default();
+        let c = Camera::new(4, 3, FRAC_PI_2 as f64)
+            .set_transform(Matrix::translation(0.0, 0.0, -5.0).inverse());
+        let calls = RefCell::new(Vec::new());
+        let canvas =
+            c.render_with_progress(&w, |done, total| calls.borrow_mut().push((done, total)));
+        assert_eq!(calls.into_inner(), vec![(1, 3), (2, 3), (3, 3)]);
+        assert_eq!(canvas, c.render(&w));
+    }
+
+    #[test]
+    fn rendering_region_matches_full_render() {
+        let w = World::default();
+        let from = point(0.0, 0.0, -5.0);
+        let to = point(0.0, 0.0, 0.0);
+        let up = vector(0.0, 1.0, 0.0);
+        let c =
+            Camera::new(11, 11, FRAC_PI_2 as f64).set_transform(view_transform(&from, &to, &up));
+        let full = c.render(&w);
+        let region = c.render_region(&w, 4, 4, 7, 7);
+        assert_eq!(region.width, 3);
+        assert_eq!(region.height, 3);
+        assert_eq!(region.color_at(1, 1), full.color_at(5, 5));
+        assert_eq!(region.color_at(0, 2), full.color_at(4, 6));
+        // clamped to the camera's canvas
+        let corner = c.render_region(&w, 9, 9, 20, 20);
+        assert_eq!(corner.width, 2);
+        assert_eq!(corner.color_at(1, 1), full.color_at(10, 10));
+    }
+
+    #[test]
+    fn motion_blur_without_motion_matches_static_render() {
+        let transform = Matrix::translation(0.5, 0.0, 0.0);
+        let light = || Light::point_light(point(-10.0, 10.0, -10.0), Color::make(1.0, 1.0, 1.0));
+        let still = World::new(
+            vec![light()],
+            vec![Box::new(Sphere::new(1).set_transform(transform.clone()))],
+        );
+        let moving = World::new(
+            vec![light()],
+            vec![Box::new(
+                Sphere::new(1)
+                    .set_transform(transform.clone())
+                    .set_motion(transform),
+            )],
+        );
+        let from = point(0.0, 0.0, -5.0);
+        let c = Camera::new(11, 11, FRAC_PI_2 as f64).set_transform(view_transform(
+            &from,
+            &point_zero(),
+            &vector(0.0, 1.0, 0.0),
+        ));
+        let expected = c.render(&still);
+        assert_eq!(c.render(&moving), expected);
+        assert_eq!(c.set_motion_blur(4).render(&moving), expected);
+    }
+
+    #[test]
+    fn rendering_world_with_normals_integrator() {
+        let w = World::default();
+        let from = point(0.0, 0.0, -5.0);
+        let to = point(0.0, 0.0, 0.0);
+        let up = vector(0.0, 1.0, 0.0);
+        let c =
+            Camera::new(11, 11, FRAC_PI_2 as f64).set_transform(view_transform(&from, &to, &up));
+        let canvas = c.render_with(&w, &NormalsIntegrator);
+        let color_at = canvas.color_at(5, 5);
+        assert_eq!(color_at.unwrap(), Color::make(0.5, 0.5, 0.0));
+    }
+
+    #[test]
+    fn rendering_with_alpha_marks_hits_and_misses() {
+        let w = World::default();
+        let from = point(0.0, 0.0, -5.0);
+        let to = point(0.0, 0.0, 0.0);
+        let up = vector(0.0, 1.0, 0.0);
+        let c =
+            Camera::new(11, 11, FRAC_PI_2 as f64).set_transform(view_transform(&from, &to, &up));
+        let canvas = c.render_with_alpha(&w, &WhittedIntegrator);
+        // the center pixel sees the sphere
+        assert_eq!(canvas.alpha_at(5, 5), Some(1.0));
+        // the corner pixel misses everything
+        assert_eq!(canvas.alpha_at(0, 0), Some(0.0));
+    }
+
+    #[test]
+    fn rendering_with_alpha_averages_the_coverage_of_the_samples() {
+        // the sphere only covers the center pixel during the first tenth of the exposure
+        let w = World::empty()
+            .set_light(Light::point_light(point(-10.0, 10.0, -10.0), WHITE))
+            .add_object(Box::new(
+                Sphere::new(1).set_motion(Matrix::translation(10.0, 0.0, 0.0)),
+            ))
+            .unwrap();
+        let from = point(0.0, 0.0, -5.0);
+        let to = point(0.0, 0.0, 0.0);
+        let up = vector(0.0, 1.0, 0.0);
+        let c = Camera::new(11, 11, FRAC_PI_2 as f64)
+            .set_transform(view_transform(&from, &to, &up))
+            .set_motion_blur(200);
+        let canvas = c.render_with_alpha(&w, &WhittedIntegrator);
+        let alpha = canvas.alpha_at(5, 5).unwrap();
+        assert!(alpha > 0.0 && alpha < 0.5, "{}", alpha);
+        assert_eq!(canvas.alpha_at(0, 0), Some(0.0));
+    }
+
+    #[test]
+    fn compositing_render_with_background_shows_the_lower_layer_on_misses() {
+        let w = World::default().set_background(Color::make(0.9, 0.1, 0.1));
+        let from = point(0.0, 0.0, -5.0);
+        let to = point(0.0, 0.0, 0.0);
+        let up = vector(0.0, 1.0, 0.0);
+        let c =
+            Camera::new(11, 11, FRAC_PI_2 as f64).set_transform(view_transform(&from, &to, &up));
+        let layer = c.render_with_alpha(&w, &WhittedIntegrator);
+        // the background is left out of the premultiplied colors
+        assert_eq!(layer.color_at(0, 0), Some(Color::default()));
+        assert_eq!(
+            c.render(&w).color_at(0, 0),
+            Some(Color::make(0.9, 0.1, 0.1))
         );
+        let lower = Canvas::make_with_color(11, 11, Color::make(0.0, 0.0, 1.0));
+        let result = layer.composite_over(&lower);
+        assert_eq!(result.color_at(0, 0), Some(Color::make(0.0, 0.0, 1.0)));
+        assert_eq!(result.color_at(5, 5), c.render(&w).color_at(5, 5));
     }
 }
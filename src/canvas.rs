@@ -1,11 +1,19 @@
 use crate::color::Color;
+use image::RgbImage;
 use std::fs::File;
-use std::io::{Result, Write};
+use std::io::{BufWriter, Error, ErrorKind, Result, Write};
 
+// some PPM readers reject longer lines
+const PPM_MAX_LINE_SIZE: usize = 70;
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Canvas {
     pub width: usize,
     pub height: usize,
     pub content: Vec<Color>,
+    // optional coverage channel, 1.0 where a ray hit geometry and 0.0 where it missed
+    pub alpha: Option<Vec<f64>>,
 }
 
 impl Canvas {
@@ -14,6 +22,7 @@ impl Canvas {
             width,
             height,
             content: [Color::default()].repeat(width * height),
+            alpha: None,
         }
     }
 
@@ -22,61 +31,200 @@ impl Canvas {
             width,
             height,
             content: [color].repeat(width * height),
+            alpha: None,
+        }
+    }
+
+    // writes outside of the canvas are ignored, returns whether the pixel was written
+    pub fn write(&mut self, x: usize, y: usize, color: Color) -> bool {
+        if x >= self.width || y >= self.height {
+            false
+        } else {
+            self.content[x + y * self.width] = color;
+            true
+        }
+    }
+
+    // the alpha channel is created fully opaque on first write, writes outside of the canvas are ignored
+    pub fn write_alpha(&mut self, x: usize, y: usize, alpha: f64) {
+        if x >= self.width || y >= self.height {
+            return;
         }
+        let size = self.width * self.height;
+        let channel = self.alpha.get_or_insert_with(|| [1.0].repeat(size));
+        channel[x + y * self.width] = alpha;
     }
 
-    pub fn write(&mut self, x: usize, y: usize, color: Color) {
-        self.content.push(color);
-        self.content.swap_remove(x + y * self.width);
+    // a canvas without alpha channel is considered opaque
+    pub fn alpha_at(&self, x: usize, y: usize) -> Option<f64> {
+        let index = x + y * self.width;
+        if index >= self.content.len() {
+            None
+        } else {
+            match &self.alpha {
+                None => Some(1.0),
+                Some(channel) => channel.get(index).copied(),
+            }
+        }
+    }
+
+    // Porter-Duff "over" operator with premultiplied colors: the rendered colors are already
+    // black where rays missed, so the background shows through proportionally to 1 - alpha.
+    pub fn composite_over(&self, background: &Canvas) -> Canvas {
+        let mut result = Canvas::make(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = x + y * self.width;
+                let alpha = self.alpha_at(x, y).unwrap();
+                let under = background.content.get(index).copied().unwrap_or_default();
+                let color = self.content[index].add(&under.multiply_value(1.0 - alpha));
+                result.write(x, y, color);
+                let under_alpha = background.alpha_at(x, y).unwrap_or(0.0);
+                result.write_alpha(x, y, alpha + under_alpha * (1.0 - alpha));
+            }
+        }
+        result
+    }
+
+    // returns None for coordinates outside of the canvas
+    pub fn color_at(&self, x: usize, y: usize) -> Option<Color> {
+        if x >= self.width || y >= self.height {
+            None
+        } else {
+            self.content.get(x + y * self.width).copied()
+        }
     }
 
-    pub fn color_at(self, x: usize, y: usize) -> Option<Color> {
-        self.content.get(x + y * self.width).copied()
+    // Parses a plain P3 PPM, the channels are scaled back into 0..1 using the declared maximum.
+    // Values can be split over any number of lines and everything following a `#` is a comment.
+    pub fn from_ppm(input: &str) -> Result<Canvas> {
+        let invalid = |message: &str| Error::new(ErrorKind::InvalidData, message.to_string());
+        let mut tokens = input
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default())
+            .flat_map(|line| line.split_whitespace());
+        if tokens.next() != Some("P3") {
+            return Err(invalid("missing P3 magic number"));
+        }
+        let mut header = || -> Result<usize> {
+            tokens
+                .next()
+                .and_then(|t| t.parse().ok())
+                .ok_or_else(|| invalid("invalid PPM header"))
+        };
+        let width = header()?;
+        let height = header()?;
+        let max = header()? as f64;
+        let values = tokens
+            .map(|t| t.parse::<f64>().map(|v| v / max))
+            .collect::<std::result::Result<Vec<f64>, _>>()
+            .map_err(|_| invalid("invalid PPM pixel value"))?;
+        if values.len() != width * height * 3 {
+            return Err(invalid("PPM pixel count does not match its dimensions"));
+        }
+        let content = values
+            .chunks(3)
+            .map(|rgb| Color::make(rgb[0], rgb[1], rgb[2]))
+            .collect();
+        Ok(Canvas {
+            width,
+            height,
+            content,
+            alpha: None,
+        })
     }
 
     pub fn to_ppm(&self) -> String {
-        let first_magic_line = "P3";
-        let second_dim = format!("{} {}", self.width, self.height);
-        let color_scale = "255";
-        let header = format!("{}\n{}\n{}", first_magic_line, second_dim, color_scale);
-        // Initiate with very bold approximate size
-        let mut content_lines: String = String::with_capacity(self.width * self.width);
-        self.content
-            .chunks(self.width) // chunk by pixel line
-            .for_each(|l| {
-                l.iter().fold(0, |current_line_size, c| {
-                    let raw_scaled_color = c.raw_scale(255);
-                    let raw_scaled_color_len = raw_scaled_color.chars().count();
-                    if current_line_size == 0 {
-                        // first line
-                        content_lines.push_str(&raw_scaled_color);
-                        raw_scaled_color_len
-                    } else {
-                        let next_line_size = current_line_size + raw_scaled_color_len + 1;
-                        if next_line_size <= 69 {
-                            // continue line
-                            content_lines.push(' ');
-                            content_lines.push_str(&raw_scaled_color);
-                            next_line_size
+        // at most 12 bytes per pixel for "255 255 255 "
+        let mut bytes = Vec::with_capacity(self.width * self.height * 12 + 20);
+        self.write_ppm(&mut bytes)
+            .expect("writing to a Vec never fails");
+        String::from_utf8(bytes).expect("PPM output is ASCII")
+    }
+
+    // streams the P3 output one pixel line at a time instead of building it in memory,
+    // each pixel line starts a new line and long ones are wrapped between two channel values
+    // so that no line exceeds 70 characters
+    pub fn write_ppm<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write!(writer, "P3\n{} {}\n255\n", self.width, self.height)?;
+        let mut line = String::with_capacity(PPM_MAX_LINE_SIZE);
+        for row in self.content.chunks(self.width) {
+            line.clear();
+            for c in row {
+                let scaled = c.scale(255);
+                for value in [scaled.red, scaled.green, scaled.blue] {
+                    let value = value.to_string();
+                    if !line.is_empty() {
+                        if line.len() + 1 + value.len() <= PPM_MAX_LINE_SIZE {
+                            line.push(' ');
                         } else {
-                            // new line
-                            content_lines.push('\n');
-                            content_lines.push_str(&raw_scaled_color);
-                            raw_scaled_color_len
+                            writer.write_all(line.as_bytes())?;
+                            writer.write_all(b"\n")?;
+                            line.clear();
                         }
                     }
-                });
-                // separate lines
-                content_lines.push('\n');
-            });
+                    line.push_str(&value);
+                }
+            }
+            writer.write_all(line.as_bytes())?;
+            writer.write_all(b"\n")?;
+        }
+        writer.write_all(b"\n ")
+    }
+
+    // the colors are gamma encoded before being scaled, see `Color::gamma`
+    pub fn to_ppm_gamma(&self, gamma: f64) -> String {
+        self.map_colors(|c| c.gamma(gamma)).to_ppm()
+    }
 
-        format!("{}\n{}\n ", header, content_lines)
+    // the colors are tone mapped instead of clamped, see `Color::reinhard`
+    pub fn to_ppm_tonemapped(&self) -> String {
+        self.map_colors(Color::reinhard).to_ppm()
+    }
+
+    fn map_colors(&self, f: impl Fn(Color) -> Color) -> Canvas {
+        Canvas {
+            width: self.width,
+            height: self.height,
+            content: self.content.iter().map(|c| f(*c)).collect(),
+            alpha: None,
+        }
+    }
+
+    // binary PPM: same header as P3 followed by one byte per channel
+    pub fn to_ppm_binary(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.width * self.height * 3 + 20);
+        self.write_ppm_binary(&mut bytes)
+            .expect("writing to a Vec never fails");
+        bytes
+    }
+
+    pub fn save_file_binary(&self, filename: &str) -> Result<()> {
+        let mut output = BufWriter::new(File::create(filename)?);
+        self.write_ppm_binary(&mut output)?;
+        output.flush()
+    }
+
+    fn write_ppm_binary<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+        for color in &self.content {
+            writer.write_all(&color.to_rgb8())?;
+        }
+        Ok(())
+    }
+
+    pub fn save_png(&self, filename: &str) -> Result<()> {
+        // row-major RGB buffer following the `x + y * width` layout of the content
+        let buffer: Vec<u8> = self.content.iter().flat_map(|c| c.to_rgb8()).collect();
+        let image = RgbImage::from_raw(self.width as u32, self.height as u32, buffer)
+            .expect("buffer matches the canvas dimensions");
+        image.save(filename).map_err(Error::other)
     }
 
     pub fn save_file(self, filename: &str) -> Result<()> {
-        let mut output = File::create(filename)?;
-        let ppm = self.to_ppm();
-        output.write(ppm.as_bytes()).map(|_| ())
+        let mut output = BufWriter::new(File::create(filename)?);
+        self.write_ppm(&mut output)?;
+        output.flush()
     }
 }
 
@@ -102,6 +250,33 @@ mod tuple_tests {
         assert_eq!(canvas.color_at(2, 3), Some(color_red));
     }
 
+    #[test]
+    fn write_outside_canvas_is_ignored() {
+        let mut canvas = Canvas::make(10, 20);
+        let color_red = Color::make(1.0, 0.0, 0.0);
+        assert!(!canvas.write(10, 0, color_red));
+        assert!(!canvas.write(0, 20, color_red));
+        // pixel zero and the first pixel of the next row are untouched
+        assert_eq!(canvas.color_at(0, 0), Some(Color::default()));
+        assert_eq!(canvas.color_at(0, 1), Some(Color::default()));
+        assert!(canvas.content.iter().all(|&c| c == Color::default()));
+        assert!(canvas.write(9, 19, color_red));
+        assert_eq!(canvas.color_at(9, 19), Some(color_red));
+    }
+
+    #[test]
+    fn read_several_colors_from_same_canvas() {
+        let mut canvas = Canvas::make(10, 20);
+        let color_red = Color::make(1.0, 0.0, 0.0);
+        let color_blue = Color::make(0.0, 0.0, 1.0);
+        canvas.write(2, 3, color_red);
+        canvas.write(9, 19, color_blue);
+        assert_eq!(canvas.color_at(2, 3), Some(color_red));
+        assert_eq!(canvas.color_at(9, 19), Some(color_blue));
+        assert_eq!(canvas.color_at(10, 0), None);
+        assert_eq!(canvas.color_at(0, 20), None);
+    }
+
     #[test]
     fn valid_ppm() {
         let mut canvas = Canvas::make(5, 3);
@@ -121,6 +296,111 @@ mod tuple_tests {
         assert_eq!(ppm_lines.next(), Some("0 0 0 0 0 0 0 0 0 0 0 0 0 0 255"));
     }
 
+    #[test]
+    fn ppm_with_gamma_correction() {
+        let mut canvas = Canvas::make(2, 1);
+        canvas.write(0, 0, Color::make(0.25, 1.5, 0.0));
+        canvas.write(1, 0, Color::make(0.5, 0.5, 0.5));
+        assert_eq!(canvas.to_ppm_gamma(1.0), canvas.to_ppm());
+        let ppm = canvas.to_ppm_gamma(2.0);
+        assert_eq!(ppm.lines().nth(3), Some("128 255 0 180 180 180"));
+    }
+
+    #[test]
+    fn ppm_with_tone_mapping() {
+        let mut canvas = Canvas::make(2, 1);
+        canvas.write(0, 0, Color::make(4.0, 0.0, 1.0));
+        canvas.write(1, 0, Color::make(1.5, 0.25, 0.0));
+        assert_eq!(canvas.to_ppm().lines().nth(3), Some("255 0 255 255 64 0"));
+        assert_eq!(
+            canvas.to_ppm_tonemapped().lines().nth(3),
+            Some("204 0 128 153 51 0")
+        );
+    }
+
+    #[test]
+    fn ppm_round_trip() {
+        let mut canvas = Canvas::make(3, 2);
+        canvas.write(0, 0, Color::make(1.0, 0.0, 0.2));
+        canvas.write(2, 0, Color::make(0.0, 1.0, 0.0));
+        canvas.write(1, 1, Color::make(0.2, 0.4, 1.0));
+        let parsed = Canvas::from_ppm(&canvas.to_ppm()).unwrap();
+        assert_eq!(parsed, canvas);
+    }
+
+    #[test]
+    fn reading_pixel_data_from_ppm() {
+        let ppm = "P3
+4 3
+255
+255 127 0  0 127 255  127 255 0  255 255 255
+0 0 0  255 0 0  0 255 0  0 0 255
+255 255 0  0 255 255  255 0 255  127 127 127";
+        let canvas = Canvas::from_ppm(ppm).unwrap();
+        assert_eq!(canvas.width, 4);
+        assert_eq!(canvas.height, 3);
+        let half = 127.0 / 255.0;
+        let cases = vec![
+            (0, 0, Color::make(1.0, half, 0.0)),
+            (1, 0, Color::make(0.0, half, 1.0)),
+            (2, 0, Color::make(half, 1.0, 0.0)),
+            (3, 0, Color::make(1.0, 1.0, 1.0)),
+            (0, 1, Color::make(0.0, 0.0, 0.0)),
+            (1, 1, Color::make(1.0, 0.0, 0.0)),
+            (2, 1, Color::make(0.0, 1.0, 0.0)),
+            (3, 1, Color::make(0.0, 0.0, 1.0)),
+            (0, 2, Color::make(1.0, 1.0, 0.0)),
+            (1, 2, Color::make(0.0, 1.0, 1.0)),
+            (2, 2, Color::make(1.0, 0.0, 1.0)),
+            (3, 2, Color::make(half, half, half)),
+        ];
+        for (x, y, color) in cases {
+            assert_eq!(canvas.color_at(x, y), Some(color));
+        }
+    }
+
+    #[test]
+    fn reading_ppm_with_comments_and_split_triples() {
+        let ppm = "P3
+# this is a comment
+2 1
+# this, too
+255
+# another comment
+51 153
+# oh, no, comments in the pixel data!
+204 255 # the end of a line can be a comment too
+255
+255";
+        let canvas = Canvas::from_ppm(ppm).unwrap();
+        assert_eq!(canvas.color_at(0, 0), Some(Color::make(0.2, 0.6, 0.8)));
+        assert_eq!(canvas.color_at(1, 0), Some(Color::make(1.0, 1.0, 1.0)));
+    }
+
+    #[test]
+    fn reading_ppm_scales_by_declared_maximum() {
+        let ppm = "P3\n2 2\n100\n100 100 100  50 50 50\n75 50 25  0 0 0\n";
+        let canvas = Canvas::from_ppm(ppm).unwrap();
+        assert_eq!(canvas.color_at(0, 0), Some(Color::make(1.0, 1.0, 1.0)));
+        assert_eq!(canvas.color_at(1, 0), Some(Color::make(0.5, 0.5, 0.5)));
+        assert_eq!(canvas.color_at(0, 1), Some(Color::make(0.75, 0.5, 0.25)));
+    }
+
+    #[test]
+    fn ppm_written_with_wrapped_lines_can_be_read_back() {
+        let canvas = Canvas::make_with_color(10, 2, Color::make(1.0, 0.8, 0.6));
+        let ppm = canvas.to_ppm();
+        assert!(ppm.lines().all(|l| l.len() <= 70));
+        assert_eq!(Canvas::from_ppm(&ppm).unwrap(), canvas);
+    }
+
+    #[test]
+    fn reading_invalid_ppm_fails() {
+        assert!(Canvas::from_ppm("P32\n1 1\n255\n0 0 0").is_err());
+        assert!(Canvas::from_ppm("P3\n1 1\n255\n0 0").is_err());
+        assert!(Canvas::from_ppm("P3\n1 x\n255\n0 0 0").is_err());
+    }
+
     #[test]
     fn ppm_ends_with_new_line() {
         let mut canvas = Canvas::make(5, 3);
@@ -135,6 +415,49 @@ mod tuple_tests {
         assert_eq!(ppm_lines.last(), Some(" "));
     }
 
+    #[test]
+    fn valid_binary_ppm() {
+        let mut canvas = Canvas::make(5, 3);
+        canvas.write(0, 0, Color::make(1.5, 0.5, -0.5));
+        canvas.write(1, 0, Color::make(0.0, 0.0, 1.0));
+        let ppm = canvas.to_ppm_binary();
+        let header = b"P6\n5 3\n255\n";
+        assert_eq!(&ppm[..header.len()], header);
+        assert_eq!(
+            &ppm[header.len()..header.len() + 6],
+            &[255, 128, 0, 0, 0, 255]
+        );
+        assert_eq!(ppm.len(), header.len() + 5 * 3 * 3);
+    }
+
+    #[test]
+    fn save_png_round_trip() {
+        let mut canvas = Canvas::make(2, 2);
+        canvas.write(1, 0, Color::make(1.0, 0.5, 0.0));
+        canvas.write(0, 1, Color::make(0.0, 0.0, 2.0));
+        let path = std::env::temp_dir().join("ray-tracer-canvas-test.png");
+        let filename = path.to_str().unwrap();
+        canvas.save_png(filename).unwrap();
+        let image = image::open(filename).unwrap().to_rgb8();
+        std::fs::remove_file(filename).unwrap();
+        assert_eq!(image.dimensions(), (2, 2));
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0]);
+        assert_eq!(image.get_pixel(1, 0).0, [255, 128, 0]);
+        assert_eq!(image.get_pixel(0, 1).0, [0, 0, 255]);
+    }
+
+    #[test]
+    fn streaming_ppm_matches_in_memory_ppm() {
+        let mut canvas = Canvas::make(13, 4);
+        canvas.write(0, 0, Color::make(1.5, 0.0, 0.0));
+        canvas.write(12, 1, Color::make(0.0, 0.5, 0.0));
+        canvas.write(4, 3, Color::make(0.2, 0.4, 1.0));
+        let mut buffer: Vec<u8> = Vec::new();
+        canvas.write_ppm(&mut buffer).unwrap();
+        assert_eq!(buffer, canvas.to_ppm().into_bytes());
+        assert_eq!(Canvas::from_ppm(&canvas.to_ppm()).unwrap().width, 13);
+    }
+
     #[test]
     fn ppm_has_max_line_size() {
         let c1 = Color::make(1.0, 0.8, 0.6);
@@ -144,21 +467,66 @@ mod tuple_tests {
         assert_eq!(ppm_lines.next(), Some("P3"));
         assert_eq!(ppm_lines.next(), Some("10 2"));
         assert_eq!(ppm_lines.next(), Some("255"));
-        assert_eq!(
-            ppm_lines.next(),
-            Some("255 204 153 255 204 153 255 204 153 255 204 153 255 204 153")
-        );
-        assert_eq!(
-            ppm_lines.next(),
-            Some("255 204 153 255 204 153 255 204 153 255 204 153 255 204 153")
-        );
-        assert_eq!(
-            ppm_lines.next(),
-            Some("255 204 153 255 204 153 255 204 153 255 204 153 255 204 153")
-        );
-        assert_eq!(
-            ppm_lines.next(),
-            Some("255 204 153 255 204 153 255 204 153 255 204 153 255 204 153")
-        );
+        for _ in 0..2 {
+            assert_eq!(
+                ppm_lines.next(),
+                Some("255 204 153 255 204 153 255 204 153 255 204 153 255 204 153 255 204")
+            );
+            assert_eq!(
+                ppm_lines.next(),
+                Some("153 255 204 153 255 204 153 255 204 153 255 204 153")
+            );
+        }
+    }
+
+    #[test]
+    fn wide_ppm_lines_are_wrapped_between_channel_values() {
+        let mut canvas = Canvas::make(57, 3);
+        for y in 0..3 {
+            for x in 0..57 {
+                let value = |offset: usize| ((x * 7 + y * 31 + offset) % 256) as f64 / 255.0;
+                canvas.write(x, y, Color::make(value(0), value(100), value(200)));
+            }
+        }
+        let ppm = canvas.to_ppm();
+        assert!(ppm.lines().all(|l| l.len() <= 70));
+        // lines are filled up before wrapping
+        assert!(ppm.lines().skip(3).any(|l| l.len() > 66));
+        let back = Canvas::from_ppm(&ppm).unwrap();
+        assert_eq!(back, canvas);
+        assert_eq!(back.to_ppm(), ppm);
+    }
+
+    #[test]
+    fn canvas_without_alpha_channel_is_opaque() {
+        let canvas = Canvas::make(2, 2);
+        assert!(canvas.alpha.is_none());
+        assert_eq!(canvas.alpha_at(1, 1), Some(1.0));
+        assert_eq!(canvas.alpha_at(2, 1), None);
+    }
+
+    #[test]
+    fn compositing_respects_alpha() {
+        let mut layer = Canvas::make(2, 1);
+        let red = Color::make(1.0, 0.0, 0.0);
+        layer.write(0, 0, red);
+        layer.write_alpha(0, 0, 1.0);
+        layer.write_alpha(1, 0, 0.0);
+        let background = Canvas::make_with_color(2, 1, Color::make(0.0, 0.0, 1.0));
+        let result = layer.composite_over(&background);
+        assert_eq!(result.content[0], red);
+        assert_eq!(result.content[1], Color::make(0.0, 0.0, 1.0));
+        assert_eq!(result.alpha_at(0, 0), Some(1.0));
+        assert_eq!(result.alpha_at(1, 0), Some(1.0));
+    }
+
+    #[test]
+    fn compositing_half_transparent_pixel_blends() {
+        let mut layer = Canvas::make(1, 1);
+        layer.write(0, 0, Color::make(0.5, 0.0, 0.0));
+        layer.write_alpha(0, 0, 0.5);
+        let background = Canvas::make_with_color(1, 1, Color::make(0.0, 0.0, 1.0));
+        let result = layer.composite_over(&background);
+        assert_eq!(result.content[0], Color::make(0.5, 0.0, 0.5));
     }
 }
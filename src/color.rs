@@ -1,6 +1,13 @@
+use crate::epsilon::EPSILON;
 use crate::tuple::Tuple;
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+// equality is approximate, see `near_eq`
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "[f64; 3]", into = "[f64; 3]")
+)]
 pub struct Color {
     pub red: f64,
     pub green: f64,
@@ -47,9 +54,35 @@ pub const YELLOW: Color = Color {
     green: 1.,
     blue: 0.,
 };
+// MAGENTA and CYAN are the same as FUCHSIA and AQUA
+pub const MAGENTA: Color = Color {
+    red: 1.,
+    green: 0.,
+    blue: 1.,
+};
+pub const CYAN: Color = Color {
+    red: 0.,
+    green: 1.,
+    blue: 1.,
+};
+pub const ORANGE: Color = Color {
+    red: 1.,
+    green: 0.5,
+    blue: 0.,
+};
+pub const PURPLE: Color = Color {
+    red: 0.5,
+    green: 0.,
+    blue: 0.5,
+};
+pub const GRAY: Color = Color {
+    red: 0.5,
+    green: 0.5,
+    blue: 0.5,
+};
 
 impl Color {
-    pub fn make(r: f64, g: f64, b: f64) -> Self {
+    pub const fn make(r: f64, g: f64, b: f64) -> Self {
         Color {
             red: r,
             green: g,
@@ -75,6 +108,108 @@ impl Color {
         }
     }
 
+    // channels limited to 0..1
+    pub fn clamp(self) -> Color {
+        Color {
+            red: self.red.clamp(0.0, 1.0),
+            green: self.green.clamp(0.0, 1.0),
+            blue: self.blue.clamp(0.0, 1.0),
+        }
+    }
+
+    // gamma encoding, 2.2 suits most displays, negative channels become black
+    pub fn gamma(self, gamma: f64) -> Color {
+        let encode = |value: f64| value.max(0.0).powf(1.0 / gamma);
+        Color {
+            red: encode(self.red),
+            green: encode(self.green),
+            blue: encode(self.blue),
+        }
+    }
+
+    // Reinhard tone mapping c / (1 + c), bright channels approach 1 instead of being clipped
+    pub fn reinhard(self) -> Color {
+        let map = |value: f64| {
+            let value = value.max(0.0);
+            value / (1.0 + value)
+        };
+        Color {
+            red: map(self.red),
+            green: map(self.green),
+            blue: map(self.blue),
+        }
+    }
+
+    // 8 bits per channel representation clamped to 0..255
+    pub fn to_rgb8(self) -> [u8; 3] {
+        let scaled = self.scale(255);
+        [scaled.red as u8, scaled.green as u8, scaled.blue as u8]
+    }
+
+    // sum of the samples divided by their count, computed in f64 without clamping, black without samples
+    pub fn average(samples: impl IntoIterator<Item = Color>) -> Color {
+        let (sum, count) = samples
+            .into_iter()
+            .fold((BLACK, 0), |(sum, count), c| (sum.add(&c), count + 1));
+        if count == 0 {
+            return BLACK;
+        }
+        let count = count as f64;
+        Color::make(sum.red / count, sum.green / count, sum.blue / count)
+    }
+
+    // hue in degrees within 0..360, saturation and value within 0..1 for channels within 0..1
+    pub fn to_hsv(self) -> (f64, f64, f64) {
+        let max = self.red.max(self.green).max(self.blue);
+        let min = self.red.min(self.green).min(self.blue);
+        let delta = max - min;
+        let hue = if delta == 0.0 {
+            0.0
+        } else if max == self.red {
+            60.0 * ((self.green - self.blue) / delta).rem_euclid(6.0)
+        } else if max == self.green {
+            60.0 * ((self.blue - self.red) / delta + 2.0)
+        } else {
+            60.0 * ((self.red - self.green) / delta + 4.0)
+        };
+        let saturation = if max == 0.0 { 0.0 } else { delta / max };
+        (hue, saturation, max)
+    }
+
+    // the hue wraps around every 360 degrees
+    pub fn from_hsv(hue: f64, saturation: f64, value: f64) -> Color {
+        let chroma = value * saturation;
+        let sector = hue.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+        let (r, g, b) = match sector as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = value - chroma;
+        Color::make(r + m, g + m, b + m)
+    }
+
+    // interpolates in HSV space, the hue takes the shortest way around the color wheel
+    pub fn lerp_hsv(self, other: &Color, t: f64) -> Color {
+        let (h1, s1, v1) = self.to_hsv();
+        let (h2, s2, v2) = other.to_hsv();
+        let mut hue_distance = h2 - h1;
+        if hue_distance > 180.0 {
+            hue_distance -= 360.0;
+        } else if hue_distance < -180.0 {
+            hue_distance += 360.0;
+        }
+        Color::from_hsv(
+            h1 + hue_distance * t,
+            s1 + (s2 - s1) * t,
+            v1 + (v2 - v1) * t,
+        )
+    }
+
     fn scale_value(value: f64, scale: f64) -> f64 {
         if value <= 0.0 {
             0.0
@@ -85,6 +220,14 @@ impl Color {
         }
     }
 
+    // channels within EPSILON of each other, absorbs floating point noise from the rendering
+    pub fn near_eq(&self, other: &Color) -> bool {
+        (self.red - other.red).abs() < EPSILON
+            && (self.green - other.green).abs() < EPSILON
+            && (self.blue - other.blue).abs() < EPSILON
+    }
+
+    #[allow(clippy::should_implement_trait)]
     pub fn add(self, c: &Color) -> Color {
         Color {
             red: self.red + c.red,
@@ -128,6 +271,24 @@ impl From<Tuple> for Color {
     }
 }
 
+impl From<[f64; 3]> for Color {
+    fn from([red, green, blue]: [f64; 3]) -> Self {
+        Color { red, green, blue }
+    }
+}
+
+impl From<Color> for [f64; 3] {
+    fn from(c: Color) -> Self {
+        [c.red, c.green, c.blue]
+    }
+}
+
+impl PartialEq for Color {
+    fn eq(&self, other: &Self) -> bool {
+        self.near_eq(other)
+    }
+}
+
 impl Default for Color {
     fn default() -> Self {
         BLACK
@@ -138,6 +299,17 @@ impl Default for Color {
 mod color_tests {
     use crate::color::*;
 
+    #[test]
+    fn named_colors() {
+        assert_eq!(WHITE, Color::make(1.0, 1.0, 1.0));
+        assert_eq!(BLACK, Color::default());
+        assert_eq!(MAGENTA, FUCHSIA);
+        assert_eq!(CYAN, AQUA);
+        assert_eq!(GRAY, Color::make(0.5, 0.5, 0.5));
+        const DARK_GRAY: Color = Color::make(0.25, 0.25, 0.25);
+        assert_eq!(DARK_GRAY.red, 0.25);
+    }
+
     #[test]
     fn created_from_tuple() {
         let t = (0.5, 0.4, 1.7, 0.0);
@@ -146,4 +318,91 @@ mod color_tests {
         assert_eq!(c.green, 0.4);
         assert_eq!(c.blue, 1.7);
     }
+
+    #[test]
+    fn clamping_limits_channels_to_unit_range() {
+        assert_eq!(
+            Color::make(1.5, -0.2, 0.4).clamp(),
+            Color::make(1.0, 0.0, 0.4)
+        );
+        assert_eq!(WHITE.clamp(), WHITE);
+        assert_eq!(BLACK.clamp(), BLACK);
+    }
+
+    #[test]
+    fn gamma_correction() {
+        let c = Color::make(0.25, 0.5, 1.0);
+        assert_eq!(c.gamma(1.0), c);
+        assert_eq!(c.gamma(2.0), Color::make(0.5, 0.5_f64.sqrt(), 1.0));
+        assert_eq!(Color::make(-0.5, 0.0, 0.0).gamma(2.2), BLACK);
+    }
+
+    #[test]
+    fn reinhard_tone_mapping() {
+        assert_eq!(
+            Color::make(4.0, 0.0, 1.0).reinhard(),
+            Color::make(0.8, 0.0, 0.5)
+        );
+        assert_eq!(Color::make(-1.0, 0.0, 0.0).reinhard(), BLACK);
+        assert_eq!(Color::make(4.0, 0.0, 0.0).reinhard().to_rgb8(), [204, 0, 0]);
+    }
+
+    #[test]
+    fn colors_are_compared_within_epsilon() {
+        assert_ne!(0.1 + 0.2, 0.3);
+        assert_eq!(Color::make(0.1 + 0.2, 0.0, 0.0), Color::make(0.3, 0.0, 0.0));
+        assert_eq!(
+            Color::make(0.5, 0.5, 0.5),
+            Color::make(0.5000001, 0.4999999, 0.5)
+        );
+        assert_ne!(Color::make(0.5, 0.5, 0.5), Color::make(0.5, 0.501, 0.5));
+        assert_ne!(Color::make(0.5, 0.5, 0.5), Color::make(0.5, 0.5, 0.49));
+    }
+
+    #[test]
+    fn averaging_samples_does_not_round() {
+        let c = Color::average(vec![Color::make(1.0, 0.0, 0.0), Color::make(0.0, 0.0, 0.0)]);
+        assert_eq!((c.red, c.green, c.blue), (0.5, 0.0, 0.0));
+        // out of range samples are kept until the output clamps them
+        let c = Color::average(vec![Color::make(3.0, 0.0, 0.0), Color::make(0.0, 0.0, 0.0)]);
+        assert_eq!(c.red, 1.5);
+        assert_eq!(Color::average(vec![]), BLACK);
+    }
+
+    #[test]
+    fn converting_to_hsv() {
+        assert_eq!(RED.to_hsv(), (0.0, 1.0, 1.0));
+        assert_eq!(GREEN.to_hsv(), (120.0, 1.0, 1.0));
+        assert_eq!(BLUE.to_hsv(), (240.0, 1.0, 1.0));
+        assert_eq!(Color::make(0.5, 0.5, 0.5).to_hsv(), (0.0, 0.0, 0.5));
+        assert_eq!(BLACK.to_hsv(), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn round_tripping_through_hsv() {
+        let (h, s, v) = GREEN.to_hsv();
+        assert_eq!(Color::from_hsv(h, s, v), GREEN);
+        let c = Color::make(0.2, 0.7, 0.4);
+        let (h, s, v) = c.to_hsv();
+        assert_eq!(Color::from_hsv(h, s, v), c);
+        assert_eq!(Color::from_hsv(360.0 + 60.0, 1.0, 1.0), YELLOW);
+    }
+
+    #[test]
+    fn hsv_interpolation_goes_around_the_color_wheel() {
+        assert_eq!(RED.lerp_hsv(&BLUE, 0.5), FUCHSIA);
+        assert_eq!(RED.lerp_hsv(&BLUE, 0.0), RED);
+        assert_eq!(RED.lerp_hsv(&BLUE, 1.0), BLUE);
+        // a linear RGB mix gives a darker color instead
+        assert_ne!(RED.add(&BLUE).multiply_value(0.5), FUCHSIA);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialized_as_an_array() {
+        let json = serde_json::to_string(&Color::make(0.5, 0.25, 1.0)).unwrap();
+        assert_eq!(json, "[0.5,0.25,1.0]");
+        let c: Color = serde_json::from_str(&json).unwrap();
+        assert_eq!(c, Color::make(0.5, 0.25, 1.0));
+    }
 }
@@ -0,0 +1,298 @@
+use crate::bounds::BoundingBox;
+use crate::epsilon::EPSILON;
+use crate::intersection::Intersection;
+use crate::material::Material;
+use crate::matrix::{Matrix, Transformation};
+use crate::ray::Ray;
+use crate::shape::Shape;
+use crate::tuple::*;
+use std::cmp::Ordering;
+
+// double-napped cone centered on the y axis, its radius at any y equals |y|
+#[derive(Debug)]
+pub struct Cone {
+    pub id: usize,
+    pub minimum: f64,
+    pub maximum: f64,
+    pub closed: bool,
+    transform: Transformation,
+    end_transform: Option<Transformation>, // pose at the end of the exposure
+    parent_transform: Option<Transformation>,
+    parent_end_transform: Option<Transformation>, // pose of the parents at the end of the exposure
+    pub material: Material,
+}
+
+impl Cone {
+    pub fn new(id: usize) -> Cone {
+        Cone {
+            id,
+            minimum: f64::NEG_INFINITY,
+            maximum: f64::INFINITY,
+            closed: false,
+            transform: Transformation::default(),
+            end_transform: None,
+            parent_transform: None,
+            parent_end_transform: None,
+            material: Material::default(),
+        }
+    }
+
+    pub fn set_transform(self, transform: Matrix) -> Cone {
+        Cone {
+            transform: Transformation::make(transform),
+            ..self
+        }
+    }
+
+    // the shape moves from its transform at time 0 to `end_transform` at time 1
+    pub fn set_motion(self, end_transform: Matrix) -> Cone {
+        Cone {
+            end_transform: Some(Transformation::make(end_transform)),
+            ..self
+        }
+    }
+
+    pub fn set_material(self, material: Material) -> Cone {
+        Cone { material, ..self }
+    }
+
+    // the bounds are exclusive and expressed on the y axis in object space
+    pub fn set_bounds(self, minimum: f64, maximum: f64) -> Cone {
+        Cone {
+            minimum,
+            maximum,
+            ..self
+        }
+    }
+
+    pub fn set_closed(self, closed: bool) -> Cone {
+        Cone { closed, ..self }
+    }
+
+    // checks if the intersection at `t` is within the radius of the cap
+    fn check_cap(ray: &Ray, t: f64, radius: f64) -> bool {
+        let x = ray.origin.0 + t * ray.direction.0;
+        let z = ray.origin.2 + t * ray.direction.2;
+        x.powi(2) + z.powi(2) <= radius.powi(2)
+    }
+
+    fn intersect_caps(&self, ray: &Ray, intersections: &mut Vec<Intersection>) {
+        // caps only matter if the cone is closed and might possibly be intersected by the ray
+        if !self.closed || ray.direction.1.abs() < EPSILON {
+            return;
+        }
+        // the radius of each cap is the absolute value of its y coordinate
+        let t = (self.minimum - ray.origin.1) / ray.direction.1;
+        if Cone::check_cap(ray, t, self.minimum.abs()) {
+            intersections.push(Intersection::new(self.id, t));
+        }
+        let t = (self.maximum - ray.origin.1) / ray.direction.1;
+        if Cone::check_cap(ray, t, self.maximum.abs()) {
+            intersections.push(Intersection::new(self.id, t));
+        }
+    }
+
+    fn push_if_within_bounds(&self, ray: &Ray, t: f64, intersections: &mut Vec<Intersection>) {
+        let y = ray.origin.1 + t * ray.direction.1;
+        if self.minimum < y && y < self.maximum {
+            intersections.push(Intersection::new(self.id, t));
+        }
+    }
+}
+
+impl Shape for Cone {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn parent_transform(&self) -> Option<&Transformation> {
+        self.parent_transform.as_ref()
+    }
+
+    fn set_parent_transform(&mut self, parent: &Matrix) {
+        self.parent_transform = Some(Transformation::make(parent.clone()));
+    }
+
+    fn end_transform(&self) -> Option<&Transformation> {
+        self.end_transform.as_ref()
+    }
+
+    fn parent_end_transform(&self) -> Option<&Transformation> {
+        self.parent_end_transform.as_ref()
+    }
+
+    fn set_parent_end_transform(&mut self, parent_end: Option<&Matrix>) {
+        self.parent_end_transform = parent_end.map(|m| Transformation::make(m.clone()));
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> Option<&mut Material> {
+        Some(&mut self.material)
+    }
+
+    fn set_transform_mut(&mut self, transform: Matrix) {
+        self.transform = Transformation::make(transform);
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        // the radius at a given y is the absolute value of y
+        let limit = self.minimum.abs().max(self.maximum.abs());
+        BoundingBox::new(
+            point(-limit, self.minimum, -limit),
+            point(limit, self.maximum, limit),
+        )
+    }
+
+    fn local_intersect(&self, local_ray: &Ray) -> Vec<Intersection<'_>> {
+        let mut intersections = Vec::new();
+        let (o, d) = (local_ray.origin, local_ray.direction);
+        let a = d.0.powi(2) - d.1.powi(2) + d.2.powi(2);
+        let b = 2.0 * o.0 * d.0 - 2.0 * o.1 * d.1 + 2.0 * o.2 * d.2;
+        let c = o.0.powi(2) - o.1.powi(2) + o.2.powi(2);
+        if a.abs() < EPSILON {
+            // the ray is parallel to one of the cone's halves, there is at most a single wall hit
+            if b.abs() >= EPSILON {
+                let t = -c / (2.0 * b);
+                self.push_if_within_bounds(local_ray, t, &mut intersections);
+            }
+        } else {
+            let discriminant = b.powi(2) - 4.0 * a * c;
+            // ray does not intersect the cone
+            if discriminant < 0.0 {
+                return intersections;
+            }
+            let sqrt_discriminant = discriminant.sqrt();
+            let t0 = (-b - sqrt_discriminant) / (2.0 * a);
+            let t1 = (-b + sqrt_discriminant) / (2.0 * a);
+            let (t0, t1) = if t0 > t1 { (t1, t0) } else { (t0, t1) };
+            self.push_if_within_bounds(local_ray, t0, &mut intersections);
+            self.push_if_within_bounds(local_ray, t1, &mut intersections);
+        }
+        self.intersect_caps(local_ray, &mut intersections);
+        intersections.sort_by(|a, b| {
+            a.distance
+                .partial_cmp(&b.distance)
+                .unwrap_or(Ordering::Equal)
+        });
+        intersections
+    }
+
+    fn local_normal_at(&self, local_point: &Tuple) -> Tuple {
+        // square of the distance from the y axis
+        let distance = local_point.0.powi(2) + local_point.2.powi(2);
+        if distance < self.maximum.powi(2) && local_point.1 >= self.maximum - EPSILON {
+            vector(0.0, 1.0, 0.0)
+        } else if distance < self.minimum.powi(2) && local_point.1 <= self.minimum + EPSILON {
+            vector(0.0, -1.0, 0.0)
+        } else {
+            let y = distance.sqrt();
+            let y = if local_point.1 > 0.0 { -y } else { y };
+            vector(local_point.0, y, local_point.2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod cone_tests {
+    use crate::cone::Cone;
+    use crate::ray::Ray;
+    use crate::shape::Shape;
+    use crate::tuple::*;
+    use std::f64::consts::SQRT_2;
+
+    #[test]
+    fn intersecting_cone_with_ray() {
+        let cone = Cone::new(1);
+        let cases = vec![
+            (point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0), 5.0, 5.0),
+            (
+                point(0.0, 0.0, -5.0),
+                vector(1.0, 1.0, 1.0),
+                8.660254037844386,
+                8.660254037844386,
+            ),
+            (
+                point(1.0, 1.0, -5.0),
+                vector(-0.5, -1.0, 1.0),
+                4.550055679356349,
+                49.449944320643645,
+            ),
+        ];
+        for (origin, direction, t0, t1) in cases {
+            let ray = Ray::new(origin, vector_normalize(&direction));
+            let intersections = cone.local_intersect(&ray);
+            assert_eq!(intersections.len(), 2);
+            assert_eq!(intersections[0].distance, t0);
+            assert_eq!(intersections[1].distance, t1);
+        }
+    }
+
+    #[test]
+    fn intersecting_cone_with_ray_parallel_to_one_half() {
+        let cone = Cone::new(1);
+        let direction = vector_normalize(&vector(0.0, 1.0, 1.0));
+        let ray = Ray::new(point(0.0, 0.0, -1.0), direction);
+        let intersections = cone.local_intersect(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].distance, 0.3535533905932738);
+    }
+
+    #[test]
+    fn intersecting_truncated_cone_caps() {
+        let cone = Cone::new(1).set_bounds(-0.5, 0.5).set_closed(true);
+        let cases = vec![
+            (point(0.0, 0.0, -5.0), vector(0.0, 1.0, 0.0), 0),
+            (point(0.0, 0.0, -0.25), vector(0.0, 1.0, 1.0), 2),
+            (point(0.0, 0.0, -0.25), vector(0.0, 1.0, 0.0), 4),
+        ];
+        for (origin, direction, count) in cases {
+            let ray = Ray::new(origin, vector_normalize(&direction));
+            assert_eq!(cone.local_intersect(&ray).len(), count);
+        }
+    }
+
+    #[test]
+    fn truncated_open_cone_ignores_caps() {
+        let cone = Cone::new(1).set_bounds(-0.5, 0.5);
+        let ray = Ray::new(point(0.0, 0.0, -0.25), vector(0.0, 1.0, 0.0));
+        // only the two wall hits remain
+        assert_eq!(cone.local_intersect(&ray).len(), 2);
+    }
+
+    #[test]
+    fn normal_on_cone() {
+        let cone = Cone::new(1);
+        assert_eq!(
+            cone.local_normal_at(&point(0.0, 0.0, 0.0)),
+            vector(0.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            cone.local_normal_at(&point(1.0, 1.0, 1.0)),
+            vector(1.0, -SQRT_2, 1.0)
+        );
+        assert_eq!(
+            cone.local_normal_at(&point(-1.0, -1.0, 0.0)),
+            vector(-1.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn normal_on_cone_caps() {
+        let cone = Cone::new(1).set_bounds(-1.0, 2.0).set_closed(true);
+        assert_eq!(
+            cone.local_normal_at(&point(0.5, 2.0, 0.0)),
+            vector(0.0, 1.0, 0.0)
+        );
+        assert_eq!(
+            cone.local_normal_at(&point(0.0, -1.0, 0.5)),
+            vector(0.0, -1.0, 0.0)
+        );
+    }
+}
@@ -0,0 +1,391 @@
+use crate::bounds::BoundingBox;
+use crate::intersection::Intersection;
+use crate::material::Material;
+use crate::matrix::{Matrix, Transformation};
+use crate::ray::Ray;
+use crate::shape::{world_matrices, Shape};
+use crate::tuple::Tuple;
+use std::cmp::Ordering;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+// Constructive solid geometry combining two shapes with a set operation.
+pub struct Csg {
+    pub id: usize,
+    operation: CsgOperation,
+    transform: Transformation,
+    end_transform: Option<Transformation>, // pose at the end of the exposure
+    parent_transform: Option<Transformation>,
+    parent_end_transform: Option<Transformation>, // pose of the parents at the end of the exposure
+    material: Material,
+    // left then right
+    children: [Box<dyn Shape>; 2],
+    // both children merged, conservative for intersections and differences
+    bounds: BoundingBox,
+}
+
+impl Csg {
+    pub fn new(
+        id: usize,
+        operation: CsgOperation,
+        left: Box<dyn Shape>,
+        right: Box<dyn Shape>,
+    ) -> Csg {
+        let bounds = left
+            .parent_space_bounds()
+            .merge(&right.parent_space_bounds());
+        let mut csg = Csg {
+            id,
+            operation,
+            transform: Transformation::default(),
+            end_transform: None,
+            parent_transform: None,
+            parent_end_transform: None,
+            material: Material::default(),
+            children: [left, right],
+            bounds,
+        };
+        csg.propagate_transform();
+        csg
+    }
+
+    pub fn set_transform(self, transform: Matrix) -> Csg {
+        let mut csg = Csg {
+            transform: Transformation::make(transform),
+            ..self
+        };
+        csg.propagate_transform();
+        csg
+    }
+
+    // the shape moves from its transform at time 0 to `end_transform` at time 1,
+    // the children follow the motion
+    pub fn set_motion(self, end_transform: Matrix) -> Csg {
+        let mut csg = Csg {
+            end_transform: Some(Transformation::make(end_transform)),
+            ..self
+        };
+        csg.propagate_transform();
+        csg
+    }
+
+    pub fn operation(&self) -> CsgOperation {
+        self.operation
+    }
+
+    pub fn left(&self) -> &dyn Shape {
+        self.children[0].as_ref()
+    }
+
+    pub fn right(&self) -> &dyn Shape {
+        self.children[1].as_ref()
+    }
+
+    // keeps only the intersections on the surface of the combined shape
+    pub fn filter_intersections<'a>(
+        &self,
+        intersections: Vec<Intersection<'a>>,
+    ) -> Vec<Intersection<'a>> {
+        // both children start outside
+        let mut inside_left = false;
+        let mut inside_right = false;
+        let mut result = Vec::new();
+        for i in intersections {
+            let left_hit = self.left().includes(i.placed_id());
+            if intersection_allowed(self.operation, left_hit, inside_left, inside_right) {
+                result.push(i);
+            }
+            if left_hit {
+                inside_left = !inside_left;
+            } else {
+                inside_right = !inside_right;
+            }
+        }
+        result
+    }
+
+    fn propagate_transform(&mut self) {
+        let (start, end) = world_matrices(
+            self.parent_transform.as_ref(),
+            self.parent_end_transform.as_ref(),
+            &self.transform,
+            self.end_transform.as_ref(),
+        );
+        for child in self.children.iter_mut() {
+            child.set_parent_transform(&start);
+            child.set_parent_end_transform(end.as_ref());
+        }
+    }
+}
+
+// `left_hit` is true when the left shape was hit, `inside_left` and `inside_right` tell
+// whether the hit occurs inside the left and the right shape
+pub fn intersection_allowed(
+    operation: CsgOperation,
+    left_hit: bool,
+    inside_left: bool,
+    inside_right: bool,
+) -> bool {
+    match operation {
+        CsgOperation::Union => (left_hit && !inside_right) || (!left_hit && !inside_left),
+        CsgOperation::Intersection => (left_hit && inside_right) || (!left_hit && inside_left),
+        CsgOperation::Difference => (left_hit && !inside_right) || (!left_hit && inside_left),
+    }
+}
+
+impl Shape for Csg {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn parent_transform(&self) -> Option<&Transformation> {
+        self.parent_transform.as_ref()
+    }
+
+    fn set_parent_transform(&mut self, parent: &Matrix) {
+        self.parent_transform = Some(Transformation::make(parent.clone()));
+        self.propagate_transform();
+    }
+
+    fn end_transform(&self) -> Option<&Transformation> {
+        self.end_transform.as_ref()
+    }
+
+    fn parent_end_transform(&self) -> Option<&Transformation> {
+        self.parent_end_transform.as_ref()
+    }
+
+    fn set_parent_end_transform(&mut self, parent_end: Option<&Matrix>) {
+        self.parent_end_transform = parent_end.map(|m| Transformation::make(m.clone()));
+        self.propagate_transform();
+    }
+
+    fn children(&self) -> &[Box<dyn Shape>] {
+        &self.children
+    }
+
+    fn divide(&mut self, threshold: usize) {
+        for child in self.children.iter_mut() {
+            child.divide(threshold);
+        }
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    // the children are shaded with their own materials
+    fn material_mut(&mut self) -> Option<&mut Material> {
+        None
+    }
+
+    fn set_transform_mut(&mut self, transform: Matrix) {
+        self.transform = Transformation::make(transform);
+        self.propagate_transform();
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.bounds
+    }
+
+    fn local_intersect(&self, local_ray: &Ray) -> Vec<Intersection<'_>> {
+        // no need to look at the children if the ray misses both of them
+        if !self.bounds.intersects(local_ray) {
+            return vec![];
+        }
+        let mut intersections: Vec<Intersection> = self
+            .children
+            .iter()
+            .flat_map(|child| {
+                child
+                    .intersect(local_ray)
+                    .into_iter()
+                    .map(|i| i.with_object(child.as_ref()))
+            })
+            .collect();
+        intersections.sort_by(|a, b| {
+            a.distance
+                .partial_cmp(&b.distance)
+                .unwrap_or(Ordering::Equal)
+        });
+        self.filter_intersections(intersections)
+    }
+
+    // normals are always computed on the children
+    fn local_normal_at(&self, _local_point: &Tuple) -> Tuple {
+        panic!("a CSG shape has no normal of its own")
+    }
+}
+
+#[cfg(test)]
+mod csg_tests {
+    use crate::csg::*;
+    use crate::intersection::Intersection;
+    use crate::matrix::Matrix;
+    use crate::ray::Ray;
+    use crate::shape::{CountingShape, Shape};
+    use crate::sphere::Sphere;
+    use crate::tuple::*;
+    use crate::world::World;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn csg_is_created_with_an_operation_and_two_shapes() {
+        let c = Csg::new(
+            1,
+            CsgOperation::Union,
+            Box::new(Sphere::new(2)),
+            Box::new(Sphere::new(3)),
+        );
+        assert_eq!(c.operation(), CsgOperation::Union);
+        assert_eq!(c.left().id(), 2);
+        assert_eq!(c.right().id(), 3);
+        assert!(c.includes(3));
+        assert!(!c.includes(4));
+    }
+
+    #[test]
+    fn evaluating_rule_for_csg_operation() {
+        use CsgOperation::*;
+        let cases = [
+            (Union, true, true, true, false),
+            (Union, true, true, false, true),
+            (Union, true, false, true, false),
+            (Union, true, false, false, true),
+            (Union, false, true, true, false),
+            (Union, false, true, false, false),
+            (Union, false, false, true, true),
+            (Union, false, false, false, true),
+            (Intersection, true, true, true, true),
+            (Intersection, true, true, false, false),
+            (Intersection, true, false, true, true),
+            (Intersection, true, false, false, false),
+            (Intersection, false, true, true, true),
+            (Intersection, false, true, false, true),
+            (Intersection, false, false, true, false),
+            (Intersection, false, false, false, false),
+            (Difference, true, true, true, false),
+            (Difference, true, true, false, true),
+            (Difference, true, false, true, false),
+            (Difference, true, false, false, true),
+            (Difference, false, true, true, true),
+            (Difference, false, true, false, true),
+            (Difference, false, false, true, false),
+            (Difference, false, false, false, false),
+        ];
+        for (op, lhit, inl, inr, expected) in cases {
+            assert_eq!(
+                intersection_allowed(op, lhit, inl, inr),
+                expected,
+                "{:?} lhit={} inl={} inr={}",
+                op,
+                lhit,
+                inl,
+                inr
+            );
+        }
+    }
+
+    #[test]
+    fn filtering_list_of_intersections() {
+        let cases = [
+            (CsgOperation::Union, 0, 3),
+            (CsgOperation::Intersection, 1, 2),
+            (CsgOperation::Difference, 0, 1),
+        ];
+        for (op, x0, x1) in cases {
+            let c = Csg::new(1, op, Box::new(Sphere::new(2)), Box::new(Sphere::new(3)));
+            let xs = vec![
+                Intersection::new(2, 1.0),
+                Intersection::new(3, 2.0),
+                Intersection::new(2, 3.0),
+                Intersection::new(3, 4.0),
+            ];
+            let result = c.filter_intersections(xs.clone());
+            assert_eq!(result, vec![xs[x0], xs[x1]]);
+        }
+    }
+
+    #[test]
+    fn ray_misses_csg_object() {
+        let c = Csg::new(
+            1,
+            CsgOperation::Union,
+            Box::new(Sphere::new(2)),
+            Box::new(Sphere::new(3)),
+        );
+        let r = Ray::new(point(0.0, 2.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert!(c.local_intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn ray_hits_csg_object() {
+        let s2 = Sphere::new(3).set_transform(Matrix::translation(0.0, 0.0, 0.5));
+        let c = Csg::new(
+            1,
+            CsgOperation::Union,
+            Box::new(Sphere::new(2)),
+            Box::new(s2),
+        );
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = c.local_intersect(&r);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].distance, 4.0);
+        assert_eq!(xs[0].object_id, 2);
+        assert_eq!(xs[1].distance, 6.5);
+        assert_eq!(xs[1].object_id, 3);
+    }
+
+    #[test]
+    fn csg_bounds_span_both_children() {
+        let c = Csg::new(
+            1,
+            CsgOperation::Union,
+            Box::new(Sphere::new(2).set_transform(Matrix::translation(-2.0, 0.0, 0.0))),
+            Box::new(Sphere::new(3).set_transform(Matrix::translation(0.0, 3.0, 1.0))),
+        );
+        let bounds = c.bounds();
+        assert_eq!(bounds.min, point(-3.0, -1.0, -1.0));
+        assert_eq!(bounds.max, point(1.0, 4.0, 2.0));
+    }
+
+    #[test]
+    fn children_are_skipped_when_ray_misses_csg_bounds() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let c = Csg::new(
+            1,
+            CsgOperation::Difference,
+            Box::new(CountingShape::new(2, calls.clone(), Matrix::identity())),
+            Box::new(Sphere::new(3).set_transform(Matrix::translation(3.0, 0.0, 0.0))),
+        );
+        let miss = Ray::new(point(0.0, 5.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert!(c.intersect(&miss).is_empty());
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        let hit = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        c.intersect(&hit);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn csg_material_cannot_be_modified_in_place() {
+        let c = Csg::new(
+            1,
+            CsgOperation::Union,
+            Box::new(Sphere::new(2)),
+            Box::new(Sphere::new(3)),
+        );
+        let mut w = World::empty().add_object(Box::new(c)).unwrap();
+        assert!(w.object_mut(1).unwrap().material_mut().is_none());
+    }
+}
@@ -0,0 +1,209 @@
+use crate::bounds::BoundingBox;
+use crate::epsilon::EPSILON;
+use crate::intersection::Intersection;
+use crate::material::Material;
+use crate::matrix::{Matrix, Transformation};
+use crate::ray::Ray;
+use crate::shape::Shape;
+use crate::tuple::*;
+
+// axis aligned cube from -1 to 1 on every axis
+#[derive(Debug, PartialEq)]
+pub struct Cube {
+    pub id: usize,
+    transform: Transformation,
+    end_transform: Option<Transformation>, // pose at the end of the exposure
+    parent_transform: Option<Transformation>,
+    parent_end_transform: Option<Transformation>, // pose of the parents at the end of the exposure
+    pub material: Material,
+}
+
+impl Cube {
+    pub fn new(id: usize) -> Cube {
+        Cube {
+            id,
+            transform: Transformation::default(),
+            end_transform: None,
+            parent_transform: None,
+            parent_end_transform: None,
+            material: Material::default(),
+        }
+    }
+
+    pub fn set_transform(self, transform: Matrix) -> Cube {
+        Cube {
+            transform: Transformation::make(transform),
+            ..self
+        }
+    }
+
+    // the shape moves from its transform at time 0 to `end_transform` at time 1
+    pub fn set_motion(self, end_transform: Matrix) -> Cube {
+        Cube {
+            end_transform: Some(Transformation::make(end_transform)),
+            ..self
+        }
+    }
+
+    pub fn set_material(self, material: Material) -> Cube {
+        Cube { material, ..self }
+    }
+}
+
+// distances at which the ray crosses the two planes bounding an axis
+pub fn check_axis(origin: f64, direction: f64, minimum: f64, maximum: f64) -> (f64, f64) {
+    let tmin_numerator = minimum - origin;
+    let tmax_numerator = maximum - origin;
+    if direction.abs() >= EPSILON {
+        let tmin = tmin_numerator / direction;
+        let tmax = tmax_numerator / direction;
+        if tmin > tmax {
+            (tmax, tmin)
+        } else {
+            (tmin, tmax)
+        }
+    } else if tmin_numerator <= 0.0 && tmax_numerator >= 0.0 {
+        // parallel to the planes and between them
+        (f64::NEG_INFINITY, f64::INFINITY)
+    } else {
+        // parallel to the planes and outside of them
+        (f64::INFINITY, f64::NEG_INFINITY)
+    }
+}
+
+impl Shape for Cube {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn end_transform(&self) -> Option<&Transformation> {
+        self.end_transform.as_ref()
+    }
+
+    fn parent_transform(&self) -> Option<&Transformation> {
+        self.parent_transform.as_ref()
+    }
+
+    fn set_parent_transform(&mut self, parent: &Matrix) {
+        self.parent_transform = Some(Transformation::make(parent.clone()));
+    }
+
+    fn parent_end_transform(&self) -> Option<&Transformation> {
+        self.parent_end_transform.as_ref()
+    }
+
+    fn set_parent_end_transform(&mut self, parent_end: Option<&Matrix>) {
+        self.parent_end_transform = parent_end.map(|m| Transformation::make(m.clone()));
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> Option<&mut Material> {
+        Some(&mut self.material)
+    }
+
+    fn set_transform_mut(&mut self, transform: Matrix) {
+        self.transform = Transformation::make(transform);
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0))
+    }
+
+    fn local_intersect(&self, local_ray: &Ray) -> Vec<Intersection<'_>> {
+        let (xtmin, xtmax) = check_axis(local_ray.origin.0, local_ray.direction.0, -1.0, 1.0);
+        let (ytmin, ytmax) = check_axis(local_ray.origin.1, local_ray.direction.1, -1.0, 1.0);
+        let (ztmin, ztmax) = check_axis(local_ray.origin.2, local_ray.direction.2, -1.0, 1.0);
+        let tmin = xtmin.max(ytmin).max(ztmin);
+        let tmax = xtmax.min(ytmax).min(ztmax);
+        if tmin > tmax {
+            vec![]
+        } else {
+            vec![
+                Intersection::new(self.id, tmin),
+                Intersection::new(self.id, tmax),
+            ]
+        }
+    }
+
+    // the face is given by the component with the largest absolute value
+    fn local_normal_at(&self, local_point: &Tuple) -> Tuple {
+        let (x, y, z) = (local_point.0, local_point.1, local_point.2);
+        let max = x.abs().max(y.abs()).max(z.abs());
+        if max == x.abs() {
+            vector(x, 0.0, 0.0)
+        } else if max == y.abs() {
+            vector(0.0, y, 0.0)
+        } else {
+            vector(0.0, 0.0, z)
+        }
+    }
+}
+
+#[cfg(test)]
+mod cube_tests {
+    use crate::cube::Cube;
+    use crate::ray::Ray;
+    use crate::shape::Shape;
+    use crate::tuple::*;
+
+    #[test]
+    fn ray_intersects_cube() {
+        let c = Cube::new(1);
+        let cases = vec![
+            (point(5.0, 0.5, 0.0), vector(-1.0, 0.0, 0.0), 4.0, 6.0),
+            (point(-5.0, 0.5, 0.0), vector(1.0, 0.0, 0.0), 4.0, 6.0),
+            (point(0.5, 5.0, 0.0), vector(0.0, -1.0, 0.0), 4.0, 6.0),
+            (point(0.5, -5.0, 0.0), vector(0.0, 1.0, 0.0), 4.0, 6.0),
+            (point(0.5, 0.0, 5.0), vector(0.0, 0.0, -1.0), 4.0, 6.0),
+            (point(0.5, 0.0, -5.0), vector(0.0, 0.0, 1.0), 4.0, 6.0),
+            (point(0.0, 0.5, 0.0), vector(0.0, 0.0, 1.0), -1.0, 1.0),
+        ];
+        for (origin, direction, t1, t2) in cases {
+            let xs = c.local_intersect(&Ray::new(origin, direction));
+            assert_eq!(xs.len(), 2);
+            assert_eq!(xs[0].distance, t1);
+            assert_eq!(xs[1].distance, t2);
+        }
+    }
+
+    #[test]
+    fn ray_misses_cube() {
+        let c = Cube::new(1);
+        let cases = vec![
+            (point(-2.0, 0.0, 0.0), vector(0.2673, 0.5345, 0.8018)),
+            (point(0.0, -2.0, 0.0), vector(0.8018, 0.2673, 0.5345)),
+            (point(0.0, 0.0, -2.0), vector(0.5345, 0.8018, 0.2673)),
+            (point(2.0, 0.0, 2.0), vector(0.0, 0.0, -1.0)),
+            (point(0.0, 2.0, 2.0), vector(0.0, -1.0, 0.0)),
+            (point(2.0, 2.0, 0.0), vector(-1.0, 0.0, 0.0)),
+        ];
+        for (origin, direction) in cases {
+            assert!(c.local_intersect(&Ray::new(origin, direction)).is_empty());
+        }
+    }
+
+    #[test]
+    fn normal_on_surface_of_cube() {
+        let c = Cube::new(1);
+        let cases = vec![
+            (point(1.0, 0.5, -0.8), vector(1.0, 0.0, 0.0)),
+            (point(-1.0, -0.2, 0.9), vector(-1.0, 0.0, 0.0)),
+            (point(-0.4, 1.0, -0.1), vector(0.0, 1.0, 0.0)),
+            (point(0.3, -1.0, -0.7), vector(0.0, -1.0, 0.0)),
+            (point(-0.6, 0.3, 1.0), vector(0.0, 0.0, 1.0)),
+            (point(0.4, 0.4, -1.0), vector(0.0, 0.0, -1.0)),
+            (point(1.0, 1.0, 1.0), vector(1.0, 0.0, 0.0)),
+            (point(-1.0, -1.0, -1.0), vector(-1.0, 0.0, 0.0)),
+        ];
+        for (p, normal) in cases {
+            assert_eq!(c.local_normal_at(&p), normal);
+        }
+    }
+}
@@ -0,0 +1,321 @@
+use crate::bounds::BoundingBox;
+use crate::epsilon::EPSILON;
+use crate::intersection::Intersection;
+use crate::material::Material;
+use crate::matrix::{Matrix, Transformation};
+use crate::ray::Ray;
+use crate::shape::Shape;
+use crate::tuple::*;
+use std::cmp::Ordering;
+
+// radius 1 cylinder centered on the y axis
+#[derive(Debug)]
+pub struct Cylinder {
+    pub id: usize,
+    pub minimum: f64,
+    pub maximum: f64,
+    pub closed: bool,
+    transform: Transformation,
+    end_transform: Option<Transformation>, // pose at the end of the exposure
+    parent_transform: Option<Transformation>,
+    parent_end_transform: Option<Transformation>, // pose of the parents at the end of the exposure
+    pub material: Material,
+}
+
+impl Cylinder {
+    pub fn new(id: usize) -> Cylinder {
+        Cylinder {
+            id,
+            minimum: f64::NEG_INFINITY,
+            maximum: f64::INFINITY,
+            closed: false,
+            transform: Transformation::default(),
+            end_transform: None,
+            parent_transform: None,
+            parent_end_transform: None,
+            material: Material::default(),
+        }
+    }
+
+    pub fn set_transform(self, transform: Matrix) -> Cylinder {
+        Cylinder {
+            transform: Transformation::make(transform),
+            ..self
+        }
+    }
+
+    // the shape moves from its transform at time 0 to `end_transform` at time 1
+    pub fn set_motion(self, end_transform: Matrix) -> Cylinder {
+        Cylinder {
+            end_transform: Some(Transformation::make(end_transform)),
+            ..self
+        }
+    }
+
+    pub fn set_material(self, material: Material) -> Cylinder {
+        Cylinder { material, ..self }
+    }
+
+    // the bounds are exclusive and expressed on the y axis in object space
+    pub fn set_bounds(self, minimum: f64, maximum: f64) -> Cylinder {
+        Cylinder {
+            minimum,
+            maximum,
+            ..self
+        }
+    }
+
+    pub fn set_closed(self, closed: bool) -> Cylinder {
+        Cylinder { closed, ..self }
+    }
+
+    // checks if the intersection at `t` is within a radius of 1 from the y axis
+    fn check_cap(ray: &Ray, t: f64) -> bool {
+        let x = ray.origin.0 + t * ray.direction.0;
+        let z = ray.origin.2 + t * ray.direction.2;
+        x.powi(2) + z.powi(2) <= 1.0
+    }
+
+    fn intersect_caps(&self, ray: &Ray, intersections: &mut Vec<Intersection>) {
+        // caps only matter if the cylinder is closed and might possibly be intersected by the ray
+        if !self.closed || ray.direction.1.abs() < EPSILON {
+            return;
+        }
+        // check for an intersection with the lower end cap by intersecting the ray with the plane at y=minimum
+        let t = (self.minimum - ray.origin.1) / ray.direction.1;
+        if Cylinder::check_cap(ray, t) {
+            intersections.push(Intersection::new(self.id, t));
+        }
+        // check for an intersection with the upper end cap by intersecting the ray with the plane at y=maximum
+        let t = (self.maximum - ray.origin.1) / ray.direction.1;
+        if Cylinder::check_cap(ray, t) {
+            intersections.push(Intersection::new(self.id, t));
+        }
+    }
+}
+
+impl Shape for Cylinder {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn parent_transform(&self) -> Option<&Transformation> {
+        self.parent_transform.as_ref()
+    }
+
+    fn set_parent_transform(&mut self, parent: &Matrix) {
+        self.parent_transform = Some(Transformation::make(parent.clone()));
+    }
+
+    fn end_transform(&self) -> Option<&Transformation> {
+        self.end_transform.as_ref()
+    }
+
+    fn parent_end_transform(&self) -> Option<&Transformation> {
+        self.parent_end_transform.as_ref()
+    }
+
+    fn set_parent_end_transform(&mut self, parent_end: Option<&Matrix>) {
+        self.parent_end_transform = parent_end.map(|m| Transformation::make(m.clone()));
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> Option<&mut Material> {
+        Some(&mut self.material)
+    }
+
+    fn set_transform_mut(&mut self, transform: Matrix) {
+        self.transform = Transformation::make(transform);
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(
+            point(-1.0, self.minimum, -1.0),
+            point(1.0, self.maximum, 1.0),
+        )
+    }
+
+    fn local_intersect(&self, local_ray: &Ray) -> Vec<Intersection<'_>> {
+        let mut intersections = Vec::new();
+        let a = local_ray.direction.0.powi(2) + local_ray.direction.2.powi(2);
+        // a ray parallel to the y axis can only hit the caps
+        if a.abs() >= EPSILON {
+            let b = 2.0 * local_ray.origin.0 * local_ray.direction.0
+                + 2.0 * local_ray.origin.2 * local_ray.direction.2;
+            let c = local_ray.origin.0.powi(2) + local_ray.origin.2.powi(2) - 1.0;
+            let discriminant = b.powi(2) - 4.0 * a * c;
+            // ray does not intersect the cylinder
+            if discriminant < 0.0 {
+                return intersections;
+            }
+            let sqrt_discriminant = discriminant.sqrt();
+            let t0 = (-b - sqrt_discriminant) / (2.0 * a);
+            let t1 = (-b + sqrt_discriminant) / (2.0 * a);
+            let (t0, t1) = if t0 > t1 { (t1, t0) } else { (t0, t1) };
+            // keep only the wall hits within the truncation bounds
+            let y0 = local_ray.origin.1 + t0 * local_ray.direction.1;
+            if self.minimum < y0 && y0 < self.maximum {
+                intersections.push(Intersection::new(self.id, t0));
+            }
+            let y1 = local_ray.origin.1 + t1 * local_ray.direction.1;
+            if self.minimum < y1 && y1 < self.maximum {
+                intersections.push(Intersection::new(self.id, t1));
+            }
+        }
+        self.intersect_caps(local_ray, &mut intersections);
+        intersections.sort_by(|a, b| {
+            a.distance
+                .partial_cmp(&b.distance)
+                .unwrap_or(Ordering::Equal)
+        });
+        intersections
+    }
+
+    fn local_normal_at(&self, local_point: &Tuple) -> Tuple {
+        // square of the distance from the y axis
+        let distance = local_point.0.powi(2) + local_point.2.powi(2);
+        if distance < 1.0 && local_point.1 >= self.maximum - EPSILON {
+            vector(0.0, 1.0, 0.0)
+        } else if distance < 1.0 && local_point.1 <= self.minimum + EPSILON {
+            vector(0.0, -1.0, 0.0)
+        } else {
+            vector(local_point.0, 0.0, local_point.2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod cylinder_tests {
+    use crate::cylinder::Cylinder;
+    use crate::ray::Ray;
+    use crate::shape::Shape;
+    use crate::tuple::*;
+
+    #[test]
+    fn ray_misses_cylinder() {
+        let cyl = Cylinder::new(1);
+        let cases = vec![
+            (point(1.0, 0.0, 0.0), vector(0.0, 1.0, 0.0)),
+            (point(0.0, 0.0, 0.0), vector(0.0, 1.0, 0.0)),
+            (point(0.0, 0.0, -5.0), vector(1.0, 1.0, 1.0)),
+        ];
+        for (origin, direction) in cases {
+            let ray = Ray::new(origin, vector_normalize(&direction));
+            assert!(cyl.local_intersect(&ray).is_empty());
+        }
+    }
+
+    #[test]
+    fn ray_strikes_cylinder() {
+        let cyl = Cylinder::new(1);
+        let cases = vec![
+            (point(1.0, 0.0, -5.0), vector(0.0, 0.0, 1.0), 5.0, 5.0),
+            (point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0), 4.0, 6.0),
+            (
+                point(0.5, 0.0, -5.0),
+                vector(0.1, 1.0, 1.0),
+                6.80798191702732,
+                7.088723439378861,
+            ),
+        ];
+        for (origin, direction, t0, t1) in cases {
+            let ray = Ray::new(origin, vector_normalize(&direction));
+            let intersections = cyl.local_intersect(&ray);
+            assert_eq!(intersections.len(), 2);
+            assert_eq!(intersections[0].distance, t0);
+            assert_eq!(intersections[1].distance, t1);
+        }
+    }
+
+    #[test]
+    fn normal_on_cylinder() {
+        let cyl = Cylinder::new(1);
+        assert_eq!(
+            cyl.local_normal_at(&point(1.0, 0.0, 0.0)),
+            vector(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            cyl.local_normal_at(&point(0.0, 5.0, -1.0)),
+            vector(0.0, 0.0, -1.0)
+        );
+        assert_eq!(
+            cyl.local_normal_at(&point(0.0, -2.0, 1.0)),
+            vector(0.0, 0.0, 1.0)
+        );
+        assert_eq!(
+            cyl.local_normal_at(&point(-1.0, 1.0, 0.0)),
+            vector(-1.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn default_cylinder_is_infinite_and_open() {
+        let cyl = Cylinder::new(1);
+        assert_eq!(cyl.minimum, f64::NEG_INFINITY);
+        assert_eq!(cyl.maximum, f64::INFINITY);
+        assert!(!cyl.closed);
+    }
+
+    #[test]
+    fn intersecting_constrained_cylinder() {
+        let cyl = Cylinder::new(1).set_bounds(1.0, 2.0);
+        let cases = vec![
+            (point(0.0, 1.5, 0.0), vector(0.1, 1.0, 0.0), 0),
+            (point(0.0, 3.0, -5.0), vector(0.0, 0.0, 1.0), 0),
+            (point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0), 0),
+            (point(0.0, 2.0, -5.0), vector(0.0, 0.0, 1.0), 0),
+            (point(0.0, 1.0, -5.0), vector(0.0, 0.0, 1.0), 0),
+            (point(0.0, 1.5, -2.0), vector(0.0, 0.0, 1.0), 2),
+        ];
+        for (origin, direction, count) in cases {
+            let ray = Ray::new(origin, vector_normalize(&direction));
+            assert_eq!(cyl.local_intersect(&ray).len(), count);
+        }
+    }
+
+    #[test]
+    fn intersecting_caps_of_closed_cylinder() {
+        let cyl = Cylinder::new(1).set_bounds(1.0, 2.0).set_closed(true);
+        let cases = vec![
+            (point(0.0, 3.0, 0.0), vector(0.0, -1.0, 0.0), 2),
+            (point(0.0, 3.0, -2.0), vector(0.0, -1.0, 2.0), 2),
+            (point(0.0, 4.0, -2.0), vector(0.0, -1.0, 1.0), 2), // corner case
+            (point(0.0, 0.0, -2.0), vector(0.0, 1.0, 2.0), 2),
+            (point(0.0, -1.0, -2.0), vector(0.0, 1.0, 1.0), 2), // corner case
+        ];
+        for (origin, direction, count) in cases {
+            let ray = Ray::new(origin, vector_normalize(&direction));
+            assert_eq!(cyl.local_intersect(&ray).len(), count);
+        }
+    }
+
+    #[test]
+    fn uncapped_cylinder_is_hollow() {
+        let cyl = Cylinder::new(1).set_bounds(1.0, 2.0);
+        let ray = Ray::new(point(0.0, 3.0, 0.0), vector(0.0, -1.0, 0.0));
+        assert!(cyl.local_intersect(&ray).is_empty());
+    }
+
+    #[test]
+    fn normal_on_cylinder_end_caps() {
+        let cyl = Cylinder::new(1).set_bounds(1.0, 2.0).set_closed(true);
+        let cases = vec![
+            (point(0.0, 1.0, 0.0), vector(0.0, -1.0, 0.0)),
+            (point(0.5, 1.0, 0.0), vector(0.0, -1.0, 0.0)),
+            (point(0.0, 1.0, 0.5), vector(0.0, -1.0, 0.0)),
+            (point(0.0, 2.0, 0.0), vector(0.0, 1.0, 0.0)),
+            (point(0.5, 2.0, 0.0), vector(0.0, 1.0, 0.0)),
+            (point(0.0, 2.0, 0.5), vector(0.0, 1.0, 0.0)),
+        ];
+        for (p, expected) in cases {
+            assert_eq!(cyl.local_normal_at(&p), expected);
+        }
+    }
+}
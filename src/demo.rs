@@ -1,10 +1,14 @@
 use crate::camera::*;
 use crate::color::*;
+use crate::cone::Cone;
+use crate::cube::Cube;
+use crate::cylinder::Cylinder;
 use crate::light::Light;
 use crate::material::Material;
 use crate::matrix::Matrix;
 use crate::pattern::Pattern;
 use crate::plane::Plane;
+use crate::shape::ShapeEnum;
 use crate::sphere::Sphere;
 use crate::transformation::*;
 use crate::tuple::*;
@@ -62,16 +66,45 @@ pub fn demo() -> Result<()> {
             ring,
         ));
 
+    let cube = Cube::new(7)
+        .set_transform(
+            Matrix::translation(2.5, 0.4, 1.5)
+                .multiply(&Matrix::rotate_y(FRAC_PI_6))
+                .multiply(&Matrix::scaling(0.4, 0.4, 0.4)),
+        )
+        .set_material(Material::new(Color::make(0.8, 0.3, 0.3), 0.7, 0.3).set_reflective(0.2));
+
+    let cylinder = Cylinder::new(8)
+        .set_bounds(0.0, 1.5)
+        .set_closed(true)
+        .set_transform(
+            Matrix::translation(-2.5, 0.0, 1.5).multiply(&Matrix::scaling(0.4, 1.0, 0.4)),
+        )
+        .set_material(Material::new(Color::make(0.3, 0.5, 0.9), 0.7, 0.3));
+
+    let cone = Cone::new(9)
+        .set_bounds(-1.0, 0.0)
+        .set_closed(true)
+        .set_transform(Matrix::translation(0.5, 1.0, 2.5).multiply(&Matrix::scaling(0.5, 1.0, 0.5)))
+        .set_material(Material::new(Color::make(0.9, 0.9, 0.3), 0.7, 0.3));
+
     let light_position = point(-10.0, 10.0, -10.0);
     let light_color = Color::make(1.0, 1.0, 1.0);
     let light = Light::point_light(light_position, light_color);
 
-    let world = World::empty()
-        .set_light(light)
-        .add_object(Box::new(floor))
-        .add_object(Box::new(middle_sphere))
-        .add_object(Box::new(right_sphere))
-        .add_object(Box::new(left_sphere));
+    // the enum intersects the objects with a match instead of a vtable call
+    let world = World::from_objects(
+        vec![light],
+        vec![
+            ShapeEnum::from(floor),
+            ShapeEnum::from(middle_sphere),
+            ShapeEnum::from(right_sphere),
+            ShapeEnum::from(left_sphere),
+            ShapeEnum::from(cube),
+            ShapeEnum::from(cylinder),
+            ShapeEnum::from(cone),
+        ],
+    );
 
     let camera = Camera::new(10000, 5000, FRAC_PI_3).set_transform(view_transform(
         &point(0.0, 1.5, -5.0),
@@ -79,6 +112,62 @@ pub fn demo() -> Result<()> {
         &vector(0.0, 1.0, 0.0),
     ));
 
-    let canvas = camera.render(&world);
+    let canvas = camera.render_with_progress(&world, |done, total| {
+        if done % 100 == 0 || done == total {
+            println!("rendered {}%", done * 100 / total);
+        }
+    });
     canvas.save_file("demo-projection.ppm")
 }
+
+// reflective floor mirroring a colored sphere
+pub fn demo_reflection() -> Result<()> {
+    let (world, camera) = reflection_scene(1000, 500);
+    let canvas = camera.render(&world);
+    canvas.save_file("demo-reflection.ppm")
+}
+
+pub fn reflection_scene(hsize: usize, vsize: usize) -> (World, Camera) {
+    let floor = Plane::new(1)
+        .set_material(Material::new(Color::make(0.3, 0.3, 0.3), 0.7, 0.3).set_reflective(0.5));
+    let sphere = Sphere::new(2)
+        .set_transform(Matrix::translation(0.0, 1.0, 0.0))
+        .set_material(Material::new(Color::make(1.0, 0.2, 0.1), 0.7, 0.3));
+    let light = Light::point_light(point(-10.0, 10.0, -10.0), WHITE);
+    let world = World::empty()
+        .set_light(light)
+        .add_object(Box::new(floor))
+        .unwrap()
+        .add_object(Box::new(sphere))
+        .unwrap();
+    let camera = Camera::new(hsize, vsize, FRAC_PI_3).set_transform(view_transform(
+        &point(0.0, 1.5, -5.0),
+        &point(0.0, 1.0, 0.0),
+        &vector(0.0, 1.0, 0.0),
+    ));
+    (world, camera)
+}
+
+#[cfg(test)]
+mod demo_tests {
+    use crate::color::BLACK;
+    use crate::demo::reflection_scene;
+
+    #[test]
+    fn reflection_scene_mirrors_the_sphere_in_the_floor() {
+        let (mut world, camera) = reflection_scene(40, 20);
+        let canvas = camera.render(&world);
+        // the floor right below the sphere
+        let reflected = canvas.color_at(20, 17).unwrap();
+        assert_ne!(reflected, BLACK);
+        world
+            .object_mut(1)
+            .unwrap()
+            .material_mut()
+            .unwrap()
+            .reflective = 0.0;
+        let matte = camera.render(&world).color_at(20, 17).unwrap();
+        // the red of the sphere shows up in the floor
+        assert!(reflected.red - matte.red > reflected.blue - matte.blue);
+    }
+}
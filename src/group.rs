@@ -0,0 +1,498 @@
+use crate::bounds::BoundingBox;
+use crate::intersection::Intersection;
+use crate::material::Material;
+use crate::matrix::{Matrix, Transformation};
+use crate::ray::Ray;
+use crate::shape::{world_matrices, Shape};
+use crate::tuple::Tuple;
+use rayon::prelude::*;
+use std::cmp::Ordering;
+
+type Shapes = Vec<Box<dyn Shape>>;
+
+// groups with more direct children than this are intersected in parallel
+const PARALLEL_THRESHOLD: usize = 1024;
+
+// A collection of shapes transformed as a single unit.
+// Children receive the combined transform of their parents when added so their normals can be
+// computed without walking back up the hierarchy.
+pub struct Group {
+    pub id: usize,
+    name: Option<String>,
+    transform: Transformation,
+    end_transform: Option<Transformation>, // pose at the end of the exposure
+    parent_transform: Option<Transformation>,
+    parent_end_transform: Option<Transformation>, // pose of the parents at the end of the exposure
+    material: Material,
+    children: Shapes,
+    // cached as the children can not be modified once added
+    bounds: BoundingBox,
+}
+
+impl Group {
+    pub fn new(id: usize) -> Group {
+        Group {
+            id,
+            name: None,
+            transform: Transformation::default(),
+            end_transform: None,
+            parent_transform: None,
+            parent_end_transform: None,
+            material: Material::default(),
+            children: vec![],
+            bounds: BoundingBox::empty(),
+        }
+    }
+
+    pub fn set_transform(self, transform: Matrix) -> Group {
+        let mut group = Group {
+            transform: Transformation::make(transform),
+            ..self
+        };
+        group.propagate_transform();
+        group
+    }
+
+    // the shape moves from its transform at time 0 to `end_transform` at time 1,
+    // the children follow the motion
+    pub fn set_motion(self, end_transform: Matrix) -> Group {
+        let mut group = Group {
+            end_transform: Some(Transformation::make(end_transform)),
+            ..self
+        };
+        group.propagate_transform();
+        group
+    }
+
+    pub fn set_name(self, name: &str) -> Group {
+        Group {
+            name: Some(name.to_string()),
+            ..self
+        }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    // this group or the first nested group with the given name
+    pub fn named_group(&self, name: &str) -> Option<&Group> {
+        if self.name() == Some(name) {
+            return Some(self);
+        }
+        self.children
+            .iter()
+            .filter_map(|child| child.as_group())
+            .find_map(|group| group.named_group(name))
+    }
+
+    pub fn add_child(mut self, child: Box<dyn Shape>) -> Group {
+        self.push_child(child);
+        self
+    }
+
+    fn push_child(&mut self, mut child: Box<dyn Shape>) {
+        let (start, end) = self.world_matrices();
+        child.set_parent_transform(&start);
+        child.set_parent_end_transform(end.as_ref());
+        self.bounds = self.bounds.merge(&child.parent_space_bounds());
+        self.children.push(child);
+    }
+
+    // moves the children fitting entirely in one half of the bounds out of the group
+    fn partition_children(&mut self) -> (Shapes, Shapes) {
+        let (left_bounds, right_bounds) = self.bounds.split();
+        let mut left = Vec::new();
+        let mut right = Vec::new();
+        let mut remaining = Vec::new();
+        for child in self.children.drain(..) {
+            let child_bounds = child.parent_space_bounds();
+            if left_bounds.contains_box(&child_bounds) {
+                left.push(child);
+            } else if right_bounds.contains_box(&child_bounds) {
+                right.push(child);
+            } else {
+                remaining.push(child);
+            }
+        }
+        self.children = remaining;
+        (left, right)
+    }
+
+    // sub groups are never hit themselves so they reuse the id of the group they come from
+    fn make_subgroup(&mut self, children: Shapes) {
+        let subgroup = children
+            .into_iter()
+            .fold(Group::new(self.id), |group, child| group.add_child(child));
+        self.push_child(Box::new(subgroup));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    // transforms from the group space to the world space at the start and end of the exposure
+    fn world_matrices(&self) -> (Matrix, Option<Matrix>) {
+        world_matrices(
+            self.parent_transform.as_ref(),
+            self.parent_end_transform.as_ref(),
+            &self.transform,
+            self.end_transform.as_ref(),
+        )
+    }
+
+    fn propagate_transform(&mut self) {
+        let (start, end) = self.world_matrices();
+        for child in self.children.iter_mut() {
+            child.set_parent_transform(&start);
+            child.set_parent_end_transform(end.as_ref());
+        }
+    }
+
+    fn intersect_children(&self, local_ray: &Ray) -> Vec<Intersection<'_>> {
+        self.children
+            .iter()
+            .flat_map(|child| {
+                child
+                    .intersect(local_ray)
+                    .into_iter()
+                    .map(|i| i.with_object(child.as_ref()))
+            })
+            .collect()
+    }
+
+    fn intersect_children_parallel(&self, local_ray: &Ray) -> Vec<Intersection<'_>> {
+        self.children
+            .par_iter()
+            .flat_map_iter(|child| {
+                child
+                    .intersect(local_ray)
+                    .into_iter()
+                    .map(|i| i.with_object(child.as_ref()))
+            })
+            .collect()
+    }
+}
+
+impl Shape for Group {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn parent_transform(&self) -> Option<&Transformation> {
+        self.parent_transform.as_ref()
+    }
+
+    fn set_parent_transform(&mut self, parent: &Matrix) {
+        self.parent_transform = Some(Transformation::make(parent.clone()));
+        self.propagate_transform();
+    }
+
+    fn end_transform(&self) -> Option<&Transformation> {
+        self.end_transform.as_ref()
+    }
+
+    fn parent_end_transform(&self) -> Option<&Transformation> {
+        self.parent_end_transform.as_ref()
+    }
+
+    fn set_parent_end_transform(&mut self, parent_end: Option<&Matrix>) {
+        self.parent_end_transform = parent_end.map(|m| Transformation::make(m.clone()));
+        self.propagate_transform();
+    }
+
+    fn children(&self) -> &[Box<dyn Shape>] {
+        &self.children
+    }
+
+    fn as_group(&self) -> Option<&Group> {
+        Some(self)
+    }
+
+    // Builds a bounding volume hierarchy by moving the children into sub groups while there are
+    // at least `threshold` of them. Shapes straddling the split stay in the group.
+    fn divide(&mut self, threshold: usize) {
+        if threshold <= self.children.len() {
+            let (left, right) = self.partition_children();
+            if !left.is_empty() {
+                self.make_subgroup(left);
+            }
+            if !right.is_empty() {
+                self.make_subgroup(right);
+            }
+        }
+        for child in self.children.iter_mut() {
+            child.divide(threshold);
+        }
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    // the children are shaded with their own materials
+    fn material_mut(&mut self) -> Option<&mut Material> {
+        None
+    }
+
+    fn set_transform_mut(&mut self, transform: Matrix) {
+        self.transform = Transformation::make(transform);
+        self.propagate_transform();
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        self.bounds
+    }
+
+    // the intersections carry the ids of the children which were hit
+    fn local_intersect(&self, local_ray: &Ray) -> Vec<Intersection<'_>> {
+        // no need to look at the children if the ray misses all of them
+        if !self.bounds.intersects(local_ray) {
+            return vec![];
+        }
+        let mut intersections = if self.children.len() > PARALLEL_THRESHOLD {
+            self.intersect_children_parallel(local_ray)
+        } else {
+            self.intersect_children(local_ray)
+        };
+        // stable sort, both paths collect the children in the same order
+        intersections.sort_by(|a, b| {
+            a.distance
+                .partial_cmp(&b.distance)
+                .unwrap_or(Ordering::Equal)
+        });
+        intersections
+    }
+
+    // normals are always computed on the children
+    fn local_normal_at(&self, _local_point: &Tuple) -> Tuple {
+        panic!("a group has no normal of its own")
+    }
+}
+
+#[cfg(test)]
+mod group_tests {
+    use crate::bounds::BoundingBox;
+    use crate::epsilon::EPSILON;
+    use crate::group::Group;
+    use crate::matrix::Matrix;
+    use crate::ray::Ray;
+    use crate::shape::{CountingShape, Shape};
+    use crate::sphere::Sphere;
+    use crate::tuple::*;
+    use crate::world::World;
+    use std::f64::consts::PI;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn creating_a_new_group() {
+        let g = Group::new(1);
+        assert_eq!(g.transform().matrix, Matrix::identity());
+        assert!(g.is_empty());
+    }
+
+    #[test]
+    fn intersecting_ray_with_empty_group() {
+        let g = Group::new(1);
+        let r = Ray::new(point_zero(), vector(0.0, 0.0, 1.0));
+        assert!(g.local_intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn intersecting_ray_with_nonempty_group() {
+        let s1 = Sphere::new(2);
+        let s2 = Sphere::new(3).set_transform(Matrix::translation(0.0, 0.0, -3.0));
+        let s3 = Sphere::new(4).set_transform(Matrix::translation(5.0, 0.0, 0.0));
+        let g = Group::new(1)
+            .add_child(Box::new(s1))
+            .add_child(Box::new(s2))
+            .add_child(Box::new(s3));
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = g.local_intersect(&r);
+        assert_eq!(xs.len(), 4);
+        assert_eq!(xs[0].object_id, 3);
+        assert_eq!(xs[1].object_id, 3);
+        assert_eq!(xs[2].object_id, 2);
+        assert_eq!(xs[3].object_id, 2);
+    }
+
+    #[test]
+    fn intersecting_transformed_group() {
+        let s = Sphere::new(2).set_transform(Matrix::translation(5.0, 0.0, 0.0));
+        let g = Group::new(1)
+            .set_transform(Matrix::scaling(2.0, 2.0, 2.0))
+            .add_child(Box::new(s));
+        let r = Ray::new(point(10.0, 0.0, -10.0), vector(0.0, 0.0, 1.0));
+        let xs = g.intersect(&r);
+        assert_eq!(xs.len(), 2);
+    }
+
+    #[test]
+    fn converting_point_from_world_to_object_space() {
+        let s = Sphere::new(3).set_transform(Matrix::translation(5.0, 0.0, 0.0));
+        let g2 = Group::new(2)
+            .set_transform(Matrix::scaling(2.0, 2.0, 2.0))
+            .add_child(Box::new(s));
+        let g1 = Group::new(1)
+            .set_transform(Matrix::rotate_y(PI / 2.0))
+            .add_child(Box::new(g2));
+        let s = &g1.children()[0].children()[0];
+        let p = s.world_to_object(&point(-2.0, 0.0, -10.0));
+        assert!(p.0.abs() < EPSILON && p.1.abs() < EPSILON);
+        assert!((p.2 - -1.0).abs() < EPSILON);
+    }
+
+    #[test]
+    fn converting_normal_from_object_to_world_space() {
+        let s = Sphere::new(3).set_transform(Matrix::translation(5.0, 0.0, 0.0));
+        let g2 = Group::new(2)
+            .set_transform(Matrix::scaling(1.0, 2.0, 3.0))
+            .add_child(Box::new(s));
+        let g1 = Group::new(1)
+            .set_transform(Matrix::rotate_y(PI / 2.0))
+            .add_child(Box::new(g2));
+        let s = &g1.children()[0].children()[0];
+        let third = 3.0_f64.sqrt() / 3.0;
+        let n = s.normal_to_world(&vector(third, third, third));
+        assert!((n.0 - 0.2857).abs() < 0.0001);
+        assert!((n.1 - 0.4286).abs() < 0.0001);
+        assert!((n.2 - -0.8571).abs() < 0.0001);
+    }
+
+    #[test]
+    fn finding_normal_on_child_object() {
+        let s = Sphere::new(3).set_transform(Matrix::translation(5.0, 0.0, 0.0));
+        let g2 = Group::new(2)
+            .set_transform(Matrix::scaling(1.0, 2.0, 3.0))
+            .add_child(Box::new(s));
+        // the outer transform is set last to check that it reaches the nested children
+        let g1 = Group::new(1)
+            .add_child(Box::new(g2))
+            .set_transform(Matrix::rotate_y(PI / 2.0));
+        let s = &g1.children()[0].children()[0];
+        let n = s.normal_at(&point(1.7321, 1.1547, -5.5774));
+        assert!((n.0 - 0.2857).abs() < 0.0001);
+        assert!((n.1 - 0.4286).abs() < 0.0001);
+        assert!((n.2 - -0.8571).abs() < 0.0001);
+    }
+
+    #[test]
+    fn children_are_skipped_when_ray_misses_group_bounds() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let shape = CountingShape::new(2, calls.clone(), Matrix::translation(5.0, 0.0, 0.0));
+        let g = Group::new(1).add_child(Box::new(shape));
+        assert_eq!(
+            g.bounds(),
+            BoundingBox::new(point(4.0, -1.0, -1.0), point(6.0, 1.0, 1.0))
+        );
+
+        let miss = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert!(g.intersect(&miss).is_empty());
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        let hit = Ray::new(point(5.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert!(g.intersect(&hit).is_empty());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    fn spheres_along_x(xs: &[f64]) -> Group {
+        xs.iter().enumerate().fold(Group::new(1), |g, (i, x)| {
+            g.add_child(Box::new(
+                Sphere::new(i + 2).set_transform(Matrix::translation(*x, 0.0, 0.0)),
+            ))
+        })
+    }
+
+    #[test]
+    fn dividing_group_partitions_its_children() {
+        let mut g = spheres_along_x(&[-4.0, -2.0, 2.0, 4.0]);
+        g.divide(4);
+        assert_eq!(g.children().len(), 2);
+        let left = g.children()[0].children();
+        let right = g.children()[1].children();
+        assert_eq!(left.iter().map(|c| c.id()).collect::<Vec<_>>(), vec![2, 3]);
+        assert_eq!(right.iter().map(|c| c.id()).collect::<Vec<_>>(), vec![4, 5]);
+    }
+
+    #[test]
+    fn dividing_group_keeps_children_straddling_the_split() {
+        let mut g = spheres_along_x(&[-4.0, -2.0, 0.0, 2.0, 4.0]);
+        g.divide(4);
+        assert_eq!(g.children().len(), 3);
+        assert_eq!(g.children()[0].id(), 4);
+        assert_eq!(g.children()[1].children().len(), 2);
+        assert_eq!(g.children()[2].children().len(), 2);
+    }
+
+    #[test]
+    fn dividing_group_does_not_change_intersections() {
+        let undivided = spheres_along_x(&[-4.0, -2.0, 0.0, 2.0, 4.0])
+            .set_transform(Matrix::scaling(1.0, 2.0, 1.0));
+        let mut divided = spheres_along_x(&[-4.0, -2.0, 0.0, 2.0, 4.0])
+            .set_transform(Matrix::scaling(1.0, 2.0, 1.0));
+        divided.divide(1);
+        for x in [-4.5, -3.0, -1.5, 0.0, 0.5, 2.0, 3.9, 6.0] {
+            let r = Ray::new(point(x, 0.5, -5.0), vector(0.0, 0.1, 1.0));
+            assert_eq!(divided.intersect(&r), undivided.intersect(&r));
+        }
+        // the sphere with id 3 moved two levels down but keeps the same normals
+        let moved = &divided.children()[1].children()[1].children()[0];
+        assert_eq!(moved.id(), 3);
+        let p = point(-2.0, 2.0, 0.0);
+        assert_eq!(moved.normal_at(&p), undivided.children()[1].normal_at(&p));
+    }
+
+    #[test]
+    fn parallel_intersection_matches_serial_intersection() {
+        // enough spheres to go through the parallel path, with overlapping ones to get ties
+        let xs: Vec<f64> = (0..2000).map(|i| (i % 500) as f64 * 0.1).collect();
+        let g = spheres_along_x(&xs);
+        let r = Ray::new(point(-5.0, 0.0, 0.0), vector(1.0, 0.0, 0.0));
+        let parallel = g.intersect_children_parallel(&r);
+        let mut serial = g.intersect_children(&r);
+        assert_eq!(parallel, serial);
+        serial.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+        let xs = g.local_intersect(&r);
+        assert_eq!(xs.len(), 4000);
+        assert_eq!(xs, serial);
+        assert!(xs.windows(2).all(|w| w[0].distance <= w[1].distance));
+    }
+
+    #[test]
+    fn moving_group_carries_its_children() {
+        let sphere = Sphere::new(3).set_transform(Matrix::translation(2.0, 0.0, 0.0));
+        let inner = Group::new(2).add_child(Box::new(sphere));
+        let end = Matrix::rotate_y(PI / 2.0).multiply(&Matrix::scaling(1.0, 2.0, 1.0));
+        let g = Group::new(1)
+            .add_child(Box::new(inner))
+            .set_motion(end.clone());
+        let s = &g.children()[0].children()[0];
+        assert!(s.parent_end_transform().is_some());
+        // the same sphere standing at the end pose
+        let reference =
+            Sphere::new(4).set_transform(end.multiply(&Matrix::translation(2.0, 0.0, 0.0)));
+        let ray = Ray::new(point(0.0, 0.5, 5.0), vector(0.0, 0.0, -1.0)).set_time(1.0);
+        let xs = g.intersect(&ray);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].object_id, 3);
+        let expected = reference.intersect(&ray);
+        assert!((xs[0].distance - expected[0].distance).abs() < EPSILON);
+        let p = ray.position_at(xs[0].distance);
+        let n = s.world_normal(&p, Some(&xs[0]), 1.0);
+        assert!(tuples_are_equal(&n, &reference.normal_at(&p)));
+        // at the start of the exposure the sphere is elsewhere
+        assert!(g.intersect(&ray.set_time(0.0)).is_empty());
+    }
+
+    #[test]
+    fn group_material_cannot_be_modified_in_place() {
+        let g = Group::new(1).add_child(Box::new(Sphere::new(2)));
+        let mut w = World::empty().add_object(Box::new(g)).unwrap();
+        assert!(w.object_mut(1).unwrap().material_mut().is_none());
+    }
+}
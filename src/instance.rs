@@ -0,0 +1,288 @@
+use crate::bounds::BoundingBox;
+use crate::intersection::Intersection;
+use crate::material::Material;
+use crate::matrix::{Matrix, Transformation};
+use crate::ray::Ray;
+use crate::shape::Shape;
+use crate::tuple::Tuple;
+use std::sync::Arc;
+
+// A placement of a shared shape in the scene.
+// Many instances can point to the same geometry while each one carries its own transform.
+pub struct Instance {
+    pub id: usize,
+    shape: Arc<dyn Shape>,
+    transform: Transformation,
+    end_transform: Option<Transformation>, // pose at the end of the exposure
+    parent_transform: Option<Transformation>,
+    parent_end_transform: Option<Transformation>, // pose of the parents at the end of the exposure
+}
+
+impl Instance {
+    pub fn new(id: usize, shape: Arc<dyn Shape>) -> Instance {
+        Instance {
+            id,
+            shape,
+            transform: Transformation::default(),
+            end_transform: None,
+            parent_transform: None,
+            parent_end_transform: None,
+        }
+    }
+
+    pub fn set_transform(self, transform: Matrix) -> Instance {
+        Instance {
+            transform: Transformation::make(transform),
+            ..self
+        }
+    }
+
+    // the shape moves from its transform at time 0 to `end_transform` at time 1
+    pub fn set_motion(self, end_transform: Matrix) -> Instance {
+        Instance {
+            end_transform: Some(Transformation::make(end_transform)),
+            ..self
+        }
+    }
+
+    pub fn shape(&self) -> &Arc<dyn Shape> {
+        &self.shape
+    }
+}
+
+impl Shape for Instance {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn parent_transform(&self) -> Option<&Transformation> {
+        self.parent_transform.as_ref()
+    }
+
+    fn set_parent_transform(&mut self, parent: &Matrix) {
+        self.parent_transform = Some(Transformation::make(parent.clone()));
+    }
+
+    fn end_transform(&self) -> Option<&Transformation> {
+        self.end_transform.as_ref()
+    }
+
+    fn parent_end_transform(&self) -> Option<&Transformation> {
+        self.parent_end_transform.as_ref()
+    }
+
+    fn set_parent_end_transform(&mut self, parent_end: Option<&Matrix>) {
+        self.parent_end_transform = parent_end.map(|m| Transformation::make(m.clone()));
+    }
+
+    fn material(&self) -> &Material {
+        self.shape.material()
+    }
+
+    // the material belongs to the shared shape and cannot be modified through an instance
+    fn material_mut(&mut self) -> Option<&mut Material> {
+        None
+    }
+
+    fn set_transform_mut(&mut self, transform: Matrix) {
+        self.transform = Transformation::make(transform);
+    }
+
+    // the shared shape lives in the instance space
+    fn bounds(&self) -> BoundingBox {
+        self.shape.parent_space_bounds()
+    }
+
+    // the local ray lives in the instance space which is the parent space of the shared shape,
+    // the hits keep the leaf shape and its uv for shading and record the instance for the normal
+    fn local_intersect(&self, local_ray: &Ray) -> Vec<Intersection<'_>> {
+        self.shape
+            .intersect(local_ray)
+            .into_iter()
+            .map(|i| Intersection {
+                instance: Some(self),
+                ..i.with_object(self.shape.as_ref())
+            })
+            .collect()
+    }
+
+    // the shared shape applies its own transform, the instance transform is applied by `normal_at`
+    fn local_normal_at(&self, local_point: &Tuple) -> Tuple {
+        self.shape.normal_at(local_point)
+    }
+
+    // the leaf of the shared shape computes the normal, e.g. a smooth triangle from the hit uv
+    fn local_normal_at_hit(&self, local_point: &Tuple, hit: &Intersection) -> Tuple {
+        match hit.object {
+            Some(leaf) => leaf.normal_at_hit(local_point, hit),
+            None => self.local_normal_at(local_point),
+        }
+    }
+}
+
+#[cfg(test)]
+mod instance_tests {
+    use crate::color::*;
+    use crate::group::Group;
+    use crate::instance::Instance;
+    use crate::light::Light;
+    use crate::material::Material;
+    use crate::matrix::Matrix;
+    use crate::ray::Ray;
+    use crate::shape::Shape;
+    use crate::sphere::Sphere;
+    use crate::triangle::SmoothTriangle;
+    use crate::tuple::*;
+    use crate::world::World;
+    use std::sync::Arc;
+
+    #[test]
+    fn instances_share_geometry_and_intersect_independently() {
+        let sphere: Arc<dyn Shape> = Arc::new(Sphere::new(1));
+        let left =
+            Instance::new(2, sphere.clone()).set_transform(Matrix::translation(-3.0, 0.0, 0.0));
+        let right =
+            Instance::new(3, sphere.clone()).set_transform(Matrix::translation(3.0, 0.0, 0.0));
+        assert_eq!(Arc::strong_count(&sphere), 3);
+
+        let ray = Ray::new(point(-3.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let left_hits = left.intersect(&ray);
+        assert_eq!(left_hits.len(), 2);
+        assert_eq!(left_hits[0].object_id, 1);
+        assert_eq!(left_hits[0].placed_id(), 2);
+        assert_eq!(left_hits[0].distance, 4.0);
+        assert_eq!(left_hits[1].distance, 6.0);
+        assert!(right.intersect(&ray).is_empty());
+
+        let ray = Ray::new(point(3.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert!(left.intersect(&ray).is_empty());
+        let right_hits = right.intersect(&ray);
+        assert_eq!(right_hits.len(), 2);
+        assert_eq!(right_hits[0].placed_id(), 3);
+    }
+
+    #[test]
+    fn instance_normal_composes_both_transforms() {
+        let sphere: Arc<dyn Shape> =
+            Arc::new(Sphere::new(1).set_transform(Matrix::scaling(1.0, 0.5, 1.0)));
+        let instance = Instance::new(2, sphere).set_transform(Matrix::translation(0.0, 1.0, 0.0));
+        let reference = Sphere::new(3).set_transform(
+            Matrix::translation(0.0, 1.0, 0.0).multiply(&Matrix::scaling(1.0, 0.5, 1.0)),
+        );
+        let value = 2.0_f64.sqrt() / 2.0;
+        let p = point(0.0, 1.0 + value / 2.0, -value);
+        let n1 = instance.normal_at(&p);
+        let n2 = reference.normal_at(&p);
+        assert!((n1.0 - n2.0).abs() < 1e-9);
+        assert!((n1.1 - n2.1).abs() < 1e-9);
+        assert!((n1.2 - n2.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn shading_instances_of_a_shared_group() {
+        let red = || Material {
+            color: RED,
+            ..Material::default()
+        };
+        let mesh: Arc<dyn Shape> = Arc::new(
+            Group::new(10)
+                .set_transform(Matrix::scaling(2.0, 2.0, 2.0))
+                .add_child(Box::new(
+                    Sphere::new(11)
+                        .set_transform(Matrix::translation(0.5, 0.0, 0.0))
+                        .set_material(red()),
+                )),
+        );
+        let light = || Light::point_light(point(-10.0, 10.0, -10.0), WHITE);
+        let instances = World::empty()
+            .set_light(light())
+            .add_objects(vec![
+                Box::new(
+                    Instance::new(1, mesh.clone())
+                        .set_transform(Matrix::translation(-4.0, 0.0, 0.0)),
+                ),
+                Box::new(
+                    Instance::new(2, mesh.clone())
+                        .set_transform(Matrix::translation(4.0, 0.0, 0.0)),
+                ),
+            ])
+            .unwrap();
+        // the same spheres without sharing
+        let reference = World::empty()
+            .set_light(light())
+            .add_objects(vec![
+                Box::new(
+                    Sphere::new(1)
+                        .set_transform(
+                            Matrix::translation(-3.0, 0.0, 0.0)
+                                .multiply(&Matrix::scaling(2.0, 2.0, 2.0)),
+                        )
+                        .set_material(red()),
+                ),
+                Box::new(
+                    Sphere::new(2)
+                        .set_transform(
+                            Matrix::translation(5.0, 0.0, 0.0)
+                                .multiply(&Matrix::scaling(2.0, 2.0, 2.0)),
+                        )
+                        .set_material(red()),
+                ),
+            ])
+            .unwrap();
+        for origin in [point(-3.5, 0.5, -10.0), point(5.5, -0.5, -10.0)] {
+            let r = Ray::new(origin, vector(0.0, 0.0, 1.0));
+            let color = instances.color_at(&r);
+            assert_ne!(color, BLACK);
+            assert_eq!(color, reference.color_at(&r));
+        }
+    }
+
+    #[test]
+    fn instance_normal_uses_the_hit_uv_of_a_shared_smooth_triangle() {
+        let triangle = || {
+            SmoothTriangle::new(
+                11,
+                (
+                    point(0.0, 1.0, 0.0),
+                    point(-1.0, 0.0, 0.0),
+                    point(1.0, 0.0, 0.0),
+                ),
+                (
+                    vector(0.0, 1.0, 0.0),
+                    vector(-1.0, 0.0, 0.0),
+                    vector(1.0, 0.0, 0.0),
+                ),
+            )
+        };
+        let mesh: Arc<dyn Shape> = Arc::new(Group::new(10).add_child(Box::new(triangle())));
+        let instance = Instance::new(1, mesh).set_transform(Matrix::translation(0.0, 0.0, 5.0));
+        let r = Ray::new(point(-0.2, 0.3, -2.0), vector(0.0, 0.0, 1.0));
+        let xs = instance.intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].object_id, 11);
+        let (u, v) = (xs[0].u.unwrap(), xs[0].v.unwrap());
+        let p = r.position_at(xs[0].distance);
+        let n = instance.normal_at_hit(&p, &xs[0]);
+        // interpolated from the vertex normals, not the flat face normal
+        let expected = vector_normalize(&triangle().normal_at_uv(u, v));
+        assert!(tuples_are_equal(&n, &expected));
+        assert!(!tuples_are_equal(&n, &vector(0.0, 0.0, -1.0)));
+    }
+
+    #[test]
+    fn instance_material_cannot_be_modified_in_place() {
+        let sphere: Arc<dyn Shape> = Arc::new(Sphere::new(1));
+        let mut w = World::empty()
+            .add_object(Box::new(Instance::new(2, sphere)))
+            .unwrap();
+        let instance = w.object_mut(2).unwrap();
+        assert!(instance.material_mut().is_none());
+        instance.set_transform_mut(Matrix::translation(0.0, 3.0, 0.0));
+        let r = Ray::new(point(0.0, 3.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(w.intersect_with_ray(&r).len(), 2);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::color::Color;
+use crate::intersection::Intersection;
+use crate::ray::Ray;
+use crate::shape::Shape;
+use crate::world::{World, WorldObject};
+
+// maximum number of bounces for the recursive integrators
+pub const MAX_DEPTH: usize = 5;
+
+// An integrator decides how the color travelling back along a ray is computed.
+// Swapping integrators allows debug/preview renders without touching the World.
+// `O` is the storage of the objects of the rendered world, see `WorldObject`.
+pub trait Integrator<O: WorldObject = Box<dyn Shape>> {
+    fn color_at(&self, world: &World<O>, ray: &Ray, depth: usize) -> Color;
+
+    // the color along the ray and whether it hit an object, used for the alpha channel
+    fn color_and_coverage(&self, world: &World<O>, ray: &Ray, depth: usize) -> (Color, bool) {
+        let color = self.color_at(world, ray, depth);
+        (color, !world.intersect_with_ray(ray).is_empty())
+    }
+}
+
+// Default integrator: direct lighting with shadows and reflections as computed by the World.
+pub struct WhittedIntegrator;
+
+impl<O: WorldObject> Integrator<O> for WhittedIntegrator {
+    fn color_at(&self, world: &World<O>, ray: &Ray, depth: usize) -> Color {
+        world.color_at_with_depth(ray, depth)
+    }
+
+    fn color_and_coverage(&self, world: &World<O>, ray: &Ray, depth: usize) -> (Color, bool) {
+        world.color_and_coverage_at(ray, depth)
+    }
+}
+
+// Debug integrator: maps the surface normal at the hit from [-1, 1] to [0, 1] per channel.
+pub struct NormalsIntegrator;
+
+impl<O: WorldObject> Integrator<O> for NormalsIntegrator {
+    fn color_at(&self, world: &World<O>, ray: &Ray, depth: usize) -> Color {
+        self.color_and_coverage(world, ray, depth).0
+    }
+
+    fn color_and_coverage(&self, world: &World<O>, ray: &Ray, _depth: usize) -> (Color, bool) {
+        let intersections = world.intersect_all(ray);
+        match intersections.iter().find(|i| i.distance > 0.0) {
+            None => (Color::default(), false),
+            Some(hit) => {
+                let comps = Intersection::prepare_computations(hit, ray, world, &intersections);
+                let n = comps.normalv;
+                let color = Color::make((n.0 + 1.0) / 2.0, (n.1 + 1.0) / 2.0, (n.2 + 1.0) / 2.0);
+                (color, true)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod integrator_tests {
+    use crate::color::Color;
+    use crate::integrator::*;
+    use crate::tuple::*;
+
+    #[test]
+    fn whitted_integrator_shades_default_world() {
+        let w = World::default();
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let color = WhittedIntegrator.color_at(&w, &r, MAX_DEPTH);
+        assert_eq!(
+            color,
+            Color::make(0.38066116930395194, 0.4758264616299399, 0.2854958769779639)
+        );
+    }
+
+    #[test]
+    fn normals_integrator_colors_hit_by_normal() {
+        let w = World::default();
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let color = NormalsIntegrator.color_at(&w, &r, MAX_DEPTH);
+        // the normal at the hit is (0, 0, -1)
+        assert_eq!(color, Color::make(0.5, 0.5, 0.0));
+    }
+
+    #[test]
+    fn normals_integrator_returns_black_on_miss() {
+        let w = World::default();
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 1.0, 0.0));
+        let color = NormalsIntegrator.color_at(&w, &r, MAX_DEPTH);
+        assert_eq!(color, Color::default());
+    }
+}
@@ -1,59 +1,156 @@
 use crate::epsilon::EPSILON;
 use crate::ray::Ray;
+use crate::shape::Shape;
 use crate::tuple::*;
-use crate::world::World;
+use crate::world::{World, WorldObject};
+use std::cmp::Ordering;
+use std::fmt;
 
-#[derive(Debug, PartialEq)]
-pub struct Intersection {
+// The intersections borrow the shapes they hit and cannot outlive the world or shape intersected.
+// `object` is filled by the world, groups and CSG around the call to `Shape::intersect`,
+// shapes intersected directly only set `object_id`.
+#[derive(Clone, Copy)]
+pub struct Intersection<'a> {
     pub object_id: usize,
+    pub object: Option<&'a dyn Shape>,
     pub distance: f64,
+    // barycentric coordinates of the hit, only set by triangles
+    pub u: Option<f64>,
+    pub v: Option<f64>,
+    // instance through which a shared shape was hit, it applies its transform to the normal
+    pub instance: Option<&'a dyn Shape>,
 }
 
-pub struct PreparedComputations {
+// the shape reference is left out, `object_id` identifies it
+impl PartialEq for Intersection<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.object_id == other.object_id
+            && self.distance == other.distance
+            && self.u == other.u
+            && self.v == other.v
+    }
+}
+
+impl fmt::Debug for Intersection<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Intersection")
+            .field("object_id", &self.object_id)
+            .field("distance", &self.distance)
+            .field("u", &self.u)
+            .field("v", &self.v)
+            .finish()
+    }
+}
+
+pub struct PreparedComputations<'a> {
     pub object_id: usize,
+    pub object: &'a dyn Shape,
     pub intersection_distance: f64,
     pub point: Tuple,
     pub over_point: Tuple,
+    pub under_point: Tuple,
     pub normalv: Tuple,
     pub eyev: Tuple,
     pub inside: bool,
+    // direction of the bounce, only computed for reflective materials
+    pub reflectv: Option<Tuple>,
+    // refractive indices of the materials on the incoming and outgoing side of the hit,
+    // only computed for transparent materials and 1.0 otherwise
+    pub n1: f64,
+    pub n2: f64,
+    // time of the incoming ray, inherited by the secondary rays
+    pub time: f64,
+}
+
+impl PreparedComputations<'_> {
+    // Schlick approximation of the Fresnel effect: fraction of the light reflected at the hit
+    pub fn schlick(&self) -> f64 {
+        // find the cosine of the angle between the eye and normal vectors
+        let mut cos = vector_dot_product(&self.eyev, &self.normalv);
+        // total internal reflection can only occur if n1 > n2
+        if self.n1 > self.n2 {
+            let n = self.n1 / self.n2;
+            let sin2_t = n.powi(2) * (1.0 - cos.powi(2));
+            if sin2_t > 1.0 {
+                return 1.0;
+            }
+            // when n1 > n2 use cos(theta_t) instead
+            cos = (1.0 - sin2_t).sqrt();
+        }
+        let r0 = ((self.n1 - self.n2) / (self.n1 + self.n2)).powi(2);
+        r0 + (1.0 - r0) * (1.0 - cos).powi(5)
+    }
 }
 
-impl Intersection {
-    pub fn new(object_id: usize, distance: f64) -> Intersection {
+impl<'a> Intersection<'a> {
+    pub fn new(object_id: usize, distance: f64) -> Intersection<'a> {
         Intersection {
             object_id,
+            object: None,
             distance,
+            u: None,
+            v: None,
+            instance: None,
+        }
+    }
+
+    pub fn new_with_uv(object_id: usize, distance: f64, u: f64, v: f64) -> Intersection<'a> {
+        Intersection {
+            object_id,
+            object: None,
+            distance,
+            u: Some(u),
+            v: Some(v),
+            instance: None,
+        }
+    }
+
+    // records the shape intersected unless a shape nested in it already did
+    pub fn with_object(self, object: &'a dyn Shape) -> Intersection<'a> {
+        Intersection {
+            object: self.object.or(Some(object)),
+            ..self
         }
     }
 
+    // id of the shape placed in the scene, the instance for the hits on shared shapes
+    pub fn placed_id(&self) -> usize {
+        self.instance
+            .map_or(self.object_id, |instance| instance.id())
+    }
+
     pub fn tupled(&self) -> (usize, f64) {
         (self.object_id, self.distance)
     }
 
+    // the hit is the intersection with the lowest non-negative distance, NaN distances are skipped
     pub fn hit(intersections: Vec<Intersection>) -> Option<(usize, f64)> {
-        if intersections.is_empty() {
-            None
-        } else {
-            intersections
-                .iter()
-                .filter(|i| i.distance > 0.0)
-                .map(|i| i.tupled())
-                .max_by(|a, b| b.1.partial_cmp(&a.1).unwrap())
-        }
+        intersections
+            .iter()
+            .filter(|i| !i.distance.is_nan() && i.distance >= 0.0)
+            .map(|i| i.tupled())
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal))
     }
 
-    pub fn prepare_computations(
-        intersection: &Intersection,
+    // the shape is looked up by id in the world when the intersection does not carry it
+    pub fn prepare_computations<'w, O: WorldObject>(
+        intersection: &Intersection<'w>,
         ray: &Ray,
-        world: &World,
-    ) -> PreparedComputations {
+        world: &'w World<O>,
+        intersections: &[Intersection],
+    ) -> PreparedComputations<'w> {
         let (object_id, intersection_distance) = intersection.tupled();
         let point = ray.position_at(intersection_distance);
-        let shape = world.objects.iter().find(|&o| o.id() == object_id).unwrap();
+        let shape = intersection
+            .object
+            .unwrap_or_else(|| world.object(object_id).unwrap());
         let eyev = negate_tuple(&ray.direction);
         let (inside, normalv) = {
-            let normalv = shape.normal_at(&point);
+            let normalv = intersection.instance.unwrap_or(shape).world_normal(
+                &point,
+                Some(intersection),
+                ray.time,
+            );
             // negative dot_product means the vectors are pointing in opposite direction
             if vector_dot_product(&normalv, &eyev) < 0.0 {
                 // the normal is inverted for a correct illumination
@@ -65,26 +162,91 @@ impl Intersection {
         // to prevent self shadowing we bump slightly the point in the direction of the normal
         // handpicked epsilon for this context
         let over_point = add_tuple(&point, &scale_tuple(&normalv, EPSILON));
+        // point just below the surface where refracted rays originate
+        let under_point = subtract_tuple(&point, &scale_tuple(&normalv, EPSILON));
+        // diffuse materials skip the reflection and refraction data
+        let material = shape.material();
+        let reflectv =
+            (material.reflective > 0.0).then(|| vector_reflect(&ray.direction, &normalv));
+        let (n1, n2) = if material.transparency > 0.0 {
+            Intersection::refractive_indices(intersection, intersections, world)
+        } else {
+            (1.0, 1.0)
+        };
         PreparedComputations {
             object_id,
+            object: shape,
             intersection_distance,
             point,
             over_point,
+            under_point,
             normalv,
             eyev,
             inside,
+            reflectv,
+            n1,
+            n2,
+            time: ray.time,
+        }
+    }
+
+    // (n1, n2) the refractive indices of the materials the ray leaves and enters at `intersection`,
+    // `intersections` must contain every intersection of the ray sorted by distance, including
+    // the ones behind its origin, to track the objects containing the ray:
+    // an object is entered at its first intersection and exited at the next one,
+    // the innermost container is the last object entered which was not exited yet
+    pub fn refractive_indices<O: WorldObject>(
+        intersection: &Intersection,
+        intersections: &[Intersection],
+        world: &World<O>,
+    ) -> (f64, f64) {
+        let (mut n1, mut n2) = (1.0, 1.0);
+        // placed id and refractive index of the objects containing the ray
+        let mut containers: Vec<(usize, f64)> = Vec::new();
+        for i in intersections {
+            if i == intersection {
+                n1 = containers.last().map_or(1.0, |&(_, n)| n);
+            }
+            // the ray exits an object it was inside of, otherwise it enters it
+            match containers.iter().position(|&(id, _)| id == i.placed_id()) {
+                Some(index) => {
+                    containers.remove(index);
+                }
+                None => {
+                    let shape = i.object.or_else(|| world.object(i.object_id)).unwrap();
+                    containers.push((i.placed_id(), shape.material().refractive_index));
+                }
+            }
+            if i == intersection {
+                n2 = containers.last().map_or(1.0, |&(_, n)| n);
+                break;
+            }
         }
+        (n1, n2)
     }
 }
 
 #[cfg(test)]
 mod intersection_tests {
+    use crate::color::Color;
+    use crate::integrator::MAX_DEPTH;
     use crate::intersection::*;
+    use crate::material::Material;
     use crate::matrix::Matrix;
+    use crate::plane::Plane;
     use crate::sphere::Sphere;
     use crate::tuple::{point, vector};
     use crate::world::World;
 
+    #[test]
+    fn intersection_can_encapsulate_u_and_v() {
+        let i = Intersection::new_with_uv(1, 3.5, 0.2, 0.4);
+        assert_eq!(i.distance, 3.5);
+        assert_eq!(i.u, Some(0.2));
+        assert_eq!(i.v, Some(0.4));
+        assert_eq!(Intersection::new(1, 3.5).u, None);
+    }
+
     #[test]
     fn hit_when_all_positive() {
         let hits = vec![
@@ -130,13 +292,32 @@ mod intersection_tests {
         assert_eq!(tuple, (1, 2.0))
     }
 
+    #[test]
+    fn hit_skips_nan_distances() {
+        let hits = vec![
+            Intersection::new(1, f64::NAN),
+            Intersection::new(1, 3.0),
+            Intersection::new(2, f64::NAN),
+            Intersection::new(2, 1.5),
+            Intersection::new(3, -1.0),
+        ];
+        let tuple = Intersection::hit(hits).unwrap();
+        assert_eq!(tuple, (2, 1.5))
+    }
+
+    #[test]
+    fn hit_when_only_nan_distances() {
+        let hits = vec![Intersection::new(1, f64::NAN)];
+        assert!(Intersection::hit(hits).is_none())
+    }
+
     #[test]
     fn prepare_computation_for_intersection_outside() {
         let ray = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
         let shape = Sphere::new(1);
         let intersection = Intersection::new(1, 4.0);
-        let w = World::empty().add_object(Box::new(shape));
-        let comps = Intersection::prepare_computations(&intersection, &ray, &w);
+        let w = World::empty().add_object(Box::new(shape)).unwrap();
+        let comps = Intersection::prepare_computations(&intersection, &ray, &w, &[intersection]);
         assert_eq!(comps.object_id, intersection.object_id);
         assert_eq!(comps.point, point(0.0, 0.0, -1.0));
         assert_eq!(comps.eyev, vector(0.0, 0.0, -1.0));
@@ -149,8 +330,8 @@ mod intersection_tests {
         let ray = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0));
         let shape = Sphere::new(1);
         let intersection = Intersection::new(1, 1.0);
-        let w = World::empty().add_object(Box::new(shape));
-        let comps = Intersection::prepare_computations(&intersection, &ray, &w);
+        let w = World::empty().add_object(Box::new(shape)).unwrap();
+        let comps = Intersection::prepare_computations(&intersection, &ray, &w, &[intersection]);
         assert_eq!(comps.object_id, intersection.object_id);
         assert_eq!(comps.point, point(0.0, 0.0, 1.0));
         assert_eq!(comps.eyev, vector(0.0, 0.0, -1.0));
@@ -163,9 +344,134 @@ mod intersection_tests {
         let ray = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
         let shape = Sphere::new(1).set_transform(Matrix::translation(0.0, 0.0, 1.0));
         let intersection = Intersection::new(1, 5.0);
-        let w = World::empty().add_object(Box::new(shape));
-        let comps = Intersection::prepare_computations(&intersection, &ray, &w);
+        let w = World::empty().add_object(Box::new(shape)).unwrap();
+        let comps = Intersection::prepare_computations(&intersection, &ray, &w, &[intersection]);
         assert!(comps.over_point.2 < -(f64::EPSILON / 2.0));
         assert!(comps.point.2 > comps.over_point.2);
     }
+
+    #[test]
+    fn precomputing_the_reflection_vector() {
+        let value = 2.0_f64.sqrt() / 2.0;
+        let ray = Ray::new(point(0.0, 1.0, -1.0), vector(0.0, -value, value));
+        let shape = Plane::new(1).set_material(Material::default().set_reflective(0.5));
+        let intersection = Intersection::new(1, 2.0_f64.sqrt());
+        let w = World::empty().add_object(Box::new(shape)).unwrap();
+        let comps = Intersection::prepare_computations(&intersection, &ray, &w, &[intersection]);
+        assert_eq!(comps.reflectv, Some(vector(0.0, value, value)));
+    }
+
+    #[test]
+    fn diffuse_hits_skip_reflection_and_refraction_data() {
+        let w = World::default();
+        let ray = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = w.intersect_all(&ray);
+        let comps = Intersection::prepare_computations(&xs[0], &ray, &w, &xs);
+        assert_eq!(comps.reflectv, None);
+        assert_eq!((comps.n1, comps.n2), (1.0, 1.0));
+        assert_eq!(
+            w.shade_hit(&comps, MAX_DEPTH),
+            Color::make(0.38066116930395194, 0.4758264616299399, 0.2854958769779639)
+        );
+    }
+
+    fn glass_sphere(id: usize) -> Sphere {
+        Sphere::new(id).set_material(
+            Material::default()
+                .set_transparency(1.0)
+                .set_refractive_index(1.5),
+        )
+    }
+
+    #[test]
+    fn the_under_point_is_offset_below_the_surface() {
+        let ray = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let shape = glass_sphere(1).set_transform(Matrix::translation(0.0, 0.0, 1.0));
+        let intersection = Intersection::new(1, 5.0);
+        let w = World::empty().add_object(Box::new(shape)).unwrap();
+        let comps = Intersection::prepare_computations(&intersection, &ray, &w, &[intersection]);
+        assert!(comps.under_point.2 > EPSILON / 2.0);
+        assert!(comps.point.2 < comps.under_point.2);
+    }
+
+    #[test]
+    fn entering_and_exiting_glass_sets_refractive_indices() {
+        let ray = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let w = World::empty()
+            .add_object(Box::new(glass_sphere(1)))
+            .unwrap();
+        let xs = vec![Intersection::new(1, 4.0), Intersection::new(1, 6.0)];
+        let entering = Intersection::prepare_computations(&xs[0], &ray, &w, &xs);
+        assert_eq!((entering.n1, entering.n2), (1.0, 1.5));
+        let exiting = Intersection::prepare_computations(&xs[1], &ray, &w, &xs);
+        assert_eq!((exiting.n1, exiting.n2), (1.5, 1.0));
+    }
+
+    #[test]
+    fn finding_n1_and_n2_at_various_intersections() {
+        let a = glass_sphere(1).set_transform(Matrix::scaling(2.0, 2.0, 2.0));
+        let mut b = glass_sphere(2).set_transform(Matrix::translation(0.0, 0.0, -0.25));
+        b.material.refractive_index = 2.0;
+        let mut c = glass_sphere(3).set_transform(Matrix::translation(0.0, 0.0, 0.25));
+        c.material.refractive_index = 2.5;
+        let w = World::empty()
+            .add_objects(vec![Box::new(a), Box::new(b), Box::new(c)])
+            .unwrap();
+        let ray = Ray::new(point(0.0, 0.0, -4.0), vector(0.0, 0.0, 1.0));
+        let xs = vec![
+            Intersection::new(1, 2.0),
+            Intersection::new(2, 2.75),
+            Intersection::new(3, 3.25),
+            Intersection::new(2, 4.75),
+            Intersection::new(3, 5.25),
+            Intersection::new(1, 6.0),
+        ];
+        let expected = [
+            (1.0, 1.5),
+            (1.5, 2.0),
+            (2.0, 2.5),
+            (2.5, 2.5),
+            (2.5, 1.5),
+            (1.5, 1.0),
+        ];
+        for (i, (n1, n2)) in xs.iter().zip(expected) {
+            assert_eq!(Intersection::refractive_indices(i, &xs, &w), (n1, n2));
+            let comps = Intersection::prepare_computations(i, &ray, &w, &xs);
+            assert_eq!((comps.n1, comps.n2), (n1, n2));
+        }
+    }
+
+    #[test]
+    fn schlick_under_total_internal_reflection() {
+        let value = 2.0_f64.sqrt() / 2.0;
+        let ray = Ray::new(point(0.0, 0.0, value), vector(0.0, 1.0, 0.0));
+        let w = World::empty()
+            .add_object(Box::new(glass_sphere(1)))
+            .unwrap();
+        let xs = vec![Intersection::new(1, -value), Intersection::new(1, value)];
+        let comps = Intersection::prepare_computations(&xs[1], &ray, &w, &xs);
+        assert_eq!(comps.schlick(), 1.0);
+    }
+
+    #[test]
+    fn schlick_with_perpendicular_viewing_angle() {
+        let ray = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 1.0, 0.0));
+        let w = World::empty()
+            .add_object(Box::new(glass_sphere(1)))
+            .unwrap();
+        let xs = vec![Intersection::new(1, -1.0), Intersection::new(1, 1.0)];
+        let comps = Intersection::prepare_computations(&xs[1], &ray, &w, &xs);
+        assert_eq!(comps.schlick(), 0.04000000000000001);
+    }
+
+    #[test]
+    fn schlick_with_small_angle_and_n2_greater_than_n1() {
+        let ray = Ray::new(point(0.0, 0.99, -2.0), vector(0.0, 0.0, 1.0));
+        let w = World::empty()
+            .add_object(Box::new(glass_sphere(1)))
+            .unwrap();
+        let xs = vec![Intersection::new(1, 1.8589)];
+        let comps = Intersection::prepare_computations(&xs[0], &ray, &w, &xs);
+        assert_eq!(comps.schlick(), 0.4887308101221217);
+    }
 }
@@ -0,0 +1,35 @@
+pub mod bounds;
+pub mod camera;
+pub mod canvas;
+pub mod color;
+pub mod cone;
+pub mod csg;
+pub mod cube;
+pub mod cylinder;
+pub mod demo;
+pub mod epsilon;
+pub mod group;
+pub mod instance;
+pub mod integrator;
+pub mod intersection;
+pub mod light;
+pub mod material;
+pub mod math;
+pub mod matrix;
+pub mod noise;
+pub mod obj;
+pub mod pattern;
+pub mod plane;
+pub mod projectile;
+pub mod ray;
+pub mod sampler;
+pub mod scene;
+pub mod shape;
+pub mod sphere;
+pub mod stats;
+pub mod torus;
+pub mod transformation;
+pub mod triangle;
+pub mod tuple;
+pub mod uv;
+pub mod world;
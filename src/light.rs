@@ -1,12 +1,33 @@
 use crate::color::*;
 use crate::material::Material;
 use crate::matrix::Transformation;
+use crate::sampler::{Sampler, MAX_REJECTION_ATTEMPTS};
 use crate::tuple::*;
+use crate::world::{World, WorldObject};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LightKind {
+    Point,
+    // infinitely far away light shining along `direction`, the position is ignored
+    Directional {
+        direction: Tuple,
+    },
+    // cone of light, the angles in radians are measured from the direction
+    Spot {
+        direction: Tuple,
+        inner_angle: f64,
+        outer_angle: f64,
+    },
+}
 
 #[derive(Debug, PartialEq)]
 pub struct Light {
     pub position: Tuple,
     pub intensity: Color,
+    pub kind: LightKind,
+    // point and spot lights with a radius cast soft shadows, see `World::shadow_fraction`
+    pub radius: f64,
+    pub shadow_samples: usize,
 }
 
 impl Light {
@@ -14,10 +35,123 @@ impl Light {
         Light {
             position,
             intensity,
+            kind: LightKind::Point,
+            radius: 0.0,
+            shadow_samples: 1,
         }
     }
 
-    pub fn lighting(
+    pub fn directional(direction: Tuple, intensity: Color) -> Light {
+        Light {
+            position: point_zero(),
+            intensity,
+            kind: LightKind::Directional {
+                direction: vector_normalize(&direction),
+            },
+            radius: 0.0,
+            shadow_samples: 1,
+        }
+    }
+
+    // fully lit within `inner_angle`, dark beyond `outer_angle`
+    pub fn spot(
+        position: Tuple,
+        direction: Tuple,
+        inner_angle: f64,
+        outer_angle: f64,
+        intensity: Color,
+    ) -> Light {
+        Light {
+            position,
+            intensity,
+            kind: LightKind::Spot {
+                direction: vector_normalize(&direction),
+                inner_angle,
+                outer_angle,
+            },
+            radius: 0.0,
+            shadow_samples: 1,
+        }
+    }
+
+    // shadow rays aim at `shadow_samples` random points within `radius` of the light position
+    pub fn set_soft_shadows(self, radius: f64, shadow_samples: usize) -> Light {
+        Light {
+            radius,
+            shadow_samples,
+            ..self
+        }
+    }
+
+    pub fn casts_soft_shadows(&self) -> bool {
+        self.radius > 0.0
+            && self.shadow_samples > 1
+            && !matches!(self.kind, LightKind::Directional { .. })
+    }
+
+    // random point within the sphere of `radius` around the light position,
+    // the light position itself when the sampler keeps missing the sphere
+    pub fn jittered_position(&self, sampler: &mut dyn Sampler) -> Tuple {
+        for _ in 0..MAX_REJECTION_ATTEMPTS {
+            let offset = vector(
+                sampler.next_f64() * 2.0 - 1.0,
+                sampler.next_f64() * 2.0 - 1.0,
+                sampler.next_f64() * 2.0 - 1.0,
+            );
+            if vector_magnitude(&offset) <= 1.0 {
+                return add_tuple(&self.position, &scale_tuple(&offset, self.radius));
+            }
+        }
+        self.position
+    }
+
+    // normalized vector from the point toward the light
+    pub fn direction_from(&self, point: &Tuple) -> Tuple {
+        match self.kind {
+            LightKind::Point | LightKind::Spot { .. } => {
+                vector_normalize(&subtract_tuple(&self.position, point))
+            }
+            LightKind::Directional { direction } => negate_tuple(&direction),
+        }
+    }
+
+    pub fn distance_from(&self, point: &Tuple) -> f64 {
+        match self.kind {
+            LightKind::Point | LightKind::Spot { .. } => {
+                vector_magnitude(&subtract_tuple(&self.position, point))
+            }
+            LightKind::Directional { .. } => f64::INFINITY,
+        }
+    }
+
+    // fraction of the light reaching the point, only spotlights fall off
+    pub fn spot_factor(&self, point: &Tuple) -> f64 {
+        match self.kind {
+            LightKind::Spot {
+                direction,
+                inner_angle,
+                outer_angle,
+            } => {
+                let to_point = negate_tuple(&self.direction_from(point));
+                let angle = vector_dot_product(&to_point, &direction)
+                    .clamp(-1.0, 1.0)
+                    .acos();
+                if angle <= inner_angle {
+                    1.0
+                } else if angle >= outer_angle {
+                    0.0
+                } else {
+                    // smoothstep across the penumbra
+                    let t = (outer_angle - angle) / (outer_angle - inner_angle);
+                    t * t * (3.0 - 2.0 * t)
+                }
+            }
+            _ => 1.0,
+        }
+    }
+
+    // adapter for hard shadows: a point in shadow receives no light
+    pub fn lighting_in_shadow(
         &self,
         material: &Material,
         object_transformation: &Transformation,
@@ -26,48 +160,165 @@ impl Light {
         normalv: &Tuple,
         in_shadow: bool,
     ) -> Color {
-        let color = match &material.pattern {
-            None => material.color,
-            Some(p) => p.pattern_at_object(object_transformation, point),
-        };
-        // combine the surface color with the light's color/intensity
-        let effective_color = color.multiply(&self.intensity);
+        let light_intensity = if in_shadow { 0.0 } else { 1.0 };
+        self.lighting(
+            material,
+            object_transformation,
+            point,
+            eyev,
+            normalv,
+            light_intensity,
+        )
+    }
+
+    // `light_intensity` is the fraction of the light reaching the point, from 0.0 in full shadow to 1.0
+    pub fn lighting(
+        &self,
+        material: &Material,
+        object_transformation: &Transformation,
+        point: &Tuple,
+        eyev: &Tuple,
+        normalv: &Tuple,
+        light_intensity: f64,
+    ) -> Color {
+        let effective_color = self.effective_color(material, object_transformation, point);
         // find the direction to the light source
-        let lightv = vector_normalize(&subtract_tuple(&self.position, point));
+        let lightv = self.direction_from(point);
         // compute the ambient contribution
         let ambient = effective_color.multiply_value(material.ambient);
 
         let mut diffuse = Color::default();
         let mut specular = Color::default();
 
+        // points outside of a spotlight cone only get the ambient contribution
+        let light_fraction = light_intensity * self.spot_factor(point);
+
         // light can't contribute to diffuse & specular
-        if !in_shadow {
+        if light_fraction > 0.0 {
             // light_dot_normal represents the cosine of the angle between the light vector and the normal vector.
             // A negative number means the light is on the other side of the surface.
             let light_dot_normal = vector_dot_product(&lightv, normalv);
 
             if light_dot_normal >= 0.0 {
-                diffuse = effective_color.multiply_value(material.diffuse * light_dot_normal);
+                diffuse = effective_color
+                    .multiply_value(material.diffuse * light_dot_normal * light_fraction);
                 let reflectv = vector_reflect(&negate_tuple(&lightv), normalv);
                 let reflect_dot_eye = vector_dot_product(&reflectv, eyev);
                 if reflect_dot_eye >= 0.0 {
                     let factor = reflect_dot_eye.powf(material.shininess);
-                    specular = self.intensity.multiply_value(material.specular * factor)
+                    specular = self
+                        .intensity
+                        .multiply_value(material.specular * factor * light_fraction)
                 }
             };
         }
         ambient.add(&diffuse).add(&specular)
     }
+
+    // same as `lighting` with a `light_intensity` of 0.0, without computing the unused terms
+    pub fn ambient(
+        &self,
+        material: &Material,
+        object_transformation: &Transformation,
+        point: &Tuple,
+    ) -> Color {
+        self.effective_color(material, object_transformation, point)
+            .multiply_value(material.ambient)
+    }
+
+    // combine the surface color with the light's color/intensity
+    fn effective_color(
+        &self,
+        material: &Material,
+        object_transformation: &Transformation,
+        point: &Tuple,
+    ) -> Color {
+        let color = match &material.pattern {
+            None => material.color,
+            Some(p) => p.pattern_at_object(object_transformation, point),
+        };
+        color.multiply(&self.intensity)
+    }
+}
+
+// rectangular light made of `usteps` x `vsteps` cells spanning `full_uvec` and `full_vvec` from `corner`
+#[derive(Debug, PartialEq)]
+pub struct AreaLight {
+    pub corner: Tuple,
+    pub uvec: Tuple,
+    pub usteps: usize,
+    pub vvec: Tuple,
+    pub vsteps: usize,
+    pub intensity: Color,
+    // center of the light
+    pub position: Tuple,
+}
+
+impl AreaLight {
+    pub fn new(
+        corner: Tuple,
+        full_uvec: Tuple,
+        usteps: usize,
+        full_vvec: Tuple,
+        vsteps: usize,
+        intensity: Color,
+    ) -> AreaLight {
+        let position = add_tuple(
+            &corner,
+            &scale_tuple(&add_tuple(&full_uvec, &full_vvec), 0.5),
+        );
+        AreaLight {
+            corner,
+            uvec: scale_tuple(&full_uvec, 1.0 / usteps as f64),
+            usteps,
+            vvec: scale_tuple(&full_vvec, 1.0 / vsteps as f64),
+            vsteps,
+            intensity,
+            position,
+        }
+    }
+
+    pub fn samples(&self) -> usize {
+        self.usteps * self.vsteps
+    }
+
+    // point within the cell (u, v), the sampler jitters it along each edge of the cell
+    pub fn point_on(&self, u: usize, v: usize, sampler: &mut dyn Sampler) -> Tuple {
+        let u_offset = scale_tuple(&self.uvec, u as f64 + sampler.next_f64());
+        let v_offset = scale_tuple(&self.vvec, v as f64 + sampler.next_f64());
+        add_tuple(&self.corner, &add_tuple(&u_offset, &v_offset))
+    }
+
+    // fraction of the cells visible from the point, from 0.0 in full shadow to 1.0
+    pub fn intensity_at<O: WorldObject>(
+        &self,
+        point: &Tuple,
+        world: &World<O>,
+        sampler: &mut dyn Sampler,
+    ) -> f64 {
+        let mut visible = 0;
+        for v in 0..self.vsteps {
+            for u in 0..self.usteps {
+                let light_position = self.point_on(u, v, sampler);
+                if !world.is_shadowed_from(point, &light_position) {
+                    visible += 1;
+                }
+            }
+        }
+        visible as f64 / self.samples() as f64
+    }
 }
 
 #[cfg(test)]
 mod light_tests {
-    use super::Light;
+    use super::{AreaLight, Light, LightKind};
     use crate::color::*;
     use crate::material::Material;
     use crate::matrix::{Matrix, Transformation};
     use crate::pattern::Pattern;
+    use crate::sampler::{RandomSampler, SequenceSampler};
     use crate::tuple::*;
+    use crate::world::World;
 
     #[test]
     fn creating_point_light() {
@@ -76,6 +327,118 @@ mod light_tests {
         let light = Light::point_light(position, intensity);
         assert_eq!(light.position, position);
         assert_eq!(light.intensity, intensity);
+        assert_eq!(light.kind, LightKind::Point);
+        assert_eq!(light.radius, 0.0);
+        assert!(!light.casts_soft_shadows());
+    }
+
+    #[test]
+    fn jittered_positions_stay_within_the_radius() {
+        let light = Light::point_light(point(1.0, 2.0, 3.0), WHITE).set_soft_shadows(0.5, 16);
+        assert!(light.casts_soft_shadows());
+        for _ in 0..100 {
+            let p = light.jittered_position(&mut RandomSampler);
+            assert!(vector_magnitude(&subtract_tuple(&p, &light.position)) <= 0.5);
+            assert_eq!(p.3, 1.0);
+        }
+    }
+
+    #[test]
+    fn jittered_position_falls_back_to_the_light_position() {
+        let light = Light::point_light(point(1.0, 2.0, 3.0), WHITE).set_soft_shadows(0.5, 16);
+        // every offset lands in a corner of the cube, outside the unit ball
+        let mut sampler = SequenceSampler::new(vec![1.0]);
+        assert_eq!(light.jittered_position(&mut sampler), light.position);
+    }
+
+    #[test]
+    fn creating_directional_light() {
+        let light = Light::directional(vector(0.0, -2.0, 0.0), WHITE);
+        assert_eq!(
+            light.kind,
+            LightKind::Directional {
+                direction: vector(0.0, -1.0, 0.0)
+            }
+        );
+        assert_eq!(
+            light.direction_from(&point(3.0, 4.0, 5.0)),
+            vector(0.0, 1.0, 0.0)
+        );
+        assert_eq!(light.distance_from(&point(3.0, 4.0, 5.0)), f64::INFINITY);
+    }
+
+    #[test]
+    fn directional_light_matches_distant_point_light() {
+        let m = Material::default();
+        let t = Transformation::default();
+        let eye = vector(0.0, 0.0, -1.0);
+        let normal = vector(0.0, 0.0, -1.0);
+        let sun = Light::directional(vector(0.0, -1.0, 1.0), WHITE);
+        let distant = Light::point_light(point(0.0, 1.0e9, -1.0e9), WHITE);
+        for p in [point(0.0, 0.0, 0.0), point(3.0, -2.0, 0.0)] {
+            let r1 = sun.lighting(&m, &t, &p, &eye, &normal, 1.0);
+            let r2 = distant.lighting(&m, &t, &p, &eye, &normal, 1.0);
+            assert!((r1.red - r2.red).abs() < 1e-6);
+            assert!((r1.green - r2.green).abs() < 1e-6);
+            assert!((r1.blue - r2.blue).abs() < 1e-6);
+        }
+        // the direction does not depend on the point lit
+        let value = 0.7363961030678927;
+        let r = sun.lighting(&m, &t, &point(0.0, 0.0, 0.0), &eye, &normal, 1.0);
+        assert!((r.red - value).abs() < 1e-12);
+    }
+
+    fn default_spot() -> Light {
+        Light::spot(
+            point(0.0, 0.0, -10.0),
+            vector(0.0, 0.0, 1.0),
+            10.0_f64.to_radians(),
+            20.0_f64.to_radians(),
+            WHITE,
+        )
+    }
+
+    #[test]
+    fn spotlight_has_full_intensity_along_its_axis() {
+        let light = default_spot();
+        let m = Material::default();
+        let t = Transformation::default();
+        let eye = vector(0.0, 0.0, -1.0);
+        let normal = vector(0.0, 0.0, -1.0);
+        assert_eq!(light.spot_factor(&point(0.0, 0.0, 0.0)), 1.0);
+        let result = light.lighting(&m, &t, &point(0.0, 0.0, 0.0), &eye, &normal, 1.0);
+        assert_eq!(result, Color::make(1.9, 1.9, 1.9));
+    }
+
+    #[test]
+    fn spotlight_does_not_light_outside_outer_cone() {
+        let light = default_spot();
+        let m = Material::default();
+        let t = Transformation::default();
+        let eye = vector(0.0, 0.0, -1.0);
+        let normal = vector(0.0, 0.0, -1.0);
+        let p = point(5.0, 0.0, 0.0);
+        assert_eq!(light.spot_factor(&p), 0.0);
+        // only the ambient part remains
+        let result = light.lighting(&m, &t, &p, &eye, &normal, 1.0);
+        assert_eq!(result, Color::make(0.1, 0.1, 0.1));
+    }
+
+    #[test]
+    fn spotlight_falls_off_in_penumbra() {
+        let light = default_spot();
+        let m = Material::default();
+        let t = Transformation::default();
+        let eye = vector(0.0, 0.0, -1.0);
+        let normal = vector(0.0, 0.0, -1.0);
+        // halfway between the inner and the outer angle
+        let p = point(10.0 * 15.0_f64.to_radians().tan(), 0.0, 0.0);
+        assert!((light.spot_factor(&p) - 0.5).abs() < 1e-9);
+        let result = light.lighting(&m, &t, &p, &eye, &normal, 1.0);
+        let unattenuated = Light::point_light(point(0.0, 0.0, -10.0), WHITE)
+            .lighting(&m, &t, &p, &eye, &normal, 1.0);
+        assert!(result.red > 0.1 && result.red < unattenuated.red);
+        assert!((result.red - (0.1 + (unattenuated.red - 0.1) * 0.5)).abs() < 1e-9);
     }
 
     #[test]
@@ -86,7 +449,7 @@ mod light_tests {
         let normal = vector(0.0, 0.0, -1.0);
         let light = Light::point_light(point(0.0, 0.0, -10.0), Color::make(1.0, 1.0, 1.0));
         let t = Transformation::default();
-        let result = light.lighting(&m, &t, &p, &eye, &normal, false);
+        let result = light.lighting(&m, &t, &p, &eye, &normal, 1.0);
         assert_eq!(result, Color::make(1.9, 1.9, 1.9))
     }
 
@@ -99,7 +462,7 @@ mod light_tests {
         let normal = vector(0.0, 0.0, -1.0);
         let light = Light::point_light(point(0.0, 0.0, -10.0), Color::make(1.0, 1.0, 1.0));
         let t = Transformation::default();
-        let result = light.lighting(&m, &t, &p, &eye, &normal, false);
+        let result = light.lighting(&m, &t, &p, &eye, &normal, 1.0);
         assert_eq!(result, Color::make(1.0, 1.0, 1.0))
     }
 
@@ -111,7 +474,7 @@ mod light_tests {
         let normal = vector(0.0, 0.0, -1.0);
         let light = Light::point_light(point(0.0, 10.0, -10.0), Color::make(1.0, 1.0, 1.0));
         let t = Transformation::default();
-        let result = light.lighting(&m, &t, &p, &eye, &normal, false);
+        let result = light.lighting(&m, &t, &p, &eye, &normal, 1.0);
         let value = 0.7363961030678927;
         assert_eq!(result, Color::make(value, value, value))
     }
@@ -124,7 +487,7 @@ mod light_tests {
         let normal = vector(0.0, 0.0, -1.0);
         let light = Light::point_light(point(0.0, 0.0, 10.0), Color::make(1.0, 1.0, 1.0));
         let t = Transformation::default();
-        let result = light.lighting(&m, &t, &p, &eye, &normal, false);
+        let result = light.lighting(&m, &t, &p, &eye, &normal, 1.0);
         assert_eq!(result, Color::make(0.1, 0.1, 0.1))
     }
 
@@ -136,10 +499,48 @@ mod light_tests {
         let normal = vector(0.0, 0.0, -1.0);
         let light = Light::point_light(point(0.0, 0.0, -10.0), Color::make(1.0, 1.0, 1.0));
         let t = Transformation::default();
-        let result = light.lighting(&m, &t, &p, &eye, &normal, true);
+        let result = light.lighting(&m, &t, &p, &eye, &normal, 0.0);
         assert_eq!(result, Color::make(0.1, 0.1, 0.1))
     }
 
+    #[test]
+    fn lighting_with_partial_light_intensity() {
+        let m = Material::default();
+        let t = Transformation::default();
+        let p = point(0.0, 0.0, 0.0);
+        let eye = vector(0.0, 0.0, -1.0);
+        let normal = vector(0.0, 0.0, -1.0);
+        let light = Light::point_light(point(0.0, 0.0, -10.0), WHITE);
+        let full = light.lighting(&m, &t, &p, &eye, &normal, 1.0);
+        let half = light.lighting(&m, &t, &p, &eye, &normal, 0.5);
+        let ambient = light.lighting(&m, &t, &p, &eye, &normal, 0.0);
+        assert_eq!(ambient, Color::make(0.1, 0.1, 0.1));
+        // diffuse 0.9 and specular 0.9 are halved, the ambient part is untouched
+        assert_eq!(half, Color::make(1.0, 1.0, 1.0));
+        assert_eq!(
+            half.subtract(&ambient),
+            full.subtract(&ambient).multiply_value(0.5)
+        );
+    }
+
+    #[test]
+    fn lighting_in_shadow_adapter_maps_to_intensity() {
+        let m = Material::default();
+        let t = Transformation::default();
+        let p = point(0.0, 0.0, 0.0);
+        let eye = vector(0.0, 0.0, -1.0);
+        let normal = vector(0.0, 0.0, -1.0);
+        let light = Light::point_light(point(0.0, 0.0, -10.0), WHITE);
+        assert_eq!(
+            light.lighting_in_shadow(&m, &t, &p, &eye, &normal, true),
+            light.lighting(&m, &t, &p, &eye, &normal, 0.0)
+        );
+        assert_eq!(
+            light.lighting_in_shadow(&m, &t, &p, &eye, &normal, false),
+            light.lighting(&m, &t, &p, &eye, &normal, 1.0)
+        );
+    }
+
     #[test]
     fn lighting_with_pattern_applied() {
         let p = Pattern::new_stripe(WHITE, BLACK, Matrix::identity());
@@ -150,6 +551,10 @@ mod light_tests {
             specular: 0.,
             shininess: 200.0,
             pattern: Some(p),
+            reflective: 0.0,
+            transparency: 0.0,
+            refractive_index: 1.0,
+            casts_shadow: true,
         };
         let eye = vector(0.0, 0.0, -1.0);
         let normal = vector(0.0, 0.0, -1.0);
@@ -157,11 +562,126 @@ mod light_tests {
         let t = Transformation::default();
 
         let p1 = point(0.9, 0.0, 0.0);
-        let r1 = light.lighting(&m, &t, &p1, &eye, &normal, true);
+        let r1 = light.lighting(&m, &t, &p1, &eye, &normal, 0.0);
         assert_eq!(r1, Color::make(1., 1., 1.));
 
         let p2 = point(1.1, 0.0, 0.0);
-        let r2 = light.lighting(&m, &t, &p2, &eye, &normal, true);
+        let r2 = light.lighting(&m, &t, &p2, &eye, &normal, 0.0);
         assert_eq!(r2, Color::make(0., 0., 0.))
     }
+
+    #[test]
+    fn creating_area_light() {
+        let light = AreaLight::new(
+            point(0.0, 0.0, 0.0),
+            vector(2.0, 0.0, 0.0),
+            4,
+            vector(0.0, 0.0, 1.0),
+            2,
+            WHITE,
+        );
+        assert_eq!(light.corner, point(0.0, 0.0, 0.0));
+        assert_eq!(light.uvec, vector(0.5, 0.0, 0.0));
+        assert_eq!(light.usteps, 4);
+        assert_eq!(light.vvec, vector(0.0, 0.0, 0.5));
+        assert_eq!(light.vsteps, 2);
+        assert_eq!(light.samples(), 8);
+        assert_eq!(light.position, point(1.0, 0.0, 0.5));
+    }
+
+    #[test]
+    fn finding_point_on_area_light() {
+        let light = AreaLight::new(
+            point(0.0, 0.0, 0.0),
+            vector(2.0, 0.0, 0.0),
+            4,
+            vector(0.0, 0.0, 1.0),
+            2,
+            WHITE,
+        );
+        let cases = vec![
+            (0, 0, point(0.25, 0.0, 0.25)),
+            (1, 0, point(0.75, 0.0, 0.25)),
+            (0, 1, point(0.25, 0.0, 0.75)),
+            (2, 0, point(1.25, 0.0, 0.25)),
+            (3, 1, point(1.75, 0.0, 0.75)),
+        ];
+        for (u, v, expected) in cases {
+            // the center of each cell
+            let mut sampler = SequenceSampler::new(vec![0.5]);
+            assert_eq!(light.point_on(u, v, &mut sampler), expected);
+        }
+    }
+
+    #[test]
+    fn finding_jittered_point_on_area_light() {
+        let light = AreaLight::new(
+            point(0.0, 0.0, 0.0),
+            vector(2.0, 0.0, 0.0),
+            4,
+            vector(0.0, 0.0, 1.0),
+            2,
+            WHITE,
+        );
+        let cases = vec![
+            (0, 0, point(0.15, 0.0, 0.35)),
+            (1, 0, point(0.65, 0.0, 0.35)),
+            (0, 1, point(0.15, 0.0, 0.85)),
+            (2, 0, point(1.15, 0.0, 0.35)),
+            (3, 1, point(1.65, 0.0, 0.85)),
+        ];
+        for (u, v, expected) in cases {
+            let mut sampler = SequenceSampler::new(vec![0.3, 0.7]);
+            let p = light.point_on(u, v, &mut sampler);
+            assert!(tuples_are_equal(&p, &expected), "{:?}", p);
+        }
+    }
+
+    #[test]
+    fn area_light_intensity() {
+        let w = World::default();
+        let light = AreaLight::new(
+            point(-0.5, -0.5, -5.0),
+            vector(1.0, 0.0, 0.0),
+            2,
+            vector(0.0, 1.0, 0.0),
+            2,
+            WHITE,
+        );
+        let cases = vec![
+            (point(0.0, 0.0, 2.0), 0.0),
+            (point(1.0, -1.0, 2.0), 0.25),
+            (point(1.5, 0.0, 2.0), 0.5),
+            (point(1.25, 1.25, 3.0), 0.75),
+            (point(0.0, 0.0, -2.0), 1.0),
+        ];
+        for (p, expected) in cases {
+            let mut sampler = SequenceSampler::new(vec![0.5]);
+            assert_eq!(light.intensity_at(&p, &w, &mut sampler), expected);
+        }
+    }
+
+    #[test]
+    fn area_light_intensity_with_jittered_samples() {
+        let w = World::default();
+        let light = AreaLight::new(
+            point(-0.5, -0.5, -5.0),
+            vector(1.0, 0.0, 0.0),
+            2,
+            vector(0.0, 1.0, 0.0),
+            2,
+            WHITE,
+        );
+        let cases = vec![
+            (point(0.0, 0.0, 2.0), 0.0),
+            (point(1.0, -1.0, 2.0), 0.5),
+            (point(1.5, 0.0, 2.0), 0.75),
+            (point(1.25, 1.25, 3.0), 0.75),
+            (point(0.0, 0.0, -2.0), 1.0),
+        ];
+        for (p, expected) in cases {
+            let mut sampler = SequenceSampler::new(vec![0.7, 0.3, 0.9, 0.1, 0.5]);
+            assert_eq!(light.intensity_at(&p, &w, &mut sampler), expected);
+        }
+    }
 }
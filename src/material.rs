@@ -1,7 +1,9 @@
 use crate::color::*;
 use crate::pattern::Pattern;
+use std::io::{Error, ErrorKind, Result};
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Material {
     pub color: Color,
     pub ambient: f64,
@@ -9,10 +11,14 @@ pub struct Material {
     pub specular: f64,
     pub shininess: f64,
     pub pattern: Option<Pattern>,
+    pub reflective: f64,
+    pub transparency: f64,
+    pub refractive_index: f64,
+    pub casts_shadow: bool, // false lets the light go through the object when testing for shadows
 }
 
-impl Material {
-    pub fn default() -> Material {
+impl Default for Material {
+    fn default() -> Material {
         Material {
             color: WHITE,
             ambient: 0.1,
@@ -20,9 +26,15 @@ impl Material {
             specular: 0.9,
             shininess: 200.0,
             pattern: None,
+            reflective: 0.0,
+            transparency: 0.0,
+            refractive_index: 1.0,
+            casts_shadow: true,
         }
     }
+}
 
+impl Material {
     pub fn new(color: Color, diffuse: f64, specular: f64) -> Material {
         Material {
             color,
@@ -31,6 +43,10 @@ impl Material {
             specular,
             shininess: 200.0,
             pattern: None,
+            reflective: 0.0,
+            transparency: 0.0,
+            refractive_index: 1.0,
+            casts_shadow: true,
         }
     }
 
@@ -47,6 +63,60 @@ impl Material {
             specular,
             shininess: 200.0,
             pattern: Some(pattern),
+            reflective: 0.0,
+            transparency: 0.0,
+            refractive_index: 1.0,
+            casts_shadow: true,
+        }
+    }
+
+    // clear glass, most of the color comes from the refracted and reflected light
+    pub fn glass() -> Material {
+        Material {
+            color: BLACK,
+            ambient: 0.0,
+            diffuse: 0.1,
+            specular: 1.0,
+            shininess: 300.0,
+            reflective: 0.9,
+            transparency: 1.0,
+            refractive_index: 1.5,
+            ..Material::default()
+        }
+    }
+
+    // perfect mirror showing only the reflected light and the highlights
+    pub fn mirror() -> Material {
+        Material {
+            color: BLACK,
+            ambient: 0.0,
+            diffuse: 0.0,
+            specular: 1.0,
+            shininess: 300.0,
+            reflective: 1.0,
+            ..Material::default()
+        }
+    }
+
+    // polished surface tinted by `color` with sharp highlights
+    pub fn metal(color: Color) -> Material {
+        Material {
+            color,
+            diffuse: 0.3,
+            specular: 1.0,
+            shininess: 300.0,
+            reflective: 0.6,
+            ..Material::default()
+        }
+    }
+
+    // rough surface without highlights nor reflection
+    pub fn matte(color: Color) -> Material {
+        Material {
+            color,
+            diffuse: 0.9,
+            specular: 0.0,
+            ..Material::default()
         }
     }
 
@@ -56,11 +126,137 @@ impl Material {
             ..self
         }
     }
+
+    pub fn set_reflective(self, reflective: f64) -> Material {
+        Material { reflective, ..self }
+    }
+
+    pub fn set_transparency(self, transparency: f64) -> Material {
+        Material {
+            transparency,
+            ..self
+        }
+    }
+
+    pub fn set_refractive_index(self, refractive_index: f64) -> Material {
+        Material {
+            refractive_index,
+            ..self
+        }
+    }
+
+    pub fn set_casts_shadow(self, casts_shadow: bool) -> Material {
+        Material {
+            casts_shadow,
+            ..self
+        }
+    }
+}
+
+// starts from `Material::default()`, `build` rejects the values producing meaningless shading
+#[derive(Default)]
+pub struct MaterialBuilder {
+    material: Material,
+}
+
+impl MaterialBuilder {
+    pub fn new() -> MaterialBuilder {
+        MaterialBuilder::default()
+    }
+
+    pub fn color(mut self, color: Color) -> MaterialBuilder {
+        self.material.color = color;
+        self
+    }
+
+    pub fn pattern(mut self, pattern: Pattern) -> MaterialBuilder {
+        self.material.pattern = Some(pattern);
+        self
+    }
+
+    pub fn ambient(mut self, ambient: f64) -> MaterialBuilder {
+        self.material.ambient = ambient;
+        self
+    }
+
+    pub fn diffuse(mut self, diffuse: f64) -> MaterialBuilder {
+        self.material.diffuse = diffuse;
+        self
+    }
+
+    pub fn specular(mut self, specular: f64) -> MaterialBuilder {
+        self.material.specular = specular;
+        self
+    }
+
+    pub fn shininess(mut self, shininess: f64) -> MaterialBuilder {
+        self.material.shininess = shininess;
+        self
+    }
+
+    pub fn reflective(mut self, reflective: f64) -> MaterialBuilder {
+        self.material.reflective = reflective;
+        self
+    }
+
+    pub fn transparency(mut self, transparency: f64) -> MaterialBuilder {
+        self.material.transparency = transparency;
+        self
+    }
+
+    pub fn refractive_index(mut self, refractive_index: f64) -> MaterialBuilder {
+        self.material.refractive_index = refractive_index;
+        self
+    }
+
+    pub fn casts_shadow(mut self, casts_shadow: bool) -> MaterialBuilder {
+        self.material.casts_shadow = casts_shadow;
+        self
+    }
+
+    // the sum of ambient, diffuse and specular can exceed 1.0, it only brightens the result
+    pub fn build(self) -> Result<Material> {
+        let m = &self.material;
+        let unit_fields = [
+            ("ambient", m.ambient),
+            ("diffuse", m.diffuse),
+            ("specular", m.specular),
+            ("reflective", m.reflective),
+            ("transparency", m.transparency),
+        ];
+        if let Some((name, value)) = unit_fields
+            .iter()
+            .find(|(_, value)| !(0.0..=1.0).contains(value))
+        {
+            return Err(invalid(&format!(
+                "{} must be between 0 and 1 but got {}",
+                name, value
+            )));
+        }
+        let positive_fields = [
+            ("shininess", m.shininess),
+            ("refractive index", m.refractive_index),
+        ];
+        if let Some((name, value)) = positive_fields
+            .iter()
+            .find(|(_, value)| value.is_nan() || *value <= 0.0)
+        {
+            return Err(invalid(&format!(
+                "{} must be positive but got {}",
+                name, value
+            )));
+        }
+        Ok(self.material)
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidInput, message)
 }
 
 #[cfg(test)]
 mod material_tests {
-    use super::Material;
+    use super::{Material, MaterialBuilder};
     use crate::color::*;
 
     #[test]
@@ -72,5 +268,94 @@ mod material_tests {
         assert_eq!(material.specular, 0.9);
         assert_eq!(material.shininess, 200.0);
         assert!(material.pattern.is_none());
+        assert_eq!(material.reflective, 0.0);
+        assert_eq!(material.transparency, 0.0);
+        assert_eq!(material.refractive_index, 1.0);
+        assert!(material.casts_shadow);
+    }
+
+    #[test]
+    fn material_builder_keeps_valid_values() {
+        let material = MaterialBuilder::new()
+            .color(RED)
+            .ambient(0.2)
+            .diffuse(0.6)
+            .specular(0.0)
+            .shininess(50.0)
+            .reflective(1.0)
+            .transparency(0.5)
+            .refractive_index(1.33)
+            .build()
+            .unwrap();
+        assert_eq!(material.color, RED);
+        assert_eq!(material.ambient, 0.2);
+        assert_eq!(material.diffuse, 0.6);
+        assert_eq!(material.specular, 0.0);
+        assert_eq!(material.shininess, 50.0);
+        assert_eq!(material.reflective, 1.0);
+        assert_eq!(material.transparency, 0.5);
+        assert_eq!(material.refractive_index, 1.33);
+        assert_eq!(MaterialBuilder::new().build().unwrap(), Material::default());
+    }
+
+    #[test]
+    fn material_builder_rejects_invalid_values() {
+        let error = MaterialBuilder::new().shininess(-10.0).build().unwrap_err();
+        assert!(error.to_string().contains("shininess"), "{}", error);
+        assert!(MaterialBuilder::new().shininess(f64::NAN).build().is_err());
+        assert!(MaterialBuilder::new().reflective(1.5).build().is_err());
+        assert!(MaterialBuilder::new().transparency(-0.1).build().is_err());
+        assert!(MaterialBuilder::new().ambient(2.0).build().is_err());
+        assert!(MaterialBuilder::new()
+            .refractive_index(0.0)
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn material_presets() {
+        let glass = Material::glass();
+        assert_eq!(glass.transparency, 1.0);
+        assert_eq!(glass.refractive_index, 1.5);
+        assert_eq!(glass.reflective, 0.9);
+        assert_eq!(glass.diffuse, 0.1);
+
+        let mirror = Material::mirror();
+        assert_eq!(mirror.reflective, 1.0);
+        assert_eq!(mirror.transparency, 0.0);
+        assert_eq!(mirror.diffuse, 0.0);
+
+        let metal = Material::metal(RED);
+        assert_eq!(metal.color, RED);
+        assert_eq!(metal.reflective, 0.6);
+        assert_eq!(metal.specular, 1.0);
+        assert_eq!(metal.shininess, 300.0);
+
+        let matte = Material::matte(BLUE);
+        assert_eq!(matte.color, BLUE);
+        assert_eq!(matte.specular, 0.0);
+        assert_eq!(matte.reflective, 0.0);
+        assert_eq!(matte.diffuse, 0.9);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn default_material_round_trips_through_json() {
+        let json = serde_json::to_string(&Material::default()).unwrap();
+        let material: Material = serde_json::from_str(&json).unwrap();
+        assert_eq!(material, Material::default());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn patterned_material_round_trips_through_json() {
+        use crate::matrix::Matrix;
+        use crate::pattern::Pattern;
+        let pattern = Pattern::new_stripe(WHITE, BLACK, Matrix::scaling(0.5, 0.5, 0.5));
+        let material = Material::new_with_pattern(WHITE, 0.7, 0.3, pattern);
+        let json = serde_json::to_string(&material).unwrap();
+        assert!(json.contains(r#""type":"StripePattern""#), "{}", json);
+        let back: Material = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, material);
     }
 }
@@ -0,0 +1,133 @@
+// real roots of a x^4 + b x^3 + c x^2 + d x + e = 0 sorted in ascending order
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    solve_polynomial(&[a, b, c, d, e])
+}
+
+// real roots of a polynomial given from the highest degree coefficient down, sorted in ascending order
+// the roots of the derivative split the real line into monotonic intervals which are bisected
+pub fn solve_polynomial(coefficients: &[f64]) -> Vec<f64> {
+    let coefficients = match coefficients.iter().position(|c| *c != 0.0) {
+        Some(first) => &coefficients[first..],
+        None => return vec![],
+    };
+    let degree = coefficients.len() - 1;
+    match degree {
+        0 => vec![],
+        1 => vec![-coefficients[1] / coefficients[0]],
+        _ => {
+            let derivative: Vec<f64> = coefficients[..degree]
+                .iter()
+                .enumerate()
+                .map(|(i, c)| c * (degree - i) as f64)
+                .collect();
+            // Cauchy bound, every real root lies within [-bound, bound]
+            let bound = 1.0
+                + coefficients[1..]
+                    .iter()
+                    .map(|c| (c / coefficients[0]).abs())
+                    .fold(0.0, f64::max);
+            let mut points = vec![-bound];
+            points.extend(
+                solve_polynomial(&derivative)
+                    .into_iter()
+                    .filter(|x| x.abs() < bound),
+            );
+            points.push(bound);
+            // a root located on an extremum does not change the sign of the polynomial
+            let tolerance = 1e-10 * coefficients.iter().fold(0.0, |m: f64, c| m.max(c.abs()));
+            let mut roots: Vec<f64> = Vec::new();
+            for window in points.windows(2) {
+                let (low, high) = (window[0], window[1]);
+                let (f_low, f_high) = (evaluate(coefficients, low), evaluate(coefficients, high));
+                if f_low.abs() <= tolerance {
+                    roots.push(low);
+                } else if f_low.signum() != f_high.signum() && f_high.abs() > tolerance {
+                    roots.push(bisect(coefficients, low, high, f_low));
+                }
+            }
+            if evaluate(coefficients, bound).abs() <= tolerance {
+                roots.push(bound);
+            }
+            roots.dedup_by(|a, b| (*a - *b).abs() < 1e-9);
+            roots
+        }
+    }
+}
+
+// Horner's method
+fn evaluate(coefficients: &[f64], x: f64) -> f64 {
+    coefficients.iter().fold(0.0, |acc, c| acc.mul_add(x, *c))
+}
+
+// the polynomial changes sign exactly once between `low` and `high`
+fn bisect(coefficients: &[f64], mut low: f64, mut high: f64, f_low: f64) -> f64 {
+    loop {
+        let middle = low + (high - low) / 2.0;
+        // the interval cannot shrink anymore
+        if middle <= low || middle >= high {
+            return middle;
+        }
+        let f_middle = evaluate(coefficients, middle);
+        if f_middle == 0.0 {
+            return middle;
+        }
+        if f_middle.signum() == f_low.signum() {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+}
+
+#[cfg(test)]
+mod math_tests {
+    use crate::epsilon::EPSILON;
+    use crate::math::*;
+
+    fn assert_roots(roots: Vec<f64>, expected: &[f64]) {
+        assert_eq!(roots.len(), expected.len(), "roots {:?}", roots);
+        for (root, expected) in roots.iter().zip(expected) {
+            assert!((root - expected).abs() < EPSILON, "roots {:?}", roots);
+        }
+    }
+
+    #[test]
+    fn quartic_with_four_distinct_roots() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        assert_roots(
+            solve_quartic(1.0, -10.0, 35.0, -50.0, 24.0),
+            &[1.0, 2.0, 3.0, 4.0],
+        );
+    }
+
+    #[test]
+    fn quartic_with_two_real_roots() {
+        // (x^2 - 1)(x^2 + 1)
+        assert_roots(solve_quartic(1.0, 0.0, 0.0, 0.0, -1.0), &[-1.0, 1.0]);
+        // (x + 0.5)(x - 2.5)(x^2 + 4)
+        assert_roots(solve_quartic(2.0, -4.0, 5.5, -16.0, -10.0), &[-0.5, 2.5]);
+    }
+
+    #[test]
+    fn quartic_without_real_roots() {
+        assert!(solve_quartic(1.0, 0.0, 0.0, 0.0, 1.0).is_empty());
+        assert!(solve_quartic(1.0, 0.0, 5.0, 0.0, 4.0).is_empty());
+    }
+
+    #[test]
+    fn quartic_with_a_double_root() {
+        // (x - 1)^2 (x + 2)(x - 3)
+        assert_roots(
+            solve_quartic(1.0, -3.0, -3.0, 11.0, -6.0),
+            &[-2.0, 1.0, 3.0],
+        );
+    }
+
+    #[test]
+    fn lower_degree_polynomials() {
+        // (x - 2)(x + 3)
+        assert_roots(solve_quartic(0.0, 0.0, 1.0, 1.0, -6.0), &[-3.0, 2.0]);
+        assert_roots(solve_polynomial(&[2.0, -1.0]), &[0.5]);
+        assert!(solve_polynomial(&[0.0, 0.0]).is_empty());
+    }
+}
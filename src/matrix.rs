@@ -1,6 +1,19 @@
-use crate::tuple::Tuple;
+use crate::epsilon::EPSILON;
+use crate::tuple::{vector, Tuple};
+use std::io::{Error, ErrorKind, Result};
+use std::ops::Deref;
+use std::sync::{Arc, LazyLock};
+
+const IDENTITY_CONTENT: [f64; 16] = [
+    1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
+];
+
+// the content lives on the heap so the shared identity is built on first use,
+// borrow it instead of calling `Matrix::identity()` to avoid an allocation
+pub static IDENTITY: LazyLock<Matrix> = LazyLock::new(Matrix::identity);
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Matrix {
     pub size: usize,
     pub content: Vec<f64>,
@@ -14,6 +27,7 @@ impl Matrix {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn make_matrix_3(
         aa: f64,
         ab: f64,
@@ -31,6 +45,7 @@ impl Matrix {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn make_matrix_4(
         aa: f64,
         ab: f64,
@@ -57,11 +72,56 @@ impl Matrix {
         }
     }
 
-    // TODO make const?
+    // checked constructor for square matrices loaded from data
+    pub fn from_vec(size: usize, content: Vec<f64>) -> Result<Matrix> {
+        if size == 0 || content.len() != size * size {
+            Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "a {}x{} matrix requires {} values but got {}",
+                    size,
+                    size,
+                    size * size,
+                    content.len()
+                ),
+            ))
+        } else {
+            Ok(Matrix { size, content })
+        }
+    }
+
+    pub fn from_rows(rows: Vec<Vec<f64>>) -> Result<Matrix> {
+        let size = rows.len();
+        if let Some(row) = rows.iter().find(|row| row.len() != size) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "a {}x{} matrix requires rows of {} values but got {}",
+                    size,
+                    size,
+                    size,
+                    row.len()
+                ),
+            ));
+        }
+        Matrix::from_vec(size, rows.into_iter().flatten().collect())
+    }
+
+    // component-wise comparison within EPSILON, `==` is exact
+    pub fn near_eq(&self, other: &Matrix) -> bool {
+        self.size == other.size
+            && self
+                .content
+                .iter()
+                .zip(&other.content)
+                .all(|(a, b)| (a - b).abs() < EPSILON)
+    }
+
     pub fn identity() -> Matrix {
-        Matrix::make_matrix_4(
-            1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0,
-        )
+        Matrix {
+            size: 4,
+            content: IDENTITY_CONTENT.to_vec(),
+        }
     }
 
     pub fn translation(x: f64, y: f64, z: f64) -> Matrix {
@@ -80,35 +140,49 @@ impl Matrix {
         self.content.get(y + x * self.size).copied().unwrap()
     }
 
+    // Gauss-Jordan elimination with partial pivoting, O(n^3)
     pub fn inverse(&self) -> Matrix {
-        let det = self.determinant();
-        if det == 0.0 {
-            panic!("matrix cannot be inverted because its determinant is 0")
-        } else {
-            let s = self.size;
-            let s_square = s * s;
-            let mut inverse: Vec<f64> = Vec::with_capacity(s_square);
-            // init vector
-            for index in 0..s_square {
-                inverse.insert(index, 0.0);
+        let s = self.size;
+        let mut m = self.content.clone();
+        // starts as the identity and receives the same row operations as `m`
+        let mut inverse = vec![0.0; s * s];
+        for i in 0..s {
+            inverse[i * s + i] = 1.0;
+        }
+        for col in 0..s {
+            // the largest pivot limits the rounding errors
+            let pivot_row = (col..s)
+                .max_by(|&a, &b| m[a * s + col].abs().total_cmp(&m[b * s + col].abs()))
+                .unwrap();
+            let pivot = m[pivot_row * s + col];
+            if pivot == 0.0 {
+                panic!("matrix cannot be inverted because its determinant is 0")
             }
-            for row in 0..s {
-                for col in 0..s {
-                    let col_index = col * s;
-                    let cofactor = self.cofactor(row, col);
-                    // we perform the transpose operation at insertion time
-                    // by switching row/col in the target matrix
-                    let target_index = row + col_index;
-                    let precise_value = cofactor / det;
-                    inverse.remove(target_index);
-                    inverse.insert(target_index, precise_value);
+            if pivot_row != col {
+                for k in 0..s {
+                    m.swap(pivot_row * s + k, col * s + k);
+                    inverse.swap(pivot_row * s + k, col * s + k);
                 }
             }
-            Matrix {
-                size: s,
-                content: inverse,
+            for k in 0..s {
+                m[col * s + k] /= pivot;
+                inverse[col * s + k] /= pivot;
+            }
+            // eliminate the column from every other row
+            for row in (0..s).filter(|&row| row != col) {
+                let factor = m[row * s + col];
+                if factor != 0.0 {
+                    for k in 0..s {
+                        m[row * s + k] -= factor * m[col * s + k];
+                        inverse[row * s + k] -= factor * inverse[col * s + k];
+                    }
+                }
             }
         }
+        Matrix {
+            size: s,
+            content: inverse,
+        }
     }
 
     pub fn determinant(&self) -> f64 {
@@ -150,7 +224,7 @@ impl Matrix {
 
     pub fn cofactor(&self, row: usize, col: usize) -> f64 {
         let minor = self.minor(row, col);
-        if (row + col) % 2 == 0 {
+        if (row + col).is_multiple_of(2) {
             minor
         } else {
             -minor
@@ -173,6 +247,20 @@ impl Matrix {
         }
     }
 
+    // componentwise linear interpolation, `t` = 0 gives `self` and `t` = 1 gives `other`
+    pub fn lerp(&self, other: &Matrix, t: f64) -> Matrix {
+        let content = self
+            .content
+            .iter()
+            .zip(&other.content)
+            .map(|(a, b)| a + (b - a) * t)
+            .collect();
+        Matrix {
+            size: self.size,
+            content,
+        }
+    }
+
     pub fn multiply(&self, m: &Matrix) -> Matrix {
         let s = self.size;
         let mut res: Vec<f64> = Vec::with_capacity(s * s);
@@ -225,6 +313,15 @@ impl Matrix {
         )
     }
 
+    // the upper left 3x3 part only, the translation and the w component of the vector are ignored
+    pub fn multiply_vector3(&self, v: &Tuple) -> Tuple {
+        let line = |row: usize| {
+            self.at(row, 0)
+                .mul_add(v.0, self.at(row, 1).mul_add(v.1, self.at(row, 2) * v.2))
+        };
+        vector(line(0), line(1), line(2))
+    }
+
     // using mul_add https://rust-lang.github.io/rust-clippy/master/index.html#manual_mul_add
     fn compute_line(at1: f64, at2: f64, at3: f64, at4: f64, t: &Tuple) -> f64 {
         at1.mul_add(t.0, at2.mul_add(t.1, at3.mul_add(t.2, at4 * t.3)))
@@ -261,31 +358,67 @@ impl Matrix {
     }
 }
 
-// structure to cache redundant operations on the transform field
+// structure to cache redundant operations on the transform field,
+// cloning only bumps a reference count and every default transformation shares the same identity
 #[derive(Debug, PartialEq, Clone)]
-pub struct Transformation {
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "Matrix", into = "Matrix")
+)]
+pub struct Transformation(Arc<TransformationMatrices>);
+
+#[derive(Debug, PartialEq)]
+pub struct TransformationMatrices {
     pub matrix: Matrix,
     pub inverse: Matrix,
     pub inverse_transpose: Matrix,
 }
 
-impl Transformation {
-    pub fn default() -> Self {
-        Transformation {
-            matrix: Matrix::identity(),
-            inverse: Matrix::identity(),
-            inverse_transpose: Matrix::identity(),
-        }
+static IDENTITY_TRANSFORMATION: LazyLock<Transformation> = LazyLock::new(|| {
+    Transformation(Arc::new(TransformationMatrices {
+        matrix: IDENTITY.clone(),
+        inverse: IDENTITY.clone(),
+        inverse_transpose: IDENTITY.clone(),
+    }))
+});
+
+impl Deref for Transformation {
+    type Target = TransformationMatrices;
+
+    fn deref(&self) -> &TransformationMatrices {
+        &self.0
+    }
+}
+
+// only the matrix is serialized, the cached inverses are recomputed when deserializing
+impl From<Matrix> for Transformation {
+    fn from(matrix: Matrix) -> Self {
+        Transformation::make(matrix)
     }
+}
+
+impl From<Transformation> for Matrix {
+    fn from(transformation: Transformation) -> Self {
+        transformation.matrix.clone()
+    }
+}
+
+impl Default for Transformation {
+    fn default() -> Self {
+        IDENTITY_TRANSFORMATION.clone()
+    }
+}
 
+impl Transformation {
     pub fn make(transform: Matrix) -> Self {
         let inverse = Matrix::inverse(&transform);
         let inverse_transpose = inverse.transpose();
-        Transformation {
+        Transformation(Arc::new(TransformationMatrices {
             matrix: transform,
             inverse,
             inverse_transpose,
-        }
+        }))
     }
 }
 
@@ -293,6 +426,7 @@ impl Transformation {
 mod matrix_tests {
     use crate::matrix::*;
     use crate::tuple::*;
+    use std::f64::consts::FRAC_1_SQRT_2;
 
     #[test]
     fn make_matrix_4_valid() {
@@ -308,6 +442,45 @@ mod matrix_tests {
         assert_eq!(m.at(3, 2), 15.5);
     }
 
+    #[test]
+    fn from_rows_valid() {
+        let m = Matrix::from_rows(vec![
+            vec![1.0, 2.0, 3.0, 4.0],
+            vec![5.5, 6.5, 7.5, 8.5],
+            vec![9.0, 10.0, 11.0, 12.0],
+            vec![13.5, 14.5, 15.5, 16.5],
+        ])
+        .unwrap();
+        assert_eq!(m.size, 4);
+        assert_eq!(m.at(0, 3), 4.0);
+        assert_eq!(m.at(1, 0), 5.5);
+        assert_eq!(m.at(3, 2), 15.5);
+        assert_eq!(
+            m,
+            Matrix::make_matrix_4(
+                1.0, 2.0, 3.0, 4.0, 5.5, 6.5, 7.5, 8.5, 9.0, 10.0, 11.0, 12.0, 13.5, 14.5, 15.5,
+                16.5,
+            )
+        );
+    }
+
+    #[test]
+    fn from_rows_mismatched_length() {
+        let m = Matrix::from_rows(vec![vec![1.0, 2.0], vec![3.0]]);
+        assert!(m.is_err());
+        let m = Matrix::from_rows(vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]]);
+        assert!(m.is_err());
+    }
+
+    #[test]
+    fn from_vec_checks_content_length() {
+        let m = Matrix::from_vec(2, vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+        assert_eq!(m, Matrix::make_matrix_2(1.0, 2.0, 3.0, 4.0));
+        let err = Matrix::from_vec(3, vec![1.0, 2.0, 3.0, 4.0]).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(Matrix::from_vec(0, vec![]).is_err());
+    }
+
     #[test]
     fn make_matrix_3_valid() {
         let m = Matrix::make_matrix_3(-3.0, 5.0, 0.0, 1.0, -2.0, -7.0, 0.0, 1.0, 1.0);
@@ -374,6 +547,35 @@ mod matrix_tests {
         assert_eq!(m.multiply_tuple(&t), (18.0, 24.0, 33.0, 1.0));
     }
 
+    #[test]
+    fn matrix_multiply_by_vector_ignores_translation() {
+        let m = Matrix::make_matrix_4(
+            1.0, 2.0, 3.0, 4.0, 2.0, 4.0, 4.0, 2.0, 8.0, 6.0, 4.0, 1.0, 0.0, 0.0, 0.0, 1.0,
+        );
+        let v = vector(1.0, 2.0, 3.0);
+        assert_eq!(m.multiply_vector3(&v), vector(14.0, 22.0, 32.0));
+        assert_eq!(m.multiply_vector3(&v), m.multiply_tuple(&v));
+    }
+
+    #[test]
+    fn shared_identity_equals_runtime_identity() {
+        assert_eq!(*IDENTITY, Matrix::identity());
+        assert_eq!(IDENTITY.content, Matrix::identity().content);
+        assert_eq!(IDENTITY.size, 4);
+    }
+
+    #[test]
+    fn default_transformations_share_the_identity() {
+        let transformations: Vec<Transformation> =
+            (0..1000).map(|_| Transformation::default()).collect();
+        for t in &transformations {
+            assert_eq!(t, &Transformation::make(Matrix::identity()));
+            // no new matrices were allocated
+            assert!(Arc::ptr_eq(&t.0, &transformations[0].0));
+        }
+        assert_eq!(transformations[0].inverse_transpose, Matrix::identity());
+    }
+
     #[test]
     fn matrix_multiply_identity() {
         let m1 = Matrix::make_matrix_4(
@@ -491,7 +693,7 @@ mod matrix_tests {
             -0.7692307692307693,
             -1.9230769230769231,
         );
-        assert_eq!(m1.inverse(), expected_inverse);
+        assert!(m1.inverse().near_eq(&expected_inverse));
     }
 
     #[test]
@@ -517,7 +719,42 @@ mod matrix_tests {
             -0.26666666666666666,
             0.3333333333333333,
         );
-        assert_eq!(m1.inverse(), expected_inverse);
+        assert!(m1.inverse().near_eq(&expected_inverse));
+    }
+
+    #[test]
+    fn matrix_multiplied_by_its_inverse_is_identity() {
+        let matrices = vec![
+            Matrix::make_matrix_4(
+                3.0, -9.0, 7.0, 3.0, 3.0, -8.0, 2.0, -9.0, -4.0, 4.0, 4.0, 1.0, -6.0, 5.0, -1.0,
+                1.0,
+            ),
+            Matrix::make_matrix_4(
+                0.0, 2.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 3.0, 0.0, 0.0, 4.0, 0.0,
+            ),
+            Matrix::make_matrix_3(1.0, 2.0, 6.0, -5.0, 8.0, -4.0, 2.0, 6.0, 4.0),
+            Matrix::translation(5.0, -3.0, 2.0)
+                .multiply(&Matrix::rotate_y(1.2))
+                .multiply(&Matrix::scaling(0.5, 7.0, 2.0))
+                .multiply(&Matrix::shearing(1.0, 0.0, 0.5, 0.0, 0.0, 2.0)),
+        ];
+        for m in matrices {
+            let identity = Matrix::from_vec(
+                m.size,
+                (0..m.size * m.size)
+                    .map(|i| if i % (m.size + 1) == 0 { 1.0 } else { 0.0 })
+                    .collect(),
+            )
+            .unwrap();
+            assert!(m.multiply(&m.inverse()).near_eq(&identity));
+            assert!(m.inverse().multiply(&m).near_eq(&identity));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot be inverted")]
+    fn singular_matrix_cannot_be_inverted() {
+        Matrix::make_matrix_3(1.0, 2.0, 3.0, 2.0, 4.0, 6.0, 0.0, 1.0, 1.0).inverse();
     }
 
     #[test]
@@ -594,7 +831,7 @@ mod matrix_tests {
         let full_quarter = Matrix::rotate_x(std::f64::consts::FRAC_PI_2);
         assert_eq!(
             half_quarter.multiply_tuple(&p),
-            point(0.0, 0.7071067811865476, 0.7071067811865475)
+            point(0.0, FRAC_1_SQRT_2, 0.7071067811865475)
         );
         assert_eq!(
             full_quarter.multiply_tuple(&p),
@@ -609,7 +846,7 @@ mod matrix_tests {
         let full_quarter = Matrix::rotate_y(std::f64::consts::FRAC_PI_2);
         assert_eq!(
             half_quarter.multiply_tuple(&p),
-            point(0.7071067811865475, 0.0, 0.7071067811865476)
+            point(0.7071067811865475, 0.0, FRAC_1_SQRT_2)
         );
         assert_eq!(
             full_quarter.multiply_tuple(&p),
@@ -624,7 +861,7 @@ mod matrix_tests {
         let full_quarter = Matrix::rotate_z(std::f64::consts::FRAC_PI_2);
         assert_eq!(
             half_quarter.multiply_tuple(&p),
-            point(-0.7071067811865475, 0.7071067811865476, 0.0)
+            point(-0.7071067811865475, FRAC_1_SQRT_2, 0.0)
         );
         assert_eq!(
             full_quarter.multiply_tuple(&p),
@@ -647,7 +884,7 @@ mod matrix_tests {
         let trans = Matrix::translation(10.0, 5.0, 7.0);
 
         let p2 = rot.multiply_tuple(&p1);
-        assert_eq!(p2, point(1.0, -1.0, 0.000000000000000061232339957367660));
+        assert_eq!(p2, point(1.0, -1.0, 0.00000000000000006123233995736766));
 
         let p3 = scaling.multiply_tuple(&p2);
         assert_eq!(p3, point(5.0, -5.0, 0.0000000000000003061616997868383));
@@ -669,4 +906,13 @@ mod matrix_tests {
         let p1 = chain.multiply_tuple(&p);
         assert_eq!(p1, point(15.0, 0.0, 7.0));
     }
+
+    #[test]
+    fn interpolating_between_two_matrices() {
+        let start = Matrix::translation(0.0, 0.0, 0.0);
+        let end = Matrix::translation(2.0, 4.0, -6.0);
+        assert_eq!(start.lerp(&end, 0.0), start);
+        assert_eq!(start.lerp(&end, 1.0), end);
+        assert_eq!(start.lerp(&end, 0.5), Matrix::translation(1.0, 2.0, -3.0));
+    }
 }
@@ -0,0 +1,89 @@
+// Deterministic 3D value noise: random values on the integer lattice smoothly interpolated in between.
+
+const DEFAULT_SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+// noise in the range [-1, 1]
+pub fn noise(x: f64, y: f64, z: f64) -> f64 {
+    noise_with_seed(DEFAULT_SEED, x, y, z)
+}
+
+pub fn noise_with_seed(seed: u64, x: f64, y: f64, z: f64) -> f64 {
+    let (x0, y0, z0) = (x.floor(), y.floor(), z.floor());
+    let (tx, ty, tz) = (fade(x - x0), fade(y - y0), fade(z - z0));
+    let (xi, yi, zi) = (x0 as i64, y0 as i64, z0 as i64);
+    let corner = |dx: i64, dy: i64, dz: i64| lattice_value(seed, xi + dx, yi + dy, zi + dz);
+    // trilinear interpolation of the eight surrounding lattice values
+    let x00 = lerp(corner(0, 0, 0), corner(1, 0, 0), tx);
+    let x10 = lerp(corner(0, 1, 0), corner(1, 1, 0), tx);
+    let x01 = lerp(corner(0, 0, 1), corner(1, 0, 1), tx);
+    let x11 = lerp(corner(0, 1, 1), corner(1, 1, 1), tx);
+    let y0 = lerp(x00, x10, ty);
+    let y1 = lerp(x01, x11, ty);
+    lerp(y0, y1, tz)
+}
+
+// smoothstep to avoid visible creases at the lattice cells boundaries
+fn fade(t: f64) -> f64 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + t * (b - a)
+}
+
+// pseudo random value in [-1, 1] for a lattice point
+fn lattice_value(seed: u64, x: i64, y: i64, z: i64) -> f64 {
+    let mut h = seed;
+    for v in [x, y, z] {
+        h = mix(h ^ (v as u64));
+    }
+    // keep the 53 most significant bits to build a float in [0, 1]
+    let unit = (h >> 11) as f64 / (1u64 << 53) as f64;
+    unit * 2.0 - 1.0
+}
+
+// splitmix64 finalizer
+fn mix(mut h: u64) -> u64 {
+    h = h.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    h ^ (h >> 31)
+}
+
+#[cfg(test)]
+mod noise_tests {
+    use crate::noise::*;
+
+    #[test]
+    fn noise_is_deterministic() {
+        assert_eq!(noise(0.3, 1.7, -2.2), noise(0.3, 1.7, -2.2));
+        assert_eq!(
+            noise_with_seed(42, 5.5, 0.1, 3.9),
+            noise_with_seed(42, 5.5, 0.1, 3.9)
+        );
+        assert_ne!(
+            noise_with_seed(42, 5.5, 0.1, 3.9),
+            noise_with_seed(43, 5.5, 0.1, 3.9)
+        );
+    }
+
+    #[test]
+    fn noise_stays_in_range_and_varies() {
+        let values: Vec<f64> = (0..1000)
+            .map(|i| {
+                let t = i as f64 * 0.137;
+                noise(t, -t * 0.5, t * 0.25)
+            })
+            .collect();
+        assert!(values.iter().all(|v| (-1.0..=1.0).contains(v)));
+        assert!(values.iter().any(|v| *v < -0.1));
+        assert!(values.iter().any(|v| *v > 0.1));
+    }
+
+    #[test]
+    fn noise_is_continuous() {
+        let a = noise(1.999999, 0.5, 0.5);
+        let b = noise(2.000001, 0.5, 0.5);
+        assert!((a - b).abs() < 1e-4);
+    }
+}
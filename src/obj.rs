@@ -0,0 +1,408 @@
+use crate::group::Group;
+use crate::shape::Shape;
+use crate::triangle::{SmoothTriangle, Triangle};
+use crate::tuple::*;
+
+// A triangle of the model referencing the parsed vertices and normals by their index.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Face {
+    pub vertices: [usize; 3],
+    pub normals: Option<[usize; 3]>,
+}
+
+// faces declared before any `g` statement
+pub const DEFAULT_GROUP: &str = "default";
+
+// Faces following a `g <name>` statement.
+#[derive(Debug, PartialEq)]
+pub struct ObjGroup {
+    pub name: String,
+    pub faces: Vec<Face>,
+}
+
+// Result of parsing a Wavefront OBJ file.
+#[derive(Debug, PartialEq)]
+pub struct ParsedObj {
+    pub vertices: Vec<Tuple>,
+    pub normals: Vec<Tuple>,
+    // the default group always comes first
+    pub groups: Vec<ObjGroup>,
+    pub ignored_lines: usize,
+}
+
+// Unsupported or malformed lines are skipped and counted in `ignored_lines`.
+pub fn parse_obj(input: &str) -> ParsedObj {
+    let mut parsed = ParsedObj {
+        vertices: vec![],
+        normals: vec![],
+        groups: vec![ObjGroup {
+            name: DEFAULT_GROUP.to_string(),
+            faces: vec![],
+        }],
+        ignored_lines: 0,
+    };
+    // faces are added to the last group named
+    let mut current_group = 0;
+    for line in input.lines() {
+        let mut tokens = line.split_whitespace();
+        let recognized = match tokens.next() {
+            Some("v") => match parse_coordinates(tokens) {
+                Some((x, y, z)) => {
+                    parsed.vertices.push(point(x, y, z));
+                    true
+                }
+                None => false,
+            },
+            Some("vn") => match parse_coordinates(tokens) {
+                Some((x, y, z)) => {
+                    parsed.normals.push(vector(x, y, z));
+                    true
+                }
+                None => false,
+            },
+            Some("f") => match parsed.parse_face(tokens) {
+                Some(faces) => {
+                    parsed.groups[current_group].faces.extend(faces);
+                    true
+                }
+                None => false,
+            },
+            Some("g") => match tokens.next() {
+                Some(name) => {
+                    current_group = parsed.group_index(name);
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        };
+        if !recognized {
+            parsed.ignored_lines += 1;
+        }
+    }
+    parsed
+}
+
+fn parse_coordinates<'a>(mut tokens: impl Iterator<Item = &'a str>) -> Option<(f64, f64, f64)> {
+    let x = tokens.next()?.parse().ok()?;
+    let y = tokens.next()?.parse().ok()?;
+    let z = tokens.next()?.parse().ok()?;
+    Some((x, y, z))
+}
+
+impl ParsedObj {
+    // faces of all the groups
+    pub fn faces(&self) -> impl Iterator<Item = &Face> {
+        self.groups.iter().flat_map(|g| g.faces.iter())
+    }
+
+    // Gives a vertex normal to the faces without normals so they render as smooth triangles,
+    // each vertex normal is the normalized average of the normals of the faces sharing the vertex.
+    // The faces with normals are left as is, the computed normals are appended to `normals`.
+    pub fn with_smooth_normals(mut self) -> ParsedObj {
+        let mut sums = vec![vector(0.0, 0.0, 0.0); self.vertices.len()];
+        for face in self.faces().filter(|f| f.normals.is_none()) {
+            let normal = self.face_normal(face);
+            for v in face.vertices {
+                sums[v] = add_tuple(&sums[v], &normal);
+            }
+        }
+        let offset = self.normals.len();
+        self.normals.extend(sums.iter().map(vector_normalize));
+        for group in &mut self.groups {
+            for face in group.faces.iter_mut().filter(|f| f.normals.is_none()) {
+                face.normals = Some(face.vertices.map(|v| offset + v));
+            }
+        }
+        self
+    }
+
+    // same orientation as the normal of `Triangle`
+    fn face_normal(&self, face: &Face) -> Tuple {
+        let [p1, p2, p3] = face.vertices.map(|i| self.vertices[i]);
+        let e1 = subtract_tuple(&p2, &p1);
+        let e2 = subtract_tuple(&p3, &p1);
+        vector_normalize(&vector_cross_product(&e2, &e1))
+    }
+
+    // a group mentioned several times gathers all its faces
+    fn group_index(&mut self, name: &str) -> usize {
+        match self.groups.iter().position(|g| g.name == name) {
+            Some(index) => index,
+            None => {
+                self.groups.push(ObjGroup {
+                    name: name.to_string(),
+                    faces: vec![],
+                });
+                self.groups.len() - 1
+            }
+        }
+    }
+
+    // polygons are split in a fan of triangles sharing the first vertex
+    fn parse_face<'a>(&self, tokens: impl Iterator<Item = &'a str>) -> Option<Vec<Face>> {
+        let mut vertices = Vec::new();
+        let mut normals = Vec::new();
+        for token in tokens {
+            // `v`, `v/vt`, `v//vn` or `v/vt/vn`
+            let mut indices = token.split('/');
+            vertices.push(self.index(indices.next()?, self.vertices.len())?);
+            match indices.nth(1) {
+                Some(normal) if !normal.is_empty() => {
+                    normals.push(self.index(normal, self.normals.len())?)
+                }
+                _ => (),
+            }
+        }
+        if vertices.len() < 3 {
+            return None;
+        }
+        let with_normals = normals.len() == vertices.len();
+        let faces = (1..vertices.len() - 1)
+            .map(|i| Face {
+                vertices: [vertices[0], vertices[i], vertices[i + 1]],
+                normals: if with_normals {
+                    Some([normals[0], normals[i], normals[i + 1]])
+                } else {
+                    None
+                },
+            })
+            .collect();
+        Some(faces)
+    }
+
+    // OBJ indices start at 1
+    fn index(&self, token: &str, len: usize) -> Option<usize> {
+        let index: usize = token.parse().ok()?;
+        if index == 0 || index > len {
+            None
+        } else {
+            Some(index - 1)
+        }
+    }
+
+    // The returned group holds the faces of the default group and one child group per named group.
+    // It takes the given id, the shapes it contains use the following ones.
+    pub fn to_group(&self, id: usize) -> Group {
+        let mut next_id = id + 1;
+        let (default_group, named_groups) = self.groups.split_first().expect("default group");
+        let mut group = self.add_faces(Group::new(id), &default_group.faces, &mut next_id);
+        for named_group in named_groups {
+            let child = Group::new(next_id).set_name(&named_group.name);
+            next_id += 1;
+            let child = self.add_faces(child, &named_group.faces, &mut next_id);
+            group = group.add_child(Box::new(child));
+        }
+        group.set_name(DEFAULT_GROUP)
+    }
+
+    fn add_faces(&self, group: Group, faces: &[Face], next_id: &mut usize) -> Group {
+        faces.iter().fold(group, |group, face| {
+            let triangle = self.triangle(*next_id, face);
+            *next_id += 1;
+            group.add_child(triangle)
+        })
+    }
+
+    fn triangle(&self, id: usize, face: &Face) -> Box<dyn Shape> {
+        let [p1, p2, p3] = face.vertices.map(|i| self.vertices[i]);
+        match face.normals {
+            Some(normals) => {
+                let [n1, n2, n3] = normals.map(|i| self.normals[i]);
+                Box::new(SmoothTriangle::new(id, (p1, p2, p3), (n1, n2, n3)))
+            }
+            None => Box::new(Triangle::new(id, p1, p2, p3)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod obj_tests {
+    use crate::epsilon::EPSILON;
+    use crate::obj::*;
+    use crate::ray::Ray;
+    use std::f64::consts::FRAC_1_SQRT_2;
+
+    #[test]
+    fn ignoring_unrecognized_lines() {
+        let gibberish = "There was a young lady named Bright
+who traveled much faster than light.
+She set out one day
+in a relative way,
+and came back the previous night.";
+        let parsed = parse_obj(gibberish);
+        assert_eq!(parsed.ignored_lines, 5);
+        assert!(parsed.vertices.is_empty());
+    }
+
+    #[test]
+    fn vertex_records() {
+        let file = "v -1 1 0
+v -1.0000 0.5000 0.0000
+v 1 0 0
+v 1 1 0";
+        let parsed = parse_obj(file);
+        assert_eq!(parsed.ignored_lines, 0);
+        assert_eq!(
+            parsed.vertices,
+            vec![
+                point(-1.0, 1.0, 0.0),
+                point(-1.0, 0.5, 0.0),
+                point(1.0, 0.0, 0.0),
+                point(1.0, 1.0, 0.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn triangulating_polygons() {
+        let file = "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+v 0 2 0
+
+f 1 2 3 4 5";
+        let parsed = parse_obj(file);
+        // the blank line is not a record
+        assert_eq!(parsed.ignored_lines, 1);
+        let vertices: Vec<[usize; 3]> = parsed.faces().map(|f| f.vertices).collect();
+        assert_eq!(vertices, vec![[0, 1, 2], [0, 2, 3], [0, 3, 4]]);
+        assert!(parsed.faces().all(|f| f.normals.is_none()));
+
+        let group = parsed.to_group(1);
+        assert_eq!(group.children().len(), 3);
+        let r = Ray::new(point(0.5, 0.5, -2.0), vector(0.0, 0.0, 1.0));
+        let xs = group.intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].object_id, 3);
+    }
+
+    #[test]
+    fn faces_with_normals() {
+        let file = "v 0 1 0
+v -1 0 0
+v 1 0 0
+
+vn -1 0 0
+vn 1 0 0
+vn 0 1 0
+
+f 1//3 2//1 3//2
+f 1/0/3 2/102/1 3/14/2
+f 1 2 3";
+        let parsed = parse_obj(file);
+        assert_eq!(
+            parsed.normals,
+            vec![
+                vector(-1.0, 0.0, 0.0),
+                vector(1.0, 0.0, 0.0),
+                vector(0.0, 1.0, 0.0)
+            ]
+        );
+        let faces: Vec<&Face> = parsed.faces().collect();
+        assert_eq!(faces.len(), 3);
+        assert_eq!(faces[0].normals, Some([2, 0, 1]));
+        assert_eq!(faces[0], faces[1]);
+        assert_eq!(faces[2].normals, None);
+
+        // the smooth triangle normal follows the vertex normals, the flat one does not
+        let group = parsed.to_group(1);
+        let p = point(-0.5, 0.5, 0.0);
+        let n = group.children()[0].normal_at(&p);
+        assert!((n.0 - -FRAC_1_SQRT_2).abs() < EPSILON);
+        assert!((n.1 - FRAC_1_SQRT_2).abs() < EPSILON);
+        assert_eq!(group.children()[2].normal_at(&p), vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn smoothing_normals_of_a_mesh_without_normals() {
+        // octahedron, the faces are wound the same way seen from outside
+        let file = "v 0 1 0
+v 0 -1 0
+v 1 0 0
+v 0 0 1
+v -1 0 0
+v 0 0 -1
+f 1 3 4
+f 1 4 5
+f 1 5 6
+f 1 6 3
+f 2 4 3
+f 2 5 4
+f 2 6 5
+f 2 3 6";
+        let parsed = parse_obj(file);
+        let flat_normals: Vec<Tuple> = parsed
+            .to_group(1)
+            .children()
+            .iter()
+            .take(4)
+            .map(|t| t.normal_at(&point(0.0, 1.0, 0.0)))
+            .collect();
+        let smooth = parsed.with_smooth_normals();
+        assert_eq!(smooth.normals.len(), 6);
+        assert!(smooth.faces().all(|f| f.normals.is_some()));
+        // the top vertex is shared by the first four faces
+        let top = smooth.normals[smooth.groups[0].faces[0].normals.unwrap()[0]];
+        let sum = flat_normals
+            .iter()
+            .fold(vector(0.0, 0.0, 0.0), |acc, n| add_tuple(&acc, n));
+        assert!(tuples_are_equal(&top, &vector_normalize(&sum)));
+        assert!(tuples_are_equal(&top, &vector(0.0, 1.0, 0.0)));
+        assert!(tuples_are_equal(&smooth.normals[2], &vector(1.0, 0.0, 0.0)));
+
+        // the smooth triangles interpolate the vertex normals
+        let group = smooth.to_group(1);
+        let n = group.children()[0].normal_at(&point(0.5, 0.5, 0.0));
+        assert!(n.0 > 0.0 && n.1 > 0.0);
+    }
+
+    #[test]
+    fn faces_with_unknown_vertices_are_ignored() {
+        let parsed = parse_obj("v 0 1 0\nv -1 0 0\nf 1 2 3");
+        assert_eq!(parsed.faces().count(), 0);
+        assert_eq!(parsed.ignored_lines, 1);
+    }
+
+    #[test]
+    fn triangles_in_named_groups() {
+        let file = "v -1 1 0
+v -1 0 0
+v 1 0 0
+v 1 1 0
+f 1 2 3
+g FirstGroup
+f 1 2 3
+g SecondGroup
+f 1 2 3 4
+g FirstGroup
+f 2 3 4";
+        let parsed = parse_obj(file);
+        assert_eq!(parsed.ignored_lines, 0);
+        let names: Vec<&str> = parsed.groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec![DEFAULT_GROUP, "FirstGroup", "SecondGroup"]);
+
+        let group = parsed.to_group(1);
+        // one default face and the two named groups
+        assert_eq!(group.children().len(), 3);
+        let default_group = group.named_group(DEFAULT_GROUP).unwrap();
+        assert_eq!(default_group.id, 1);
+        let first = group.named_group("FirstGroup").unwrap();
+        assert_eq!(first.children().len(), 2);
+        let second = group.named_group("SecondGroup").unwrap();
+        assert_eq!(second.children().len(), 2);
+        assert!(group.named_group("ThirdGroup").is_none());
+
+        // ids are unique across the whole model
+        let mut ids: Vec<usize> = first
+            .children()
+            .iter()
+            .chain(second.children())
+            .map(|c| c.id())
+            .chain([group.id, first.id, second.id, group.children()[0].id()])
+            .collect();
+        ids.sort();
+        assert_eq!(ids, (1..=8).collect::<Vec<usize>>());
+    }
+}
@@ -1,11 +1,19 @@
+use crate::canvas::Canvas;
 use crate::color::Color;
 use crate::matrix::{Matrix, Transformation};
+use crate::noise::noise;
 use crate::pattern::Pattern::*;
 use crate::tuple::Tuple;
+use crate::uv::{cube_uv, ImageTexture, UvMap, UvPattern};
 use std::fmt::Debug;
 
 // decided against the trait based solution like in Shape and went for an enum.
 #[derive(Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type")
+)]
 pub enum Pattern {
     StripePattern {
         inner: Stripe,
@@ -23,6 +31,39 @@ pub enum Pattern {
         inner: Checker,
         transform: Transformation,
     },
+    MultiStripePattern {
+        inner: MultiStripe,
+        transform: Transformation,
+    },
+    // stripes alternating between two patterns evaluated in the space of this pattern
+    NestedStripePattern {
+        a: Box<Pattern>,
+        b: Box<Pattern>,
+        transform: Transformation,
+    },
+    // average of two patterns
+    BlendPattern {
+        a: Box<Pattern>,
+        b: Box<Pattern>,
+        transform: Transformation,
+    },
+    // 2D pattern wrapped around the object through a UV mapping
+    TexturePattern {
+        mapping: UvMap,
+        uv_pattern: UvPattern,
+        transform: Transformation,
+    },
+    // one 2D pattern per face of the cube from -1 to 1, indexed by `Face`
+    CubeMapPattern {
+        faces: Box<[UvPattern; 6]>,
+        transform: Transformation,
+    },
+    // jitters the point with noise before evaluating the inner pattern
+    PerturbPattern {
+        inner: Box<Pattern>,
+        scale: f64,
+        transform: Transformation,
+    },
 }
 
 impl Pattern {
@@ -42,26 +83,68 @@ impl Pattern {
         object_transformation: &Transformation,
         point: &Tuple,
     ) -> Color {
+        // world-space point into object point
+        let object_point = object_transformation.inverse.multiply_tuple(point);
+        self.pattern_at(&object_point)
+    }
+
+    fn transform(&self) -> &Transformation {
+        match self {
+            StripePattern { transform, .. }
+            | GradientPattern { transform, .. }
+            | RingPattern { transform, .. }
+            | CheckerPattern { transform, .. }
+            | MultiStripePattern { transform, .. }
+            | NestedStripePattern { transform, .. }
+            | BlendPattern { transform, .. }
+            | PerturbPattern { transform, .. }
+            | TexturePattern { transform, .. }
+            | CubeMapPattern { transform, .. } => transform,
+        }
+    }
+
+    // `point` is in the space of the parent, the object or the enclosing pattern,
+    // only the transform of the pattern is applied which lets patterns be tested on their own
+    pub fn pattern_at(&self, point: &Tuple) -> Color {
+        let pattern_point = self.transform().inverse.multiply_tuple(point);
         match self {
-            Pattern::StripePattern { inner, transform } => {
-                let pattern_point =
-                    Pattern::convert_to_pattern_point(transform, object_transformation, point);
-                inner.stripe_at(&pattern_point)
+            StripePattern { inner, .. } => inner.stripe_at(&pattern_point),
+            GradientPattern { inner, .. } => inner.gradient_at(&pattern_point),
+            RingPattern { inner, .. } => inner.ring_at(&pattern_point),
+            CheckerPattern { inner, .. } => inner.checker_at(&pattern_point),
+            MultiStripePattern { inner, .. } => inner.multi_stripe_at(&pattern_point),
+            NestedStripePattern { a, b, .. } => {
+                if Stripe::is_first_stripe(&pattern_point) {
+                    a.pattern_at(&pattern_point)
+                } else {
+                    b.pattern_at(&pattern_point)
+                }
             }
-            Pattern::GradientPattern { inner, transform } => {
-                let pattern_point =
-                    Pattern::convert_to_pattern_point(transform, object_transformation, point);
-                inner.gradient_at(&pattern_point)
+            BlendPattern { a, b, .. } => a
+                .pattern_at(&pattern_point)
+                .add(&b.pattern_at(&pattern_point))
+                .multiply_value(0.5),
+            TexturePattern {
+                mapping,
+                uv_pattern,
+                ..
+            } => {
+                let (u, v) = mapping.map(&pattern_point);
+                uv_pattern.uv_pattern_at(u, v)
             }
-            Pattern::RingPattern { inner, transform } => {
-                let pattern_point =
-                    Pattern::convert_to_pattern_point(transform, object_transformation, point);
-                inner.ring_at(&pattern_point)
+            CubeMapPattern { faces, .. } => {
+                let (face, u, v) = cube_uv(&pattern_point);
+                faces[face as usize].uv_pattern_at(u, v)
             }
-            Pattern::CheckerPattern { inner, transform } => {
-                let pattern_point =
-                    Pattern::convert_to_pattern_point(transform, object_transformation, point);
-                inner.checker_at(&pattern_point)
+            PerturbPattern { inner, scale, .. } => {
+                let (x, y, z) = (pattern_point.0, pattern_point.1, pattern_point.2);
+                // offset the noise samples so that each axis moves independently
+                let perturbed = crate::tuple::point(
+                    x + scale * noise(x, y, z),
+                    y + scale * noise(x, y, z + 1.0),
+                    z + scale * noise(x, y, z + 2.0),
+                );
+                inner.pattern_at(&perturbed)
             }
         }
     }
@@ -80,6 +163,13 @@ impl Pattern {
         }
     }
 
+    pub fn new_gradient_clamped(a: Color, b: Color, transform: Matrix) -> Pattern {
+        GradientPattern {
+            inner: Gradient::new_clamped(a, b),
+            transform: Transformation::make(transform),
+        }
+    }
+
     pub fn new_ring(a: Color, b: Color, transform: Matrix) -> Pattern {
         RingPattern {
             inner: Ring::new(a, b),
@@ -93,25 +183,101 @@ impl Pattern {
             transform: Transformation::make(transform),
         }
     }
+
+    pub fn new_checker_scaled(a: Color, b: Color, scale: f64, transform: Matrix) -> Pattern {
+        CheckerPattern {
+            inner: Checker::new_scaled(a, b, scale),
+            transform: Transformation::make(transform),
+        }
+    }
+
+    pub fn new_multi_stripe(colors: Vec<Color>, transform: Matrix) -> Pattern {
+        MultiStripePattern {
+            inner: MultiStripe::new(colors),
+            transform: Transformation::make(transform),
+        }
+    }
+
+    pub fn new_nested_stripe(a: Pattern, b: Pattern, transform: Matrix) -> Pattern {
+        NestedStripePattern {
+            a: Box::new(a),
+            b: Box::new(b),
+            transform: Transformation::make(transform),
+        }
+    }
+
+    pub fn new_texture(uv_pattern: UvPattern, mapping: UvMap, transform: Matrix) -> Pattern {
+        TexturePattern {
+            mapping,
+            uv_pattern,
+            transform: Transformation::make(transform),
+        }
+    }
+
+    pub fn new_image(canvas: Canvas, mapping: UvMap, transform: Matrix) -> Pattern {
+        Pattern::new_texture(
+            UvPattern::Image(ImageTexture::new(canvas)),
+            mapping,
+            transform,
+        )
+    }
+
+    // faces in the order left, front, right, back, up, down
+    pub fn new_cube_map(faces: [UvPattern; 6], transform: Matrix) -> Pattern {
+        CubeMapPattern {
+            faces: Box::new(faces),
+            transform: Transformation::make(transform),
+        }
+    }
+
+    pub fn new_perturb(inner: Pattern, scale: f64, transform: Matrix) -> Pattern {
+        PerturbPattern {
+            inner: Box::new(inner),
+            scale,
+            transform: Transformation::make(transform),
+        }
+    }
+
+    pub fn new_blend(a: Pattern, b: Pattern, transform: Matrix) -> Pattern {
+        BlendPattern {
+            a: Box::new(a),
+            b: Box::new(b),
+            transform: Transformation::make(transform),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Checker {
     a: Color,
     b: Color,
+    // size of a cell along each axis
+    #[cfg_attr(feature = "serde", serde(default = "unit_scale"))]
+    scale: f64,
+}
+
+#[cfg(feature = "serde")]
+fn unit_scale() -> f64 {
+    1.0
 }
 
 impl Checker {
     pub fn new(a: Color, b: Color) -> Checker {
-        Checker { a, b }
+        Checker::new_scaled(a, b, 1.0)
+    }
+
+    // cells of `scale` units without having to scale the pattern transform
+    pub fn new_scaled(a: Color, b: Color, scale: f64) -> Checker {
+        Checker { a, b, scale }
     }
 
     // The function for this pattern is very much like that for stripes,
     // but instead of relying on a single dimension, it relies on the sum of all three dimensions, x, y, and z.
     pub fn checker_at(&self, point: &Tuple) -> Color {
-        let x = point.0.floor();
-        let y = point.1.floor();
-        let z = point.2.floor();
+        let x = (point.0 / self.scale).floor();
+        let y = (point.1 / self.scale).floor();
+        let z = (point.2 / self.scale).floor();
         let threshold = x + y + z;
         if threshold % 2.0 == 0.0 {
             self.a
@@ -122,6 +288,7 @@ impl Checker {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Ring {
     a: Color,
     b: Color,
@@ -147,28 +314,49 @@ impl Ring {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Gradient {
     a: Color,
     distance: Color,
+    // stays on the first color below x=0 and on the second one above x=1 instead of repeating
+    clamp: bool,
 }
 
 impl Gradient {
     pub fn new(a: Color, b: Color) -> Gradient {
         // save only the distance between the two colors as it is constant
         let distance = b.subtract(&a);
-        Gradient { a, distance }
+        Gradient {
+            a,
+            distance,
+            clamp: false,
+        }
+    }
+
+    pub fn new_clamped(a: Color, b: Color) -> Gradient {
+        Gradient {
+            clamp: true,
+            ..Gradient::new(a, b)
+        }
     }
 
     // This takes the distance between the two colors, multiplies it by the fractional portion of the x coordinate, and adds the product to the first color.
     // The result is a smooth, linear transition from the first color to the second.
     pub fn gradient_at(&self, point: &Tuple) -> Color {
-        let fraction = point.0.fract();
+        let x = point.0;
+        let fraction = if self.clamp {
+            x.clamp(0.0, 1.0)
+        } else {
+            // unlike `fract` this stays positive for negative x
+            x - x.floor()
+        };
         let portion = self.distance.multiply_value(fraction);
         self.a.add(&portion)
     }
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Stripe {
     a: Color,
     b: Color,
@@ -182,19 +370,44 @@ impl Stripe {
     // As the x coordinate changes, the pattern alternates between the two colors.
     // The other two dimensions, y and z, have no effect on it
     pub fn stripe_at(&self, point: &Tuple) -> Color {
-        let x = point.0;
-        if x < 0. {
-            if x.abs() % 2. <= 1. {
-                self.b
-            } else {
-                self.a
-            }
-        } else if x % 2. < 1. {
+        if Stripe::is_first_stripe(point) {
             self.a
         } else {
             self.b
         }
     }
+
+    fn is_first_stripe(point: &Tuple) -> bool {
+        let x = point.0;
+        if x < 0. {
+            x.abs() % 2. > 1.
+        } else {
+            x % 2. < 1.
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MultiStripe {
+    colors: Vec<Color>,
+}
+
+impl MultiStripe {
+    pub fn new(colors: Vec<Color>) -> MultiStripe {
+        assert!(
+            !colors.is_empty(),
+            "a multi stripe needs at least one color"
+        );
+        MultiStripe { colors }
+    }
+
+    // Stripes of width 1 along x cycling through the colors,
+    // the stripe index is floored so negative x continues the cycle backwards like `stripe_at`
+    pub fn multi_stripe_at(&self, point: &Tuple) -> Color {
+        let index = (point.0.floor() as i64).rem_euclid(self.colors.len() as i64);
+        self.colors[index as usize]
+    }
 }
 
 #[cfg(test)]
@@ -205,6 +418,7 @@ mod pattern_tests {
     use crate::shape::Shape;
     use crate::sphere::Sphere;
     use crate::tuple::point;
+    use crate::uv::{AlignCheck, UvCheckers, UvMap, UvPattern};
 
     #[test]
     fn a_stripe_pattern_is_constant_in_y() {
@@ -232,6 +446,26 @@ mod pattern_tests {
         assert_eq!(pattern.stripe_at(&point(-1.1, 0., 0.)), WHITE);
     }
 
+    #[test]
+    fn a_multi_stripe_pattern_cycles_through_its_colors() {
+        let red = Color::make(1., 0., 0.);
+        let pattern = MultiStripe::new(vec![WHITE, red, BLACK]);
+        assert_eq!(pattern.multi_stripe_at(&point(0., 0., 0.)), WHITE);
+        assert_eq!(pattern.multi_stripe_at(&point(1., 0., 0.)), red);
+        assert_eq!(pattern.multi_stripe_at(&point(2.5, 0., 0.)), BLACK);
+        assert_eq!(pattern.multi_stripe_at(&point(3., 0., 0.)), WHITE);
+        // negative x walks the cycle backwards
+        assert_eq!(pattern.multi_stripe_at(&point(-0.5, 0., 0.)), BLACK);
+        assert_eq!(pattern.multi_stripe_at(&point(-1.5, 0., 0.)), red);
+        // two colors match the regular stripes
+        let two = MultiStripe::new(vec![WHITE, BLACK]);
+        let stripe = Stripe::new(WHITE, BLACK);
+        for x in [-1.1, -1., -0.5, 0., 0.9, 1., 2.] {
+            let p = point(x, 0., 0.);
+            assert_eq!(two.multi_stripe_at(&p), stripe.stripe_at(&p), "{}", x);
+        }
+    }
+
     #[test]
     fn a_stripe_pattern_on_transformed_object() {
         let s = Sphere::new(1).set_transform(Matrix::scaling(2., 2., 2.));
@@ -256,6 +490,174 @@ mod pattern_tests {
         assert_eq!(c, WHITE);
     }
 
+    #[test]
+    fn a_stripe_pattern_evaluated_in_object_space() {
+        let pattern = Pattern::new_stripe(WHITE, BLACK, Matrix::scaling(2., 2., 2.));
+        assert_eq!(pattern.pattern_at(&point(1.5, 0., 0.)), WHITE);
+        assert_eq!(pattern.pattern_at(&point(2.5, 0., 0.)), BLACK);
+        assert_eq!(pattern.pattern_at(&point(-0.5, 5., 3.)), BLACK);
+        // same as going through an untransformed object
+        let s = Sphere::new(1);
+        assert_eq!(
+            pattern.pattern_at(&point(3.5, 0., 0.)),
+            pattern.pattern_at_object(s.transform(), &point(3.5, 0., 0.))
+        );
+    }
+
+    #[test]
+    fn blending_opposite_stripes_yields_gray() {
+        let pattern = Pattern::new_blend(
+            Pattern::new_stripe(WHITE, BLACK, Matrix::identity()),
+            Pattern::new_stripe(BLACK, WHITE, Matrix::identity()),
+            Matrix::identity(),
+        );
+        let s = Sphere::new(1);
+        let gray = Color::make(0.5, 0.5, 0.5);
+        assert_eq!(
+            pattern.pattern_at_object(s.transform(), &point(0.5, 0., 0.)),
+            gray
+        );
+        assert_eq!(
+            pattern.pattern_at_object(s.transform(), &point(1.5, 0., 0.)),
+            gray
+        );
+        assert_eq!(
+            pattern.pattern_at_object(s.transform(), &point(-0.5, 0., 0.)),
+            gray
+        );
+    }
+
+    #[test]
+    fn a_stripe_of_gradients_alternates_gradient_regions() {
+        let pattern = Pattern::new_nested_stripe(
+            Pattern::new_gradient(WHITE, BLACK, Matrix::identity()),
+            Pattern::new_gradient(BLACK, WHITE, Matrix::identity()),
+            Matrix::identity(),
+        );
+        let s = Sphere::new(1);
+        let light = Color::make(0.75, 0.75, 0.75);
+        let dark = Color::make(0.25, 0.25, 0.25);
+        assert_eq!(
+            pattern.pattern_at_object(s.transform(), &point(0.25, 0., 0.)),
+            light
+        );
+        assert_eq!(
+            pattern.pattern_at_object(s.transform(), &point(1.25, 0., 0.)),
+            dark
+        );
+        assert_eq!(
+            pattern.pattern_at_object(s.transform(), &point(2.25, 0., 0.)),
+            light
+        );
+    }
+
+    #[test]
+    fn nested_patterns_apply_their_own_transform() {
+        let pattern = Pattern::new_nested_stripe(
+            Pattern::new_stripe(WHITE, BLACK, Matrix::scaling(0.25, 1., 1.)),
+            Pattern::new_stripe(BLACK, WHITE, Matrix::identity()),
+            Matrix::identity(),
+        );
+        let s = Sphere::new(1);
+        assert_eq!(
+            pattern.pattern_at_object(s.transform(), &point(0.1, 0., 0.)),
+            WHITE
+        );
+        assert_eq!(
+            pattern.pattern_at_object(s.transform(), &point(0.3, 0., 0.)),
+            BLACK
+        );
+    }
+
+    #[test]
+    fn perturbation_with_scale_zero_leaves_pattern_unchanged() {
+        let s = Sphere::new(1);
+        let base = Pattern::new_stripe(WHITE, BLACK, Matrix::identity());
+        let perturbed = Pattern::new_perturb(
+            Pattern::new_stripe(WHITE, BLACK, Matrix::identity()),
+            0.0,
+            Matrix::identity(),
+        );
+        for x in [-1.5, -0.5, 0.2, 0.99, 1.01, 2.7] {
+            let p = point(x, 0.3, -0.7);
+            assert_eq!(
+                perturbed.pattern_at_object(s.transform(), &p),
+                base.pattern_at_object(s.transform(), &p)
+            );
+        }
+    }
+
+    #[test]
+    fn perturbation_moves_stripe_boundaries() {
+        let s = Sphere::new(1);
+        let base = Pattern::new_stripe(WHITE, BLACK, Matrix::identity());
+        let perturbed = Pattern::new_perturb(
+            Pattern::new_stripe(WHITE, BLACK, Matrix::identity()),
+            0.5,
+            Matrix::identity(),
+        );
+        let differences = (0..200)
+            .map(|i| point(i as f64 * 0.05, 0.3, -0.7))
+            .filter(|p| {
+                perturbed.pattern_at_object(s.transform(), p)
+                    != base.pattern_at_object(s.transform(), p)
+            })
+            .count();
+        assert!(differences > 0);
+    }
+
+    #[test]
+    fn using_texture_map_pattern_with_spherical_map() {
+        let checkers = UvPattern::Checkers(UvCheckers::new(16.0, 8.0, BLACK, WHITE));
+        let pattern = Pattern::new_texture(checkers, UvMap::Spherical, Matrix::identity());
+        let s = Sphere::new(1);
+        let cases = vec![
+            (point(0.4315, 0.4670, 0.7719), WHITE),
+            (point(-0.9654, 0.2552, -0.0534), BLACK),
+            (point(0.1039, 0.7090, 0.6975), WHITE),
+            (point(-0.4986, -0.7856, -0.3663), BLACK),
+            (point(-0.0317, -0.9395, 0.3411), BLACK),
+            (point(0.4809, -0.7721, 0.4154), BLACK),
+            (point(0.0285, -0.9612, -0.2745), BLACK),
+            (point(-0.5734, -0.2162, -0.7903), WHITE),
+            (point(0.7688, -0.1470, 0.6223), BLACK),
+            (point(-0.7652, 0.2175, 0.6060), BLACK),
+        ];
+        for (p, expected) in cases {
+            assert_eq!(pattern.pattern_at_object(s.transform(), &p), expected);
+        }
+    }
+
+    #[test]
+    fn a_cube_map_picks_the_pattern_of_each_face() {
+        let face = |color: Color| {
+            UvPattern::AlignCheck(AlignCheck::new(color, BLACK, BLACK, BLACK, BLACK))
+        };
+        let colors = [
+            Color::make(1., 0., 0.),
+            Color::make(0., 1., 0.),
+            Color::make(0., 0., 1.),
+            Color::make(1., 1., 0.),
+            Color::make(0., 1., 1.),
+            Color::make(1., 0., 1.),
+        ];
+        let pattern = Pattern::new_cube_map(colors.map(face), Matrix::identity());
+        let s = Sphere::new(1);
+        let cases = vec![
+            (point(-1., 0., 0.), colors[0]),
+            (point(0., 0., 1.), colors[1]),
+            (point(1., 0., 0.), colors[2]),
+            (point(0., 0., -1.), colors[3]),
+            (point(0., 1., 0.), colors[4]),
+            (point(0., -1., 0.), colors[5]),
+            // corner of the front face
+            (point(-0.9, 0.9, 1.), BLACK),
+        ];
+        for (p, expected) in cases {
+            assert_eq!(pattern.pattern_at_object(s.transform(), &p), expected);
+        }
+    }
+
     #[test]
     fn a_gradient_pattern_linearly_interpolates_between_two_colors() {
         let g = Gradient::new(WHITE, BLACK);
@@ -269,6 +671,36 @@ mod pattern_tests {
         assert_eq!(r4, Color::make(0.25, 0.25, 0.25));
     }
 
+    #[test]
+    fn a_gradient_pattern_repeats_past_its_bounds() {
+        let g = Gradient::new(WHITE, BLACK);
+        assert_eq!(g.gradient_at(&point(1., 0., 0.)), WHITE);
+        assert_eq!(
+            g.gradient_at(&point(1.5, 0., 0.)),
+            Color::make(0.5, 0.5, 0.5)
+        );
+        assert_eq!(
+            g.gradient_at(&point(-0.5, 0., 0.)),
+            Color::make(0.5, 0.5, 0.5)
+        );
+        assert_eq!(
+            g.gradient_at(&point(-0.25, 0., 0.)),
+            Color::make(0.25, 0.25, 0.25)
+        );
+    }
+
+    #[test]
+    fn a_clamped_gradient_pattern_stays_on_its_end_colors() {
+        let g = Gradient::new_clamped(WHITE, BLACK);
+        assert_eq!(
+            g.gradient_at(&point(0.25, 0., 0.)),
+            Color::make(0.75, 0.75, 0.75)
+        );
+        assert_eq!(g.gradient_at(&point(1., 0., 0.)), BLACK);
+        assert_eq!(g.gradient_at(&point(1.5, 0., 0.)), BLACK);
+        assert_eq!(g.gradient_at(&point(-0.5, 0., 0.)), WHITE);
+    }
+
     #[test]
     fn a_ring_pattern_should_extend_in_both_x_and_z() {
         let g = Ring::new(WHITE, BLACK);
@@ -315,4 +747,31 @@ mod pattern_tests {
         let r3 = g.checker_at(&point(0., 0., 1.01));
         assert_eq!(r3, BLACK);
     }
+
+    #[test]
+    fn a_scaled_checker_pattern_has_larger_cells() {
+        let g = Checker::new_scaled(WHITE, BLACK, 2.0);
+        for axis in 0..3 {
+            let at = |value: f64| {
+                let mut p = point(0., 0., 0.);
+                match axis {
+                    0 => p.0 = value,
+                    1 => p.1 = value,
+                    _ => p.2 = value,
+                }
+                g.checker_at(&p)
+            };
+            assert_eq!(at(0.), WHITE);
+            assert_eq!(at(1.99), WHITE);
+            assert_eq!(at(2.01), BLACK);
+            assert_eq!(at(3.99), BLACK);
+            assert_eq!(at(4.01), WHITE);
+            assert_eq!(at(-0.01), BLACK);
+        }
+        let pattern = Pattern::new_checker_scaled(WHITE, BLACK, 2.0, Matrix::identity());
+        let scaled = Pattern::new_checker(WHITE, BLACK, Matrix::scaling(2., 2., 2.));
+        for p in [point(1.5, 0.5, 0.), point(2.5, 0., 3.), point(-1., 5., 0.3)] {
+            assert_eq!(pattern.pattern_at(&p), scaled.pattern_at(&p));
+        }
+    }
 }
@@ -1,7 +1,8 @@
+use crate::bounds::BoundingBox;
 use crate::epsilon::EPSILON;
 use crate::intersection::Intersection;
 use crate::material::Material;
-use crate::matrix::Transformation;
+use crate::matrix::{Matrix, Transformation};
 use crate::ray::Ray;
 use crate::shape::Shape;
 use crate::tuple::*;
@@ -10,7 +11,12 @@ use crate::tuple::*;
 #[derive(Debug)]
 pub struct Plane {
     pub id: usize,
+    // half sizes along x and z of a finite rectangular tile, infinite plane when `None`
+    pub extent: Option<(f64, f64)>,
     transform: Transformation,
+    end_transform: Option<Transformation>, // pose at the end of the exposure
+    parent_transform: Option<Transformation>,
+    parent_end_transform: Option<Transformation>, // pose of the parents at the end of the exposure
     pub material: Material,
 }
 
@@ -18,14 +24,41 @@ impl Plane {
     pub fn new(id: usize) -> Plane {
         Plane {
             id,
+            extent: None,
             transform: Transformation::default(),
+            end_transform: None,
+            parent_transform: None,
+            parent_end_transform: None,
             material: Material::default(),
         }
     }
 
+    pub fn set_transform(self, transform: Matrix) -> Plane {
+        Plane {
+            transform: Transformation::make(transform),
+            ..self
+        }
+    }
+
+    // the shape moves from its transform at time 0 to `end_transform` at time 1
+    pub fn set_motion(self, end_transform: Matrix) -> Plane {
+        Plane {
+            end_transform: Some(Transformation::make(end_transform)),
+            ..self
+        }
+    }
+
     pub fn set_material(self, material: Material) -> Plane {
         Plane { material, ..self }
     }
+
+    // keeps only the tile from (-x, -z) to (x, z)
+    pub fn set_extent(self, x: f64, z: f64) -> Plane {
+        Plane {
+            extent: Some((x, z)),
+            ..self
+        }
+    }
 }
 
 impl Shape for Plane {
@@ -37,11 +70,44 @@ impl Shape for Plane {
         &self.transform
     }
 
+    fn parent_transform(&self) -> Option<&Transformation> {
+        self.parent_transform.as_ref()
+    }
+
+    fn set_parent_transform(&mut self, parent: &Matrix) {
+        self.parent_transform = Some(Transformation::make(parent.clone()));
+    }
+
+    fn end_transform(&self) -> Option<&Transformation> {
+        self.end_transform.as_ref()
+    }
+
+    fn parent_end_transform(&self) -> Option<&Transformation> {
+        self.parent_end_transform.as_ref()
+    }
+
+    fn set_parent_end_transform(&mut self, parent_end: Option<&Matrix>) {
+        self.parent_end_transform = parent_end.map(|m| Transformation::make(m.clone()));
+    }
+
     fn material(&self) -> &Material {
         &self.material
     }
 
-    fn local_intersect(&self, local_ray: &Ray) -> Vec<Intersection> {
+    fn material_mut(&mut self) -> Option<&mut Material> {
+        Some(&mut self.material)
+    }
+
+    fn set_transform_mut(&mut self, transform: Matrix) {
+        self.transform = Transformation::make(transform);
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let (x, z) = self.extent.unwrap_or((f64::INFINITY, f64::INFINITY));
+        BoundingBox::new(point(-x, 0.0, -z), point(x, 0.0, z))
+    }
+
+    fn local_intersect(&self, local_ray: &Ray) -> Vec<Intersection<'_>> {
         // To know if a ray is parallel to the plane, you need to note that the plane is in xz, it has no slope in y at all.
         // Thus, if your ray’s direction vector also has no slope in y (its y component is 0), it is parallel to the plane.
         // In practice, you’ll want to treat any tiny number as 0 for this comparison”
@@ -49,22 +115,31 @@ impl Shape for Plane {
             Vec::new()
         } else {
             let distance = -local_ray.origin.1 / local_ray.direction.1;
+            if let Some((x, z)) = self.extent {
+                let hit = local_ray.position_at(distance);
+                if hit.0.abs() > x || hit.2.abs() > z {
+                    return Vec::new();
+                }
+            }
             let intersection = Intersection::new(self.id(), distance);
             vec![intersection]
         }
     }
 
-    fn local_normal_at(&self, local_point: &(f64, f64, f64, f64)) -> (f64, f64, f64, f64) {
+    fn local_normal_at(&self, _local_point: &(f64, f64, f64, f64)) -> (f64, f64, f64, f64) {
         vector(0.0, 1.0, 0.0)
     }
 }
 
 #[cfg(test)]
 mod plane_tests {
+    use crate::epsilon::EPSILON;
+    use crate::matrix::Matrix;
     use crate::plane::Plane;
     use crate::ray::*;
     use crate::shape::Shape;
     use crate::tuple::*;
+    use std::f64::consts::FRAC_PI_2;
 
     #[test]
     fn normal_of_plan_is_constant_everywhere() {
@@ -113,4 +188,44 @@ mod plane_tests {
         assert_eq!(intersections[0].object_id, p.id);
         assert_eq!(intersections[0].distance, 1.0);
     }
+
+    #[test]
+    fn intersect_plane_within_its_extent() {
+        let p = Plane::new(1).set_extent(2.0, 1.0);
+        let ray = Ray::new(point(1.5, 1.0, -0.5), vector(0.0, -1.0, 0.0));
+        let intersections = p.local_intersect(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].distance, 1.0);
+        // the edges belong to the tile
+        let ray = Ray::new(point(2.0, 1.0, 1.0), vector(0.0, -1.0, 0.0));
+        assert_eq!(p.local_intersect(&ray).len(), 1);
+    }
+
+    #[test]
+    fn intersect_plane_outside_of_its_extent() {
+        let p = Plane::new(1).set_extent(2.0, 1.0);
+        let ray = Ray::new(point(0.0, 1.0, -1.5), vector(0.0, -1.0, 0.0));
+        assert!(p.local_intersect(&ray).is_empty());
+        let ray = Ray::new(point(-3.0, 1.0, 0.0), vector(0.0, -1.0, 0.0));
+        assert!(p.local_intersect(&ray).is_empty());
+        assert!(p.bounds().is_finite());
+        assert!(!Plane::new(2).bounds().is_finite());
+    }
+
+    #[test]
+    fn intersect_transformed_plane() {
+        // the plane becomes the xy plane at z = 3
+        let p = Plane::new(1).set_transform(
+            Matrix::translation(0.0, 0.0, 3.0).multiply(&Matrix::rotate_x(FRAC_PI_2)),
+        );
+        assert_eq!(p.transform().matrix.at(2, 3), 3.0);
+        let ray = Ray::new(point(0.5, 0.5, -2.0), vector(0.0, 0.0, 1.0));
+        let intersections = p.intersect(&ray);
+        assert_eq!(intersections.len(), 1);
+        assert!((intersections[0].distance - 5.0).abs() < EPSILON);
+        assert!(tuples_are_equal(
+            &p.normal_at(&point(0.5, 0.5, 3.0)),
+            &vector(0.0, 0.0, 1.0)
+        ));
+    }
 }
@@ -40,11 +40,10 @@ impl Projectile {
         let mut canvas = Canvas::make(900, 550);
         let red = Color::make(1.5, 0.0, 0.0);
         while pos.position.1 > 0.0 {
-            canvas.write(
-                pos.position.0.round() as usize,
-                canvas.height - pos.position.1.round() as usize,
-                red,
-            );
+            // positions falling outside of the canvas are not drawn
+            if let Some(y) = canvas.height.checked_sub(pos.position.1.round() as usize) {
+                canvas.write(pos.position.0.round() as usize, y, red);
+            }
             pos = pos.tick(&env);
             println!("{:?}", pos);
         }
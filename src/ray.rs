@@ -1,14 +1,54 @@
 use crate::matrix::Matrix;
-use crate::tuple::{add_tuple, scale_tuple, Tuple};
+use crate::tuple::{
+    add_tuple, scale_tuple, tuple_is_point, tuple_is_vector, vector_magnitude, vector_reflect,
+    Tuple,
+};
+use std::io::{Error, ErrorKind, Result};
 
+#[derive(Debug, Clone, Copy)]
 pub struct Ray {
     pub origin: Tuple,
     pub direction: Tuple,
+    pub time: f64, // in [0, 1], the moment of the exposure when the ray is cast
 }
 
 impl Ray {
+    // the direction must not contain NaNs nor be degenerate, a zero direction only ever reaches the origin
     pub fn new(origin: Tuple, direction: Tuple) -> Ray {
-        Ray { origin, direction }
+        debug_assert!(
+            !(direction.0.is_nan() || direction.1.is_nan() || direction.2.is_nan()),
+            "ray direction must not be NaN"
+        );
+        debug_assert!(
+            vector_magnitude(&direction) > 0.0,
+            "ray direction must have a non-zero length"
+        );
+        Ray {
+            origin,
+            direction,
+            time: 0.0,
+        }
+    }
+
+    // same as `new` but fails when the origin is not a point or the direction not a vector
+    pub fn try_new(origin: Tuple, direction: Tuple) -> Result<Ray> {
+        if !tuple_is_point(&origin) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "ray origin must be a point",
+            ));
+        }
+        if !tuple_is_vector(&direction) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "ray direction must be a vector",
+            ));
+        }
+        Ok(Ray::new(origin, direction))
+    }
+
+    pub fn set_time(self, time: f64) -> Ray {
+        Ray { time, ..self }
     }
 
     pub fn position_at(&self, t: f64) -> Tuple {
@@ -16,10 +56,22 @@ impl Ray {
         add_tuple(&self.origin, &scaled)
     }
 
+    // the point at parameter `t` along the ray, same as `position_at`
+    pub fn at_parametric(&self, t: f64) -> Tuple {
+        self.position_at(t)
+    }
+
+    // ray leaving `point` in the direction of this ray bounced off a surface of the given normal,
+    // it happens at the same time as this ray
+    pub fn reflect(&self, point: &Tuple, normal: &Tuple) -> Ray {
+        Ray::new(*point, vector_reflect(&self.direction, normal)).set_time(self.time)
+    }
+
     pub fn transform(&self, matrix: &Matrix) -> Ray {
         Ray {
             origin: matrix.multiply_tuple(&self.origin),
             direction: matrix.multiply_tuple(&self.direction),
+            time: self.time,
         }
     }
 }
@@ -39,6 +91,38 @@ mod ray_tests {
         assert_eq!(ray.direction, direction);
     }
 
+    #[test]
+    fn creating_checked_ray() {
+        let origin = point(1.0, 2.0, 3.0);
+        let direction = vector(4.0, 5.0, 6.0);
+        let ray = Ray::try_new(origin, direction).unwrap();
+        assert_eq!(ray.origin, origin);
+        assert_eq!(ray.direction, direction);
+    }
+
+    #[test]
+    fn checked_ray_rejects_swapped_origin_and_direction() {
+        let origin = point(1.0, 2.0, 3.0);
+        let direction = vector(4.0, 5.0, 6.0);
+        let err = Ray::try_new(direction, origin).unwrap_err();
+        assert_eq!(err.to_string(), "ray origin must be a point");
+        let err = Ray::try_new(origin, origin).unwrap_err();
+        assert_eq!(err.to_string(), "ray direction must be a vector");
+    }
+
+    #[test]
+    fn ray_with_degenerate_direction_stays_at_origin() {
+        let origin = point(1.0, 2.0, 3.0);
+        // bypasses the debug assertion of `Ray::new`
+        let ray = Ray {
+            origin,
+            direction: vector_normalize(&vector(0.0, 0.0, 0.0)),
+            time: 0.0,
+        };
+        assert_eq!(ray.position_at(0.0), origin);
+        assert_eq!(ray.position_at(10.0), origin);
+    }
+
     #[test]
     fn computing_point_from_distance() {
         let origin = point(2.0, 3.0, 4.0);
@@ -67,4 +151,15 @@ mod ray_tests {
         assert_eq!(r2.origin, point(2.0, 6.0, 12.0));
         assert_eq!(r2.direction, vector(0.0, 3.0, 0.0));
     }
+
+    #[test]
+    fn reflecting_ray_off_horizontal_surface() {
+        let ray = Ray::new(point(0.0, 5.0, 0.0), vector(1.0, -1.0, 0.5)).set_time(0.25);
+        let p = point(5.0, 0.0, 2.5);
+        let reflected = ray.reflect(&p, &vector(0.0, 1.0, 0.0));
+        assert_eq!(reflected.origin, p);
+        assert_eq!(reflected.direction, vector(1.0, 1.0, 0.5));
+        assert_eq!(reflected.time, 0.25);
+        assert_eq!(ray.at_parametric(5.0), p);
+    }
 }
@@ -0,0 +1,59 @@
+// Source of the numbers in [0, 1) driving the random sampling (soft shadows, depth of field, motion blur...),
+// tests swap the real generator for a fixed sequence to stay deterministic.
+pub trait Sampler {
+    fn next_f64(&mut self) -> f64;
+}
+
+// rejection sampling gives up after this many draws, a sequence may never land in the accepted region
+pub const MAX_REJECTION_ATTEMPTS: usize = 64;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RandomSampler;
+
+impl Sampler for RandomSampler {
+    fn next_f64(&mut self) -> f64 {
+        rand::random::<f64>()
+    }
+}
+
+// returns the given values in order and starts over once they are exhausted
+#[derive(Debug, Clone)]
+pub struct SequenceSampler {
+    values: Vec<f64>,
+    next: usize,
+}
+
+impl SequenceSampler {
+    pub fn new(values: Vec<f64>) -> SequenceSampler {
+        assert!(!values.is_empty(), "a sequence needs at least one value");
+        SequenceSampler { values, next: 0 }
+    }
+}
+
+impl Sampler for SequenceSampler {
+    fn next_f64(&mut self) -> f64 {
+        let value = self.values[self.next];
+        self.next = (self.next + 1) % self.values.len();
+        value
+    }
+}
+
+#[cfg(test)]
+mod sampler_tests {
+    use crate::sampler::*;
+
+    #[test]
+    fn sequence_sampler_cycles_through_its_values() {
+        let mut sampler = SequenceSampler::new(vec![0.1, 0.5, 1.0]);
+        let values: Vec<f64> = (0..7).map(|_| sampler.next_f64()).collect();
+        assert_eq!(values, vec![0.1, 0.5, 1.0, 0.1, 0.5, 1.0, 0.1]);
+    }
+
+    #[test]
+    fn random_sampler_stays_in_unit_range() {
+        let mut sampler = RandomSampler;
+        assert!((0..100)
+            .map(|_| sampler.next_f64())
+            .all(|x| (0.0..1.0).contains(&x)));
+    }
+}
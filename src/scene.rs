@@ -0,0 +1,491 @@
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::cube::Cube;
+use crate::light::Light;
+use crate::material::Material;
+use crate::matrix::Matrix;
+use crate::plane::Plane;
+use crate::shape::Shape;
+use crate::sphere::Sphere;
+use crate::transformation::{view_transform, TransformBuilder};
+use crate::tuple::{point, vector, Tuple};
+use crate::world::World;
+use serde_yaml::{Mapping, Value};
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Result};
+
+// loads the subset of the YAML scene format used by the book
+// - add: camera, with width, height, field-of-view, from, to and up
+// - add: light, with at and intensity
+// - add: sphere, plane or cube, with optional transform and material
+// - define: a named material or transform, optionally extending another definition
+pub fn load_scene(yaml: &str) -> Result<(World, Camera)> {
+    let items: Vec<Value> = serde_yaml::from_str(yaml).map_err(|e| invalid(&e.to_string()))?;
+    let items = items
+        .iter()
+        .map(|item| {
+            item.as_mapping()
+                .ok_or_else(|| invalid("scene entries must be mappings"))
+        })
+        .collect::<Result<Vec<&Mapping>>>()?;
+    // definitions can be referenced by entries declared before them
+    let mut definitions = Definitions::default();
+    for item in items.iter().filter(|item| item.contains_key("define")) {
+        definitions.add(item)?;
+    }
+    let mut camera = None;
+    let mut lights = Vec::new();
+    let mut world = World::empty();
+    let mut next_id = 1;
+    for item in items.iter().filter(|item| !item.contains_key("define")) {
+        let kind = field(item, "add")?
+            .as_str()
+            .ok_or_else(|| invalid("`add` must be a string"))?;
+        match kind {
+            "camera" => camera = Some(parse_camera(item)?),
+            "light" => lights.push(Light::point_light(
+                parse_point(field(item, "at")?)?,
+                parse_color(field(item, "intensity")?)?,
+            )),
+            _ => {
+                world = world.add_object(parse_shape(kind, next_id, item, &definitions)?)?;
+                next_id += 1;
+            }
+        }
+    }
+    let camera = camera.ok_or_else(|| invalid("the scene has no camera"))?;
+    Ok((world.set_lights(lights), camera))
+}
+
+struct Definition {
+    extend: Option<String>,
+    value: Value,
+}
+
+#[derive(Default)]
+struct Definitions {
+    entries: HashMap<String, Definition>,
+}
+
+impl Definitions {
+    // accepts `define: name` next to `value` as in the book, or `define: { name, value }`
+    fn add(&mut self, item: &Mapping) -> Result<()> {
+        let (name, fields) = match field(item, "define")? {
+            Value::String(name) => (name.clone(), item),
+            Value::Mapping(fields) => (
+                field(fields, "name")?
+                    .as_str()
+                    .ok_or_else(|| invalid("`name` must be a string"))?
+                    .to_string(),
+                fields,
+            ),
+            _ => return Err(invalid("`define` must be a name or a mapping")),
+        };
+        let extend = match fields.get("extend") {
+            Some(base) => Some(
+                base.as_str()
+                    .ok_or_else(|| invalid("`extend` must be a name"))?
+                    .to_string(),
+            ),
+            None => None,
+        };
+        let value = field(fields, "value")?.clone();
+        self.entries.insert(name, Definition { extend, value });
+        Ok(())
+    }
+
+    // `visiting` holds the definitions being resolved to detect cycles
+    fn resolve(&self, name: &str, visiting: &mut Vec<String>) -> Result<Value> {
+        if visiting.iter().any(|n| n == name) {
+            return Err(invalid(&format!("cyclic definition of `{}`", name)));
+        }
+        let definition = self
+            .entries
+            .get(name)
+            .ok_or_else(|| invalid(&format!("undefined reference to `{}`", name)))?;
+        visiting.push(name.to_string());
+        let value = match &definition.extend {
+            Some(base) => merge(self.resolve(base, visiting)?, &definition.value)?,
+            None => definition.value.clone(),
+        };
+        visiting.pop();
+        Ok(value)
+    }
+
+    // a material is either inline or the name of a definition
+    fn material(&self, value: &Value) -> Result<Material> {
+        match value {
+            Value::String(name) => parse_material(&self.resolve(name, &mut vec![])?),
+            _ => parse_material(value),
+        }
+    }
+
+    // named transforms are expanded in place
+    fn transform_steps(&self, value: &Value, visiting: &mut Vec<String>) -> Result<Vec<Value>> {
+        let steps = value
+            .as_sequence()
+            .ok_or_else(|| invalid("`transform` must be a list"))?;
+        let mut expanded = Vec::new();
+        for step in steps {
+            match step {
+                Value::String(name) => {
+                    let definition = self.resolve(name, visiting)?;
+                    visiting.push(name.clone());
+                    expanded.extend(self.transform_steps(&definition, visiting)?);
+                    visiting.pop();
+                }
+                _ => expanded.push(step.clone()),
+            }
+        }
+        Ok(expanded)
+    }
+}
+
+// the extending fields override the base ones, extending a list appends to it
+fn merge(base: Value, extension: &Value) -> Result<Value> {
+    match (base, extension) {
+        (Value::Mapping(mut base), Value::Mapping(fields)) => {
+            fields.iter().for_each(|(key, value)| {
+                base.insert(key.clone(), value.clone());
+            });
+            Ok(Value::Mapping(base))
+        }
+        (Value::Sequence(mut base), Value::Sequence(steps)) => {
+            base.extend(steps.iter().cloned());
+            Ok(Value::Sequence(base))
+        }
+        _ => Err(invalid(
+            "only materials and transforms of the same kind can be extended",
+        )),
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+fn field<'a>(mapping: &'a Mapping, key: &str) -> Result<&'a Value> {
+    mapping
+        .get(key)
+        .ok_or_else(|| invalid(&format!("missing field `{}`", key)))
+}
+
+fn parse_number(value: &Value) -> Result<f64> {
+    value
+        .as_f64()
+        .ok_or_else(|| invalid(&format!("expected a number but got {:?}", value)))
+}
+
+fn parse_numbers(value: &Value, count: usize) -> Result<Vec<f64>> {
+    let numbers = value
+        .as_sequence()
+        .ok_or_else(|| invalid(&format!("expected a list but got {:?}", value)))?
+        .iter()
+        .map(parse_number)
+        .collect::<Result<Vec<f64>>>()?;
+    if numbers.len() == count {
+        Ok(numbers)
+    } else {
+        Err(invalid(&format!(
+            "expected {} numbers but got {}",
+            count,
+            numbers.len()
+        )))
+    }
+}
+
+fn parse_point(value: &Value) -> Result<Tuple> {
+    let n = parse_numbers(value, 3)?;
+    Ok(point(n[0], n[1], n[2]))
+}
+
+fn parse_color(value: &Value) -> Result<Color> {
+    let n = parse_numbers(value, 3)?;
+    Ok(Color::make(n[0], n[1], n[2]))
+}
+
+fn parse_camera(item: &Mapping) -> Result<Camera> {
+    let size = |key: &str| -> Result<usize> {
+        field(item, key)?
+            .as_u64()
+            .map(|n| n as usize)
+            .ok_or_else(|| invalid(&format!("`{}` must be a positive integer", key)))
+    };
+    let from = parse_point(field(item, "from")?)?;
+    let to = parse_point(field(item, "to")?)?;
+    let up = parse_numbers(field(item, "up")?, 3)?;
+    let transform = view_transform(&from, &to, &vector(up[0], up[1], up[2]));
+    Ok(Camera::new(
+        size("width")?,
+        size("height")?,
+        parse_number(field(item, "field-of-view")?)?,
+    )
+    .set_transform(transform))
+}
+
+fn parse_shape(
+    kind: &str,
+    id: usize,
+    item: &Mapping,
+    definitions: &Definitions,
+) -> Result<Box<dyn Shape>> {
+    let transform = match item.get("transform") {
+        Some(value) => parse_transform(&definitions.transform_steps(value, &mut vec![])?)?,
+        None => Matrix::identity(),
+    };
+    let material = match item.get("material") {
+        Some(value) => definitions.material(value)?,
+        None => Material::default(),
+    };
+    let shape: Box<dyn Shape> = match kind {
+        "sphere" => Box::new(
+            Sphere::new(id)
+                .set_transform(transform)
+                .set_material(material),
+        ),
+        "plane" => Box::new(
+            Plane::new(id)
+                .set_transform(transform)
+                .set_material(material),
+        ),
+        "cube" => Box::new(
+            Cube::new(id)
+                .set_transform(transform)
+                .set_material(material),
+        ),
+        _ => return Err(invalid(&format!("unknown entry `{}`", kind))),
+    };
+    Ok(shape)
+}
+
+// the transformations are listed in the order they are applied
+fn parse_transform(steps: &[Value]) -> Result<Matrix> {
+    steps
+        .iter()
+        .try_fold(TransformBuilder::new(), |builder, step| {
+            let step = step
+                .as_sequence()
+                .ok_or_else(|| invalid("transform steps must be lists"))?;
+            let (name, args) = step
+                .split_first()
+                .ok_or_else(|| invalid("empty transform step"))?;
+            let args = args
+                .iter()
+                .map(parse_number)
+                .collect::<Result<Vec<f64>>>()?;
+            let name = name.as_str().unwrap_or_default();
+            match (name, args.as_slice()) {
+                ("translate", [x, y, z]) => Ok(builder.translate(*x, *y, *z)),
+                ("scale", [x, y, z]) => Ok(builder.scale(*x, *y, *z)),
+                ("rotate-x", [angle]) => Ok(builder.rotate_x(*angle)),
+                ("rotate-y", [angle]) => Ok(builder.rotate_y(*angle)),
+                ("rotate-z", [angle]) => Ok(builder.rotate_z(*angle)),
+                ("shear", [xy, xz, yx, yz, zx, zy]) => {
+                    Ok(builder.shear(*xy, *xz, *yx, *yz, *zx, *zy))
+                }
+                _ => Err(invalid(&format!(
+                    "invalid transform `{}` with {} arguments",
+                    name,
+                    args.len()
+                ))),
+            }
+        })
+        .map(|builder| builder.build())
+}
+
+// unspecified fields keep their default value
+fn parse_material(value: &Value) -> Result<Material> {
+    let fields = value
+        .as_mapping()
+        .ok_or_else(|| invalid("`material` must be a mapping"))?;
+    let mut material = Material::default();
+    for (key, value) in fields {
+        match key.as_str().unwrap_or_default() {
+            "color" => material.color = parse_color(value)?,
+            "ambient" => material.ambient = parse_number(value)?,
+            "diffuse" => material.diffuse = parse_number(value)?,
+            "specular" => material.specular = parse_number(value)?,
+            "shininess" => material.shininess = parse_number(value)?,
+            "reflective" => material.reflective = parse_number(value)?,
+            "transparency" => material.transparency = parse_number(value)?,
+            "refractive-index" => material.refractive_index = parse_number(value)?,
+            other => return Err(invalid(&format!("unknown material field `{}`", other))),
+        }
+    }
+    Ok(material)
+}
+
+#[cfg(test)]
+mod scene_tests {
+    use crate::color::Color;
+    use crate::matrix::Matrix;
+    use crate::scene::*;
+    use crate::tuple::point;
+
+    const SCENE: &str = "
+- add: camera
+  width: 100
+  height: 50
+  field-of-view: 0.785
+  from: [-6, 6, -10]
+  to: [6, 0, 6]
+  up: [-0.45, 1, 0]
+
+- add: light
+  at: [50, 100, -50]
+  intensity: [1, 1, 1]
+
+- add: sphere
+  transform:
+    - [scale, 0.5, 0.5, 0.5]
+    - [translate, 1, 2, 3]
+  material:
+    color: [1, 0.2, 0.3]
+    diffuse: 0.7
+    reflective: 0.1
+";
+
+    #[test]
+    fn load_scene_with_camera_light_and_sphere() {
+        let (world, camera) = load_scene(SCENE).unwrap();
+        assert_eq!(world.objects.len(), 1);
+        assert_eq!(world.lights.len(), 1);
+        assert_eq!(world.lights[0].position, point(50.0, 100.0, -50.0));
+        assert_eq!(camera.hsize(), 100);
+        assert_eq!(camera.vsize(), 50);
+
+        let sphere = &world.objects[0];
+        assert_eq!(
+            sphere.transform().matrix,
+            Matrix::translation(1.0, 2.0, 3.0).multiply(&Matrix::scaling(0.5, 0.5, 0.5))
+        );
+        let material = sphere.material();
+        assert_eq!(material.color, Color::make(1.0, 0.2, 0.3));
+        assert_eq!(material.diffuse, 0.7);
+        assert_eq!(material.reflective, 0.1);
+        // unspecified fields keep the defaults
+        assert_eq!(material.ambient, 0.1);
+    }
+
+    #[test]
+    fn load_scene_rejects_invalid_entries() {
+        assert!(load_scene("- add: light\n  at: [1, 2, 3]\n  intensity: [1, 1, 1]\n").is_err());
+        let unknown_shape = format!("{}\n- add: teapot\n", SCENE);
+        assert!(load_scene(&unknown_shape).is_err());
+        let invalid_transform =
+            format!("{}\n- add: plane\n  transform:\n    - [scale, 1]\n", SCENE);
+        assert!(load_scene(&invalid_transform).is_err());
+    }
+
+    const DEFINITIONS: &str = "
+- define: white-material
+  value:
+    color: [1, 1, 1]
+    diffuse: 0.7
+    ambient: 0.1
+    specular: 0.0
+    reflective: 0.1
+
+- define: blue-material
+  extend: white-material
+  value:
+    color: [0.537, 0.831, 0.914]
+
+- define: { name: standard-transform, value: [[translate, 1, -1, 1], [scale, 0.5, 0.5, 0.5]] }
+
+- define: large-object
+  value:
+    - standard-transform
+    - [scale, 3.5, 3.5, 3.5]
+";
+
+    #[test]
+    fn defined_material_is_shared_by_shapes() {
+        let scene = format!(
+            "{}{}
+- add: sphere
+  material: white-material
+
+- add: cube
+  material: white-material
+  transform:
+    - large-object
+",
+            SCENE, DEFINITIONS
+        );
+        let (world, _) = load_scene(&scene).unwrap();
+        assert_eq!(world.objects.len(), 3);
+        for shape in &world.objects[1..] {
+            let material = shape.material();
+            assert_eq!(material.color, Color::make(1.0, 1.0, 1.0));
+            assert_eq!(material.diffuse, 0.7);
+            assert_eq!(material.specular, 0.0);
+            assert_eq!(material.reflective, 0.1);
+        }
+        assert_eq!(
+            world.objects[2].transform().matrix,
+            Matrix::scaling(3.5, 3.5, 3.5)
+                .multiply(&Matrix::scaling(0.5, 0.5, 0.5))
+                .multiply(&Matrix::translation(1.0, -1.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn extended_material_overrides_fields() {
+        let scene = format!(
+            "{}{}
+- add: sphere
+  material: blue-material
+",
+            SCENE, DEFINITIONS
+        );
+        let (world, _) = load_scene(&scene).unwrap();
+        let material = world.objects[1].material();
+        assert_eq!(material.color, Color::make(0.537, 0.831, 0.914));
+        // inherited from white-material
+        assert_eq!(material.diffuse, 0.7);
+        assert_eq!(material.specular, 0.0);
+        assert_eq!(material.reflective, 0.1);
+    }
+
+    #[test]
+    fn undefined_and_cyclic_references_are_rejected() {
+        let undefined = format!(
+            "{}
+- add: sphere
+  material: missing-material
+",
+            SCENE
+        );
+        assert!(load_scene(&undefined).is_err());
+        let cyclic = format!(
+            "{}
+- define: a
+  extend: b
+  value:
+    diffuse: 0.5
+- define: b
+  extend: a
+  value:
+    diffuse: 0.6
+- add: sphere
+  material: a
+",
+            SCENE
+        );
+        let err = load_scene(&cyclic).err().unwrap();
+        assert!(err.to_string().contains("cyclic"));
+        let cyclic_transform = format!(
+            "{}
+- define: t
+  value:
+    - t
+- add: sphere
+  transform:
+    - t
+",
+            SCENE
+        );
+        assert!(load_scene(&cyclic_transform).is_err());
+    }
+}
@@ -1,96 +1,528 @@
+use crate::bounds::BoundingBox;
+use crate::cone::Cone;
+use crate::cube::Cube;
+use crate::cylinder::Cylinder;
+use crate::group::Group;
 use crate::intersection::Intersection;
 use crate::material::Material;
-use crate::matrix::Transformation;
+use crate::matrix::{Matrix, Transformation};
+use crate::plane::Plane;
 use crate::ray::Ray;
+use crate::sphere::Sphere;
 use crate::tuple::Tuple;
 use crate::tuple::*;
+use std::borrow::Cow;
 
-pub trait Shape {
+// shapes are shared across threads, e.g. when intersecting large groups in parallel
+pub trait Shape: Send + Sync {
     fn id(&self) -> usize;
     fn transform(&self) -> &Transformation;
     fn material(&self) -> &Material;
-    fn local_intersect(&self, local_ray: &Ray) -> Vec<Intersection>;
+    fn local_intersect(&self, local_ray: &Ray) -> Vec<Intersection<'_>>;
     fn local_normal_at(&self, local_point: &Tuple) -> Tuple;
+    // combined transform of the groups containing the shape, `None` at the top level
+    fn parent_transform(&self) -> Option<&Transformation>;
+    fn set_parent_transform(&mut self, parent: &Matrix);
+    // combined transform of the groups at the end of the exposure, `None` unless one of them moves
+    fn parent_end_transform(&self) -> Option<&Transformation>;
+    fn set_parent_end_transform(&mut self, parent_end: Option<&Matrix>);
+
+    // in place edition for shapes already in a world, see `World::object_mut`,
+    // `None` when the material is shared with other shapes or never used for shading
+    fn material_mut(&mut self) -> Option<&mut Material>;
+    fn set_transform_mut(&mut self, transform: Matrix);
+
+    // bounding box in object space, infinite unless the shape knows better
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::infinite()
+    }
+
+    // bounding box in the space of the parent group, covering the whole motion of moving shapes
+    fn parent_space_bounds(&self) -> BoundingBox {
+        let start = self.bounds().transform(&self.transform().matrix);
+        match self.end_transform() {
+            // interpolated points stay between their start and end positions
+            Some(end) => start.merge(&self.bounds().transform(&end.matrix)),
+            None => start,
+        }
+    }
+
+    // shapes nested inside this one, only groups and CSG have any
+    fn children(&self) -> &[Box<dyn Shape>] {
+        &[]
+    }
+
+    // splits large groups into a bounding volume hierarchy, see `Group::divide`
+    fn divide(&mut self, _threshold: usize) {}
+
+    // lets named groups be found among the children of a group
+    fn as_group(&self) -> Option<&Group> {
+        None
+    }
+
+    // true if the id belongs to this shape or to one of its descendants
+    fn includes(&self, id: usize) -> bool {
+        self.id() == id || self.children().iter().any(|c| c.includes(id))
+    }
+
+    // pose of the shape at the end of the exposure for motion blur, `None` for static shapes
+    fn end_transform(&self) -> Option<&Transformation> {
+        None
+    }
+
+    // transform interpolated between the start and end poses, `time` goes from 0 to 1
+    fn transform_at(&self, time: f64) -> Cow<'_, Transformation> {
+        match self.end_transform() {
+            Some(end) if time > 0.0 => Cow::Owned(Transformation::make(
+                self.transform().matrix.lerp(&end.matrix, time),
+            )),
+            _ => Cow::Borrowed(self.transform()),
+        }
+    }
+
+    // parent transform interpolated like `transform_at`, exact as long as a single level
+    // of the hierarchy moves
+    fn parent_transform_at(&self, time: f64) -> Option<Cow<'_, Transformation>> {
+        match (self.parent_transform(), self.parent_end_transform()) {
+            (Some(start), Some(end)) if time > 0.0 => Some(Cow::Owned(Transformation::make(
+                start.matrix.lerp(&end.matrix, time),
+            ))),
+            (start, _) => start.map(Cow::Borrowed),
+        }
+    }
+
+    // the intersection carries the u/v needed by smooth triangles, other shapes ignore it
+    fn local_normal_at_hit(&self, local_point: &Tuple, _hit: &Intersection) -> Tuple {
+        self.local_normal_at(local_point)
+    }
 
     fn normal_at(&self, p: &Tuple) -> Tuple {
-        let local_point = self.transform().inverse.multiply_tuple(p);
-        let local_normal = self.local_normal_at(&local_point);
-        let world_normal = self
-            .transform()
-            .inverse_transpose
-            .multiply_tuple(&local_normal);
-        let tmp = vector(world_normal.0, world_normal.1, world_normal.2);
-        vector_normalize(&tmp)
-    }
-
-    fn intersect(&self, ray: &Ray) -> Vec<Intersection> {
-        let local_ray = ray.transform(&self.transform().inverse);
+        self.world_normal(p, None, 0.0)
+    }
+
+    fn normal_at_hit(&self, p: &Tuple, hit: &Intersection) -> Tuple {
+        self.world_normal(p, Some(hit), 0.0)
+    }
+
+    // world space point to object space, through the groups containing the shape
+    fn world_to_object(&self, p: &Tuple) -> Tuple {
+        point_to_object(self.parent_transform(), self.transform(), p)
+    }
+
+    // object space normal to world space, through the groups containing the shape
+    fn normal_to_world(&self, normal: &Tuple) -> Tuple {
+        normal_from_object(self.parent_transform(), self.transform(), normal)
+    }
+
+    // normal in world space at the given time, using the hit when there is one
+    fn world_normal(&self, p: &Tuple, hit: Option<&Intersection>, time: f64) -> Tuple {
+        let transform = self.transform_at(time);
+        let parent = self.parent_transform_at(time);
+        let local_point = point_to_object(parent.as_deref(), &transform, p);
+        let local_normal = match hit {
+            Some(hit) => self.local_normal_at_hit(&local_point, hit),
+            None => self.local_normal_at(&local_point),
+        };
+        normal_from_object(parent.as_deref(), &transform, &local_normal)
+    }
+
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let local_ray = ray.transform(&self.transform_at(ray.time).inverse);
         self.local_intersect(&local_ray)
     }
 }
 
-#[cfg(test)]
-mod shape_tests {
-    use crate::intersection::Intersection;
-    use crate::material::Material;
-    use crate::matrix::Matrix;
-    use crate::matrix::Transformation;
-    use crate::ray::Ray;
-    use crate::shape::Shape;
+// (start, end) transforms from the space of a group to the world space, given the group's
+// own poses and the ones of its parents, the end is `None` when nothing moves
+pub(crate) fn world_matrices(
+    parent: Option<&Transformation>,
+    parent_end: Option<&Transformation>,
+    transform: &Transformation,
+    end_transform: Option<&Transformation>,
+) -> (Matrix, Option<Matrix>) {
+    let combine = |parent: Option<&Transformation>, transform: &Transformation| match parent {
+        Some(parent) => parent.matrix.multiply(&transform.matrix),
+        None => transform.matrix.clone(),
+    };
+    let start = combine(parent, transform);
+    let end = (parent_end.is_some() || end_transform.is_some())
+        .then(|| combine(parent_end.or(parent), end_transform.unwrap_or(transform)));
+    (start, end)
+}
+
+// the parent transform combines the transforms of all the groups containing the shape
+fn point_to_object(
+    parent: Option<&Transformation>,
+    transform: &Transformation,
+    p: &Tuple,
+) -> Tuple {
+    let object_point = match parent {
+        Some(parent) => parent.inverse.multiply_tuple(p),
+        None => *p,
+    };
+    transform.inverse.multiply_tuple(&object_point)
+}
 
-    struct TestShape {
-        transform: Transformation,
-        material: Material,
+fn normal_from_object(
+    parent: Option<&Transformation>,
+    transform: &Transformation,
+    normal: &Tuple,
+) -> Tuple {
+    // the w component would be discarded anyway, the 3x3 product skips the translation
+    let mut world_normal = transform.inverse_transpose.multiply_vector3(normal);
+    if let Some(parent) = parent {
+        world_normal = parent.inverse_transpose.multiply_vector3(&world_normal);
     }
+    vector_normalize(&world_normal)
+}
 
-    impl TestShape {
-        fn new() -> Self {
-            TestShape {
-                transform: Transformation::default(),
-                material: Material::default(),
-            }
-        }
+// closed set of the common shapes dispatched with a match instead of a vtable,
+// a `World<ShapeEnum>` stores them directly, it implements `Shape` so it can be used
+// anywhere a `dyn Shape` is expected as well
+pub enum ShapeEnum {
+    Sphere(Sphere),
+    Plane(Plane),
+    Cube(Cube),
+    Cylinder(Cylinder),
+    Cone(Cone),
+    Group(Group),
+}
 
-        fn set_transform(self, transform: Matrix) -> TestShape {
-            let inverse = Matrix::inverse(&transform);
-            let inverse_transpose = inverse.transpose();
-            let transformation = Transformation {
-                matrix: transform,
-                inverse,
-                inverse_transpose,
-            };
-            TestShape {
-                transform: transformation,
-                ..self
-            }
+// calls the same method on the shape held by any variant
+macro_rules! dispatch {
+    ($value:expr, $shape:ident => $call:expr) => {
+        match $value {
+            ShapeEnum::Sphere($shape) => $call,
+            ShapeEnum::Plane($shape) => $call,
+            ShapeEnum::Cube($shape) => $call,
+            ShapeEnum::Cylinder($shape) => $call,
+            ShapeEnum::Cone($shape) => $call,
+            ShapeEnum::Group($shape) => $call,
         }
+    };
+}
 
-        fn set_material(self, material: Material) -> TestShape {
-            TestShape { material, ..self }
-        }
+impl Shape for ShapeEnum {
+    fn id(&self) -> usize {
+        dispatch!(self, s => s.id())
     }
 
-    impl Shape for TestShape {
-        fn id(&self) -> usize {
-            unimplemented!()
-        }
+    fn transform(&self) -> &Transformation {
+        dispatch!(self, s => s.transform())
+    }
 
-        fn transform(&self) -> &Transformation {
-            &self.transform
-        }
+    fn material(&self) -> &Material {
+        dispatch!(self, s => s.material())
+    }
+
+    fn material_mut(&mut self) -> Option<&mut Material> {
+        dispatch!(self, s => s.material_mut())
+    }
+
+    fn set_transform_mut(&mut self, transform: Matrix) {
+        dispatch!(self, s => s.set_transform_mut(transform))
+    }
+
+    fn local_intersect(&self, local_ray: &Ray) -> Vec<Intersection<'_>> {
+        dispatch!(self, s => s.local_intersect(local_ray))
+    }
+
+    fn local_normal_at(&self, local_point: &Tuple) -> Tuple {
+        dispatch!(self, s => s.local_normal_at(local_point))
+    }
+
+    fn parent_end_transform(&self) -> Option<&Transformation> {
+        dispatch!(self, s => s.parent_end_transform())
+    }
+
+    fn set_parent_end_transform(&mut self, parent_end: Option<&Matrix>) {
+        dispatch!(self, s => s.set_parent_end_transform(parent_end))
+    }
+
+    fn parent_transform(&self) -> Option<&Transformation> {
+        dispatch!(self, s => s.parent_transform())
+    }
+
+    fn set_parent_transform(&mut self, parent: &Matrix) {
+        dispatch!(self, s => s.set_parent_transform(parent))
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        dispatch!(self, s => s.bounds())
+    }
+
+    fn children(&self) -> &[Box<dyn Shape>] {
+        dispatch!(self, s => s.children())
+    }
+
+    fn divide(&mut self, threshold: usize) {
+        dispatch!(self, s => s.divide(threshold))
+    }
 
-        fn material(&self) -> &Material {
-            &self.material
+    fn as_group(&self) -> Option<&Group> {
+        dispatch!(self, s => s.as_group())
+    }
+
+    fn end_transform(&self) -> Option<&Transformation> {
+        dispatch!(self, s => s.end_transform())
+    }
+
+    fn normal_at(&self, p: &Tuple) -> Tuple {
+        dispatch!(self, s => s.normal_at(p))
+    }
+
+    fn local_normal_at_hit(&self, local_point: &Tuple, hit: &Intersection) -> Tuple {
+        dispatch!(self, s => s.local_normal_at_hit(local_point, hit))
+    }
+
+    fn world_normal(&self, p: &Tuple, hit: Option<&Intersection>, time: f64) -> Tuple {
+        dispatch!(self, s => s.world_normal(p, hit, time))
+    }
+
+    fn intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        dispatch!(self, s => s.intersect(ray))
+    }
+}
+
+impl From<Sphere> for ShapeEnum {
+    fn from(sphere: Sphere) -> Self {
+        ShapeEnum::Sphere(sphere)
+    }
+}
+
+impl From<Plane> for ShapeEnum {
+    fn from(plane: Plane) -> Self {
+        ShapeEnum::Plane(plane)
+    }
+}
+
+impl From<Cube> for ShapeEnum {
+    fn from(cube: Cube) -> Self {
+        ShapeEnum::Cube(cube)
+    }
+}
+
+impl From<Cylinder> for ShapeEnum {
+    fn from(cylinder: Cylinder) -> Self {
+        ShapeEnum::Cylinder(cylinder)
+    }
+}
+
+impl From<Cone> for ShapeEnum {
+    fn from(cone: Cone) -> Self {
+        ShapeEnum::Cone(cone)
+    }
+}
+
+impl From<Group> for ShapeEnum {
+    fn from(group: Group) -> Self {
+        ShapeEnum::Group(group)
+    }
+}
+
+// shape recording the last ray it was intersected with in object space,
+// used by the tests to check the transformations applied before reaching a shape
+#[cfg(test)]
+pub(crate) struct TestShape {
+    id: usize,
+    transform: Transformation,
+    parent_transform: Option<Transformation>,
+    material: Material,
+    saved_ray: std::sync::Mutex<Option<Ray>>,
+}
+
+#[cfg(test)]
+impl TestShape {
+    pub(crate) fn new() -> Self {
+        TestShape {
+            id: 0,
+            transform: Transformation::default(),
+            parent_transform: None,
+            material: Material::default(),
+            saved_ray: std::sync::Mutex::new(None),
         }
+    }
 
-        fn local_intersect(&self, local_ray: &Ray) -> Vec<Intersection> {
-            unimplemented!()
+    pub(crate) fn set_transform(self, transform: Matrix) -> TestShape {
+        TestShape {
+            transform: Transformation::make(transform),
+            ..self
         }
+    }
+
+    pub(crate) fn set_material(self, material: Material) -> TestShape {
+        TestShape { material, ..self }
+    }
+
+    pub(crate) fn saved_ray(&self) -> Option<Ray> {
+        *self.saved_ray.lock().unwrap()
+    }
+}
 
-        fn local_normal_at(&self, local_point: &(f64, f64, f64, f64)) -> (f64, f64, f64, f64) {
-            unimplemented!()
+#[cfg(test)]
+impl Shape for TestShape {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn parent_transform(&self) -> Option<&Transformation> {
+        self.parent_transform.as_ref()
+    }
+
+    fn set_parent_transform(&mut self, parent: &Matrix) {
+        self.parent_transform = Some(Transformation::make(parent.clone()));
+    }
+
+    fn parent_end_transform(&self) -> Option<&Transformation> {
+        None
+    }
+
+    fn set_parent_end_transform(&mut self, _parent_end: Option<&Matrix>) {}
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> Option<&mut Material> {
+        Some(&mut self.material)
+    }
+
+    fn set_transform_mut(&mut self, transform: Matrix) {
+        self.transform = Transformation::make(transform);
+    }
+
+    fn local_intersect(&self, local_ray: &Ray) -> Vec<Intersection<'_>> {
+        *self.saved_ray.lock().unwrap() = Some(*local_ray);
+        vec![]
+    }
+
+    // the point itself, seen as a vector from the origin
+    fn local_normal_at(&self, local_point: &Tuple) -> Tuple {
+        vector(local_point.0, local_point.1, local_point.2)
+    }
+}
+
+// unit cube sized shape recording how many times it was intersected
+#[cfg(test)]
+pub(crate) struct CountingShape {
+    id: usize,
+    calls: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+    transform: Transformation,
+    material: Material,
+}
+
+#[cfg(test)]
+impl CountingShape {
+    pub(crate) fn new(
+        id: usize,
+        calls: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+        transform: Matrix,
+    ) -> Self {
+        CountingShape {
+            id,
+            calls,
+            transform: Transformation::make(transform),
+            material: Material::default(),
         }
     }
+}
+
+#[cfg(test)]
+impl Shape for CountingShape {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn parent_transform(&self) -> Option<&Transformation> {
+        None
+    }
+
+    fn set_parent_transform(&mut self, _parent: &Matrix) {}
+
+    fn parent_end_transform(&self) -> Option<&Transformation> {
+        None
+    }
+
+    fn set_parent_end_transform(&mut self, _parent_end: Option<&Matrix>) {}
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> Option<&mut Material> {
+        Some(&mut self.material)
+    }
+
+    fn set_transform_mut(&mut self, transform: Matrix) {
+        self.transform = Transformation::make(transform);
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::new(point(-1.0, -1.0, -1.0), point(1.0, 1.0, 1.0))
+    }
+
+    fn local_intersect(&self, _local_ray: &Ray) -> Vec<Intersection<'_>> {
+        self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        vec![]
+    }
+
+    fn local_normal_at(&self, local_point: &Tuple) -> Tuple {
+        vector(local_point.0, local_point.1, local_point.2)
+    }
+}
+
+#[cfg(test)]
+mod shape_tests {
+    use crate::camera::Camera;
+    use crate::color::Color;
+    use crate::cone::Cone;
+    use crate::cube::Cube;
+    use crate::cylinder::Cylinder;
+    use crate::epsilon::EPSILON;
+    use crate::group::Group;
+    use crate::intersection::Intersection;
+    use crate::light::Light;
+    use crate::material::Material;
+    use crate::matrix::Matrix;
+    use crate::plane::Plane;
+    use crate::ray::Ray;
+    use crate::shape::{Shape, ShapeEnum, TestShape};
+    use crate::sphere::Sphere;
+    use crate::torus::Torus;
+    use crate::transformation::view_transform;
+    use crate::triangle::{SmoothTriangle, Triangle};
+    use crate::tuple::{point, tuples_are_equal, vector, vector_normalize};
+    use crate::world::World;
+    use std::f64::consts::FRAC_PI_2;
+
+    #[test]
+    fn intersecting_scaled_shape_with_ray() {
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let s = TestShape::new().set_transform(Matrix::scaling(2.0, 2.0, 2.0));
+        assert!(s.saved_ray().is_none());
+        s.intersect(&r);
+        let saved = s.saved_ray().unwrap();
+        assert_eq!(saved.origin, point(0.0, 0.0, -2.5));
+        assert_eq!(saved.direction, vector(0.0, 0.0, 0.5));
+    }
+
+    #[test]
+    fn intersecting_translated_shape_with_ray() {
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let s = TestShape::new().set_transform(Matrix::translation(5.0, 0.0, 0.0));
+        s.intersect(&r);
+        let saved = s.saved_ray().unwrap();
+        assert_eq!(saved.origin, point(-5.0, 0.0, -5.0));
+        assert_eq!(saved.direction, vector(0.0, 0.0, 1.0));
+    }
 
     #[test]
     fn has_a_default_transformation() {
@@ -120,4 +552,136 @@ mod shape_tests {
         let s2 = s.set_material(new_m);
         assert_eq!(s2.material().specular, 1.0)
     }
+
+    #[test]
+    fn rendering_default_world_through_shape_enum() {
+        let objects = vec![
+            ShapeEnum::from(Sphere::new(1).set_material(Material::new(
+                Color::make(0.8, 1.0, 0.6),
+                0.7,
+                0.2,
+            ))),
+            ShapeEnum::from(Sphere::new(2).set_transform(Matrix::scaling(0.5, 0.5, 0.5))),
+        ];
+        let light = Light::point_light(point(-10.0, 10.0, -10.0), Color::make(1.0, 1.0, 1.0));
+        // the objects are stored in the world without boxing
+        let w: World<ShapeEnum> = World::from_objects(vec![light], objects);
+        let c = Camera::new(11, 11, FRAC_PI_2).set_transform(view_transform(
+            &point(0.0, 0.0, -5.0),
+            &point(0.0, 0.0, 0.0),
+            &vector(0.0, 1.0, 0.0),
+        ));
+        let canvas = c.render(&w);
+        assert_eq!(
+            canvas.color_at(5, 5).unwrap(),
+            Color::make(0.38066116930395194, 0.4758264616299399, 0.2854958769779639)
+        );
+    }
+
+    #[test]
+    fn shape_enum_delegates_to_the_shape() {
+        let cylinder = Cylinder::new(3).set_bounds(-1.0, 1.0).set_closed(true);
+        let expected = cylinder.bounds();
+        let s = ShapeEnum::from(cylinder);
+        assert_eq!(s.id(), 3);
+        assert_eq!(s.bounds(), expected);
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = s.intersect(&r);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].distance, 4.0);
+        assert_eq!(s.normal_at(&point(0.0, 0.0, -1.0)), vector(0.0, 0.0, -1.0));
+        let group = ShapeEnum::from(Group::new(4).add_child(Box::new(Cone::new(5))));
+        assert!(group.as_group().is_some());
+        assert!(group.includes(5));
+    }
+
+    #[test]
+    fn normal_fast_path_matches_full_matrix_product() {
+        let transform = Matrix::translation(1.0, -2.0, 3.0)
+            .multiply(&Matrix::rotate_y(0.7))
+            .multiply(&Matrix::scaling(2.0, 0.5, 1.5));
+        let s = Sphere::new(1).set_transform(transform.clone());
+        let inverse_transpose = transform.inverse().transpose();
+        let local_normal = vector_normalize(&vector(0.3, -0.6, 0.742));
+        let world_point = transform.multiply_tuple(&point(0.3, -0.6, 0.742));
+        let full = inverse_transpose.multiply_tuple(&local_normal);
+        let expected = vector_normalize(&vector(full.0, full.1, full.2));
+        assert!(tuples_are_equal(&s.normal_at(&world_point), &expected));
+    }
+
+    #[test]
+    fn every_shape_can_move() {
+        let end = || Matrix::translation(0.1, 0.2, 0.5);
+        let (p1, p2, p3) = (
+            point(0.0, 1.0, 0.0),
+            point(-1.0, 0.0, 0.0),
+            point(1.0, 0.0, 0.0),
+        );
+        let normals = (
+            vector(0.0, 1.0, 0.0),
+            vector(-1.0, 0.0, 0.0),
+            vector(1.0, 0.0, 0.0),
+        );
+        let group = || Group::new(1).add_child(Box::new(Sphere::new(2)));
+        // (moving shape, same shape standing at the end pose)
+        let cases: Vec<(Box<dyn Shape>, Box<dyn Shape>)> = vec![
+            (
+                Box::new(Sphere::new(1).set_motion(end())),
+                Box::new(Sphere::new(1).set_transform(end())),
+            ),
+            (
+                Box::new(Cube::new(1).set_motion(end())),
+                Box::new(Cube::new(1).set_transform(end())),
+            ),
+            (
+                Box::new(Cylinder::new(1).set_motion(end())),
+                Box::new(Cylinder::new(1).set_transform(end())),
+            ),
+            (
+                Box::new(Cone::new(1).set_motion(end())),
+                Box::new(Cone::new(1).set_transform(end())),
+            ),
+            (
+                Box::new(Plane::new(1).set_motion(end())),
+                Box::new(Plane::new(1).set_transform(end())),
+            ),
+            (
+                Box::new(Triangle::new(1, p1, p2, p3).set_motion(end())),
+                Box::new(Triangle::new(1, p1, p2, p3).set_transform(end())),
+            ),
+            (
+                Box::new(SmoothTriangle::new(1, (p1, p2, p3), normals).set_motion(end())),
+                Box::new(SmoothTriangle::new(1, (p1, p2, p3), normals).set_transform(end())),
+            ),
+            (
+                Box::new(Torus::new(1, 1.0, 0.25).set_motion(end())),
+                Box::new(Torus::new(1, 1.0, 0.25).set_transform(end())),
+            ),
+            (
+                Box::new(group().set_motion(end())),
+                Box::new(group().set_transform(end())),
+            ),
+        ];
+        let rays = [
+            Ray::new(point(0.2, 0.5, -5.0), vector(0.0, 0.0, 1.0)),
+            Ray::new(point(1.0, -5.0, 0.6), vector(0.0, 1.0, 0.0)),
+        ];
+        let distances = |xs: Vec<Intersection>| -> Vec<f64> {
+            xs.iter().map(|i| i.distance).collect::<Vec<f64>>()
+        };
+        for (moving, static_end) in &cases {
+            assert!(moving.end_transform().is_some());
+            let mut hits = 0;
+            for ray in &rays {
+                let at_end = distances(moving.intersect(&ray.set_time(1.0)));
+                let expected = distances(static_end.intersect(ray));
+                assert_eq!(at_end.len(), expected.len());
+                for (a, b) in at_end.iter().zip(&expected) {
+                    assert!((a - b).abs() < EPSILON, "{} != {}", a, b);
+                }
+                hits += at_end.len();
+            }
+            assert!(hits > 0);
+        }
+    }
 }
@@ -1,3 +1,4 @@
+use crate::bounds::BoundingBox;
 use crate::epsilon::EPSILON;
 use crate::intersection::*;
 use crate::material::Material;
@@ -13,6 +14,9 @@ pub struct Sphere {
     center: Tuple,
     radius: f64,
     transform: Transformation,
+    end_transform: Option<Transformation>, // pose at the end of the exposure
+    parent_transform: Option<Transformation>,
+    parent_end_transform: Option<Transformation>, // pose of the parents at the end of the exposure
     pub material: Material,
 }
 
@@ -23,6 +27,9 @@ impl Sphere {
             center: point_zero(),
             radius: 1.0,
             transform: Transformation::default(),
+            end_transform: None,
+            parent_transform: None,
+            parent_end_transform: None,
             material: Material::default(),
         }
     }
@@ -34,6 +41,14 @@ impl Sphere {
         }
     }
 
+    // the shape moves from its transform at time 0 to `end_transform` at time 1
+    pub fn set_motion(self, end_transform: Matrix) -> Sphere {
+        Sphere {
+            end_transform: Some(Transformation::make(end_transform)),
+            ..self
+        }
+    }
+
     pub fn set_material(self, material: Material) -> Sphere {
         Sphere { material, ..self }
     }
@@ -52,17 +67,53 @@ impl Shape for Sphere {
         &self.transform
     }
 
+    fn end_transform(&self) -> Option<&Transformation> {
+        self.end_transform.as_ref()
+    }
+
+    fn parent_transform(&self) -> Option<&Transformation> {
+        self.parent_transform.as_ref()
+    }
+
+    fn set_parent_transform(&mut self, parent: &Matrix) {
+        self.parent_transform = Some(Transformation::make(parent.clone()));
+    }
+
+    fn parent_end_transform(&self) -> Option<&Transformation> {
+        self.parent_end_transform.as_ref()
+    }
+
+    fn set_parent_end_transform(&mut self, parent_end: Option<&Matrix>) {
+        self.parent_end_transform = parent_end.map(|m| Transformation::make(m.clone()));
+    }
+
     fn material(&self) -> &Material {
         &self.material
     }
 
+    fn material_mut(&mut self) -> Option<&mut Material> {
+        Some(&mut self.material)
+    }
+
+    fn set_transform_mut(&mut self, transform: Matrix) {
+        self.transform = Transformation::make(transform);
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let r = self.radius;
+        BoundingBox::new(
+            add_tuple(&self.center, &vector(-r, -r, -r)),
+            add_tuple(&self.center, &vector(r, r, r)),
+        )
+    }
+
     // https://www.scratchapixel.com/lessons/3d-basic-rendering/minimal-ray-tracer-rendering-simple-shapes/ray-sphere-intersection
-    fn local_intersect(&self, local_ray: &Ray) -> Vec<Intersection> {
+    fn local_intersect(&self, local_ray: &Ray) -> Vec<Intersection<'_>> {
         // ray from the sphere center to the ray origin
         let sphere_to_ray = subtract_tuple(&local_ray.origin, &self.center);
         let a = vector_dot_product(&local_ray.direction, &local_ray.direction);
         let b = 2.0 * vector_dot_product(&local_ray.direction, &sphere_to_ray);
-        let c = vector_dot_product(&sphere_to_ray, &sphere_to_ray) - self.radius;
+        let c = vector_dot_product(&sphere_to_ray, &sphere_to_ray) - self.radius.powi(2);
         let discriminant = b.powi(2) - 4.0 * a * c;
         if discriminant < 0.0 {
             vec![]
@@ -96,7 +147,6 @@ impl Shape for Sphere {
 mod sphere_tests {
     use crate::material::Material;
     use crate::matrix::Matrix;
-    use crate::ray::*;
     use crate::shape::Shape;
     use crate::sphere::*;
     use std::f64::consts::PI;
@@ -155,6 +205,17 @@ mod sphere_tests {
         assert_eq!(intersections[1].distance, -4.0);
     }
 
+    #[test]
+    fn ray_intersects_sphere_with_custom_radius() {
+        let ray = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let sphere = Sphere::new(1).set_radius(2.0);
+        let intersections = sphere.intersect(&ray);
+        // the ray travels along the z axis and crosses the sphere at z = -2 and z = 2
+        assert_eq!(2, intersections.len());
+        assert_eq!(intersections[0].distance, 3.0);
+        assert_eq!(intersections[1].distance, 7.0);
+    }
+
     #[test]
     fn sphere_default_transform() {
         let s = Sphere::new(1);
@@ -181,6 +242,19 @@ mod sphere_tests {
         assert_eq!(intersections[1].distance, 7.0);
     }
 
+    #[test]
+    fn intersecting_moving_sphere_depends_on_ray_time() {
+        let sphere = Sphere::new(1).set_motion(Matrix::translation(0.0, 0.0, 2.0));
+        let distances = |time: f64| -> Vec<f64> {
+            let ray = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0)).set_time(time);
+            let xs = sphere.intersect(&ray);
+            xs.iter().map(|i| i.distance).collect()
+        };
+        assert_eq!(distances(0.0), vec![4.0, 6.0]);
+        assert_eq!(distances(0.5), vec![5.0, 7.0]);
+        assert_eq!(distances(1.0), vec![6.0, 8.0]);
+    }
+
     #[test]
     fn intersecting_translated_sphere_with_ray() {
         let ray = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
@@ -218,6 +292,14 @@ mod sphere_tests {
         assert_eq!(normal, vector(value, value, value))
     }
 
+    #[test]
+    fn normal_on_sphere_ignores_the_hit() {
+        let sphere = Sphere::new(1).set_transform(Matrix::translation(0.0, 1.0, 0.0));
+        let p = point(0.0, 1.6, -0.8);
+        let hit = Intersection::new_with_uv(1, 4.0, 0.3, 0.6);
+        assert_eq!(sphere.normal_at_hit(&p, &hit), sphere.normal_at(&p));
+    }
+
     #[test]
     fn normal_is_a_normalized_vector() {
         let sphere = Sphere::new(1);
@@ -239,14 +321,14 @@ mod sphere_tests {
         let sphere = Sphere::new(1).set_transform(trans);
         let value = 2.0_f64.sqrt() / 2.0;
         let normal = sphere.normal_at(&point(0.0, value, -value));
-        assert_eq!(
+        assert!(tuples_are_equal(
             &normal,
             &vector(
                 0.00000000000000000972703314792188,
                 0.9701425001453319,
                 -0.24253562503633297
             )
-        )
+        ))
     }
 
     #[test]
@@ -0,0 +1,69 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// what a render cost, see `Camera::render_with_stats`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct RenderStats {
+    pub rays: usize,          // rays cast from the camera
+    pub intersections: usize, // intersections found by every ray, including reflected and refracted ones
+    pub shadow_rays: usize, // rays testing if a point is occluded, for shadows and ambient occlusion
+}
+
+// updated by the world while rendering, atomics keep it usable through a shared reference
+#[derive(Debug, Default)]
+pub struct RenderCounters {
+    rays: AtomicUsize,
+    intersections: AtomicUsize,
+    shadow_rays: AtomicUsize,
+}
+
+impl RenderCounters {
+    pub fn add_ray(&self) {
+        self.rays.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn add_intersections(&self, count: usize) {
+        self.intersections.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn add_shadow_ray(&self) {
+        self.shadow_rays.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn reset(&self) {
+        self.rays.store(0, Ordering::Relaxed);
+        self.intersections.store(0, Ordering::Relaxed);
+        self.shadow_rays.store(0, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> RenderStats {
+        RenderStats {
+            rays: self.rays.load(Ordering::Relaxed),
+            intersections: self.intersections.load(Ordering::Relaxed),
+            shadow_rays: self.shadow_rays.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod stats_tests {
+    use crate::stats::*;
+
+    #[test]
+    fn counters_accumulate_until_reset() {
+        let counters = RenderCounters::default();
+        counters.add_ray();
+        counters.add_ray();
+        counters.add_intersections(3);
+        counters.add_shadow_ray();
+        assert_eq!(
+            counters.snapshot(),
+            RenderStats {
+                rays: 2,
+                intersections: 3,
+                shadow_rays: 1
+            }
+        );
+        counters.reset();
+        assert_eq!(counters.snapshot(), RenderStats::default());
+    }
+}
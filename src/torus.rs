@@ -0,0 +1,205 @@
+use crate::bounds::BoundingBox;
+use crate::intersection::Intersection;
+use crate::material::Material;
+use crate::math::solve_quartic;
+use crate::matrix::{Matrix, Transformation};
+use crate::ray::Ray;
+use crate::shape::Shape;
+use crate::tuple::*;
+
+// torus centered on the origin lying in the xz plane,
+// the tube of radius `minor_radius` goes around the y axis at a distance of `major_radius`
+#[derive(Debug)]
+pub struct Torus {
+    pub id: usize,
+    pub major_radius: f64,
+    pub minor_radius: f64,
+    transform: Transformation,
+    end_transform: Option<Transformation>, // pose at the end of the exposure
+    parent_transform: Option<Transformation>,
+    parent_end_transform: Option<Transformation>, // pose of the parents at the end of the exposure
+    pub material: Material,
+}
+
+impl Torus {
+    pub fn new(id: usize, major_radius: f64, minor_radius: f64) -> Torus {
+        Torus {
+            id,
+            major_radius,
+            minor_radius,
+            transform: Transformation::default(),
+            end_transform: None,
+            parent_transform: None,
+            parent_end_transform: None,
+            material: Material::default(),
+        }
+    }
+
+    pub fn set_transform(self, transform: Matrix) -> Torus {
+        Torus {
+            transform: Transformation::make(transform),
+            ..self
+        }
+    }
+
+    // the shape moves from its transform at time 0 to `end_transform` at time 1
+    pub fn set_motion(self, end_transform: Matrix) -> Torus {
+        Torus {
+            end_transform: Some(Transformation::make(end_transform)),
+            ..self
+        }
+    }
+
+    pub fn set_material(self, material: Material) -> Torus {
+        Torus { material, ..self }
+    }
+}
+
+impl Shape for Torus {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn parent_transform(&self) -> Option<&Transformation> {
+        self.parent_transform.as_ref()
+    }
+
+    fn set_parent_transform(&mut self, parent: &Matrix) {
+        self.parent_transform = Some(Transformation::make(parent.clone()));
+    }
+
+    fn end_transform(&self) -> Option<&Transformation> {
+        self.end_transform.as_ref()
+    }
+
+    fn parent_end_transform(&self) -> Option<&Transformation> {
+        self.parent_end_transform.as_ref()
+    }
+
+    fn set_parent_end_transform(&mut self, parent_end: Option<&Matrix>) {
+        self.parent_end_transform = parent_end.map(|m| Transformation::make(m.clone()));
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> Option<&mut Material> {
+        Some(&mut self.material)
+    }
+
+    fn set_transform_mut(&mut self, transform: Matrix) {
+        self.transform = Transformation::make(transform);
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        let outer = self.major_radius + self.minor_radius;
+        BoundingBox::new(
+            point(-outer, -self.minor_radius, -outer),
+            point(outer, self.minor_radius, outer),
+        )
+    }
+
+    // substituting the ray in (x² + y² + z² + R² - r²)² = 4R²(x² + z²) gives a quartic in t
+    fn local_intersect(&self, local_ray: &Ray) -> Vec<Intersection<'_>> {
+        // solving the quartic is expensive, skip the rays missing the bounding box
+        if !self.bounds().intersects(local_ray) {
+            return vec![];
+        }
+        let (o, d) = (&local_ray.origin, &local_ray.direction);
+        let r2 = self.major_radius.powi(2);
+        let dd = d.0 * d.0 + d.1 * d.1 + d.2 * d.2;
+        let od = o.0 * d.0 + o.1 * d.1 + o.2 * d.2;
+        let k = o.0 * o.0 + o.1 * o.1 + o.2 * o.2 + r2 - self.minor_radius.powi(2);
+        let a = dd * dd;
+        let b = 4.0 * dd * od;
+        let c = 2.0 * dd * k + 4.0 * od * od - 4.0 * r2 * (d.0 * d.0 + d.2 * d.2);
+        let d_coefficient = 4.0 * od * k - 8.0 * r2 * (o.0 * d.0 + o.2 * d.2);
+        let e = k * k - 4.0 * r2 * (o.0 * o.0 + o.2 * o.2);
+        solve_quartic(a, b, c, d_coefficient, e)
+            .into_iter()
+            .map(|t| Intersection::new(self.id, t))
+            .collect()
+    }
+
+    // gradient of the implicit torus equation
+    fn local_normal_at(&self, local_point: &Tuple) -> Tuple {
+        let (x, y, z) = (local_point.0, local_point.1, local_point.2);
+        let k = x * x + y * y + z * z + self.major_radius.powi(2) - self.minor_radius.powi(2);
+        let ring = k - 2.0 * self.major_radius.powi(2);
+        vector(x * ring, y * k, z * ring)
+    }
+}
+
+#[cfg(test)]
+mod torus_tests {
+    use crate::epsilon::EPSILON;
+    use crate::matrix::Matrix;
+    use crate::ray::Ray;
+    use crate::shape::Shape;
+    use crate::torus::Torus;
+    use crate::tuple::*;
+
+    fn assert_distances(torus: &Torus, ray: &Ray, expected: &[f64]) {
+        let xs = torus.local_intersect(ray);
+        assert_eq!(xs.len(), expected.len());
+        for (i, t) in xs.iter().zip(expected) {
+            assert!((i.distance - t).abs() < EPSILON, "{} != {}", i.distance, t);
+        }
+    }
+
+    #[test]
+    fn ray_through_the_hole_misses_torus() {
+        let torus = Torus::new(1, 1.0, 0.25);
+        let r = Ray::new(point(0.0, 5.0, 0.0), vector(0.0, -1.0, 0.0));
+        assert!(torus.local_intersect(&r).is_empty());
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 1.0, 0.0));
+        assert!(torus.local_intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn ray_through_the_ring_hits_twice() {
+        let torus = Torus::new(1, 1.0, 0.25);
+        let r = Ray::new(point(1.0, 5.0, 0.0), vector(0.0, -1.0, 0.0));
+        assert_distances(&torus, &r, &[4.75, 5.25]);
+        // enters the tube at a distance of 1.25 from the y axis and leaves it on the other side
+        let r = Ray::new(point(1.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert_distances(&torus, &r, &[4.25, 5.75]);
+    }
+
+    #[test]
+    fn ray_across_the_torus_hits_four_times() {
+        let torus = Torus::new(1, 1.0, 0.25);
+        let r = Ray::new(point(-5.0, 0.0, 0.0), vector(1.0, 0.0, 0.0));
+        assert_distances(&torus, &r, &[3.75, 4.25, 5.75, 6.25]);
+    }
+
+    #[test]
+    fn intersecting_transformed_torus() {
+        let torus = Torus::new(1, 2.0, 0.5).set_transform(Matrix::translation(0.0, 0.0, 3.0));
+        let r = Ray::new(point(2.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = torus.intersect(&r);
+        assert_eq!(xs.len(), 2);
+        assert!((xs[0].distance - 6.5).abs() < EPSILON);
+        assert!((xs[1].distance - 9.5).abs() < EPSILON);
+    }
+
+    #[test]
+    fn normal_on_torus() {
+        let torus = Torus::new(1, 1.0, 0.25);
+        let cases = vec![
+            (point(1.25, 0.0, 0.0), vector(1.0, 0.0, 0.0)),
+            (point(0.75, 0.0, 0.0), vector(-1.0, 0.0, 0.0)),
+            (point(1.0, 0.25, 0.0), vector(0.0, 1.0, 0.0)),
+            (point(0.0, -0.25, -1.0), vector(0.0, -1.0, 0.0)),
+        ];
+        for (p, expected) in cases {
+            let n = torus.normal_at(&p);
+            assert!(tuples_are_equal(&n, &expected), "{:?}", n);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::matrix::Matrix;
+use crate::matrix::{Matrix, IDENTITY};
 use crate::tuple::{subtract_tuple, vector_cross_product, vector_normalize, Tuple};
 
 // from: position of the eye
@@ -18,12 +18,91 @@ pub fn view_transform(from: &Tuple, to: &Tuple, up: &Tuple) -> Matrix {
     orientation.multiply(&translation)
 }
 
+// chains transformations in the order they are applied to a point
+// e.g. `TransformBuilder::new().rotate_x(a).scale(5.0, 5.0, 5.0).build()` rotates first
+pub struct TransformBuilder {
+    matrix: Option<Matrix>, // `None` until a transformation is applied, borrows the shared identity
+}
+
+impl Default for TransformBuilder {
+    fn default() -> Self {
+        TransformBuilder::new()
+    }
+}
+
+impl TransformBuilder {
+    pub fn new() -> TransformBuilder {
+        TransformBuilder { matrix: None }
+    }
+
+    // the new transformation is applied after the existing ones
+    pub fn then(self, transform: Matrix) -> TransformBuilder {
+        TransformBuilder {
+            matrix: Some(transform.multiply(self.matrix.as_ref().unwrap_or(&IDENTITY))),
+        }
+    }
+
+    pub fn rotate_x(self, angle: f64) -> TransformBuilder {
+        self.then(Matrix::rotate_x(angle))
+    }
+
+    pub fn rotate_y(self, angle: f64) -> TransformBuilder {
+        self.then(Matrix::rotate_y(angle))
+    }
+
+    pub fn rotate_z(self, angle: f64) -> TransformBuilder {
+        self.then(Matrix::rotate_z(angle))
+    }
+
+    pub fn scale(self, x: f64, y: f64, z: f64) -> TransformBuilder {
+        self.then(Matrix::scaling(x, y, z))
+    }
+
+    pub fn translate(self, x: f64, y: f64, z: f64) -> TransformBuilder {
+        self.then(Matrix::translation(x, y, z))
+    }
+
+    pub fn shear(self, xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> TransformBuilder {
+        self.then(Matrix::shearing(xy, xz, yx, yz, zx, zy))
+    }
+
+    pub fn build(self) -> Matrix {
+        self.matrix.unwrap_or_else(|| IDENTITY.clone())
+    }
+}
+
 #[cfg(test)]
 mod transformation_tests {
     use crate::matrix::Matrix;
-    use crate::transformation::view_transform;
+    use crate::transformation::{view_transform, TransformBuilder};
     use crate::tuple::*;
 
+    #[test]
+    fn builder_applies_transformations_in_sequence() {
+        let p = point(1.0, 0.0, 1.0);
+        let transform = TransformBuilder::new()
+            .rotate_x(std::f64::consts::FRAC_PI_2)
+            .scale(5.0, 5.0, 5.0)
+            .translate(10.0, 5.0, 7.0)
+            .build();
+        assert_eq!(transform.multiply_tuple(&p), point(15.0, 0.0, 7.0));
+        let chain = Matrix::translation(10.0, 5.0, 7.0)
+            .multiply(&Matrix::scaling(5.0, 5.0, 5.0))
+            .multiply(&Matrix::rotate_x(std::f64::consts::FRAC_PI_2));
+        assert_eq!(transform, chain);
+    }
+
+    #[test]
+    fn empty_builder_is_identity() {
+        assert_eq!(TransformBuilder::new().build(), Matrix::identity());
+        assert_eq!(
+            TransformBuilder::default()
+                .shear(1.0, 0.0, 0.0, 0.0, 0.0, 0.0)
+                .build(),
+            Matrix::shearing(1.0, 0.0, 0.0, 0.0, 0.0, 0.0)
+        );
+    }
+
     #[test]
     fn transformation_for_default_orientation() {
         let from = point(0.0, 0.0, 0.0);
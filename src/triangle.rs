@@ -0,0 +1,423 @@
+use crate::bounds::BoundingBox;
+use crate::epsilon::EPSILON;
+use crate::intersection::Intersection;
+use crate::material::Material;
+use crate::matrix::{Matrix, Transformation};
+use crate::ray::Ray;
+use crate::shape::Shape;
+use crate::tuple::*;
+
+#[derive(Debug, PartialEq)]
+pub struct Triangle {
+    pub id: usize,
+    pub p1: Tuple,
+    pub p2: Tuple,
+    pub p3: Tuple,
+    e1: Tuple,
+    e2: Tuple,
+    normal: Tuple,
+    transform: Transformation,
+    end_transform: Option<Transformation>, // pose at the end of the exposure
+    parent_transform: Option<Transformation>,
+    parent_end_transform: Option<Transformation>, // pose of the parents at the end of the exposure
+    pub material: Material,
+}
+
+impl Triangle {
+    pub fn new(id: usize, p1: Tuple, p2: Tuple, p3: Tuple) -> Triangle {
+        let e1 = subtract_tuple(&p2, &p1);
+        let e2 = subtract_tuple(&p3, &p1);
+        let normal = vector_normalize(&vector_cross_product(&e2, &e1));
+        Triangle {
+            id,
+            p1,
+            p2,
+            p3,
+            e1,
+            e2,
+            normal,
+            transform: Transformation::default(),
+            end_transform: None,
+            parent_transform: None,
+            parent_end_transform: None,
+            material: Material::default(),
+        }
+    }
+
+    pub fn set_transform(self, transform: Matrix) -> Triangle {
+        Triangle {
+            transform: Transformation::make(transform),
+            ..self
+        }
+    }
+
+    // the shape moves from its transform at time 0 to `end_transform` at time 1
+    pub fn set_motion(self, end_transform: Matrix) -> Triangle {
+        Triangle {
+            end_transform: Some(Transformation::make(end_transform)),
+            ..self
+        }
+    }
+
+    pub fn set_material(self, material: Material) -> Triangle {
+        Triangle { material, ..self }
+    }
+}
+
+impl Shape for Triangle {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn parent_transform(&self) -> Option<&Transformation> {
+        self.parent_transform.as_ref()
+    }
+
+    fn set_parent_transform(&mut self, parent: &Matrix) {
+        self.parent_transform = Some(Transformation::make(parent.clone()));
+    }
+
+    fn end_transform(&self) -> Option<&Transformation> {
+        self.end_transform.as_ref()
+    }
+
+    fn parent_end_transform(&self) -> Option<&Transformation> {
+        self.parent_end_transform.as_ref()
+    }
+
+    fn set_parent_end_transform(&mut self, parent_end: Option<&Matrix>) {
+        self.parent_end_transform = parent_end.map(|m| Transformation::make(m.clone()));
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> Option<&mut Material> {
+        Some(&mut self.material)
+    }
+
+    fn set_transform_mut(&mut self, transform: Matrix) {
+        self.transform = Transformation::make(transform);
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::empty()
+            .add_point(&self.p1)
+            .add_point(&self.p2)
+            .add_point(&self.p3)
+    }
+
+    fn local_intersect(&self, local_ray: &Ray) -> Vec<Intersection<'_>> {
+        intersect_triangle(self.id, &self.p1, &self.e1, &self.e2, local_ray)
+    }
+
+    fn local_normal_at(&self, _local_point: &Tuple) -> Tuple {
+        self.normal
+    }
+}
+
+// A triangle interpolating the normals of its vertices to look curved.
+#[derive(Debug, PartialEq)]
+pub struct SmoothTriangle {
+    pub id: usize,
+    pub p1: Tuple,
+    pub p2: Tuple,
+    pub p3: Tuple,
+    pub n1: Tuple,
+    pub n2: Tuple,
+    pub n3: Tuple,
+    e1: Tuple,
+    e2: Tuple,
+    transform: Transformation,
+    end_transform: Option<Transformation>, // pose at the end of the exposure
+    parent_transform: Option<Transformation>,
+    parent_end_transform: Option<Transformation>, // pose of the parents at the end of the exposure
+    pub material: Material,
+}
+
+impl SmoothTriangle {
+    pub fn new(
+        id: usize,
+        (p1, p2, p3): (Tuple, Tuple, Tuple),
+        (n1, n2, n3): (Tuple, Tuple, Tuple),
+    ) -> SmoothTriangle {
+        SmoothTriangle {
+            id,
+            p1,
+            p2,
+            p3,
+            n1,
+            n2,
+            n3,
+            e1: subtract_tuple(&p2, &p1),
+            e2: subtract_tuple(&p3, &p1),
+            transform: Transformation::default(),
+            end_transform: None,
+            parent_transform: None,
+            parent_end_transform: None,
+            material: Material::default(),
+        }
+    }
+
+    pub fn set_transform(self, transform: Matrix) -> SmoothTriangle {
+        SmoothTriangle {
+            transform: Transformation::make(transform),
+            ..self
+        }
+    }
+
+    // the shape moves from its transform at time 0 to `end_transform` at time 1
+    pub fn set_motion(self, end_transform: Matrix) -> SmoothTriangle {
+        SmoothTriangle {
+            end_transform: Some(Transformation::make(end_transform)),
+            ..self
+        }
+    }
+
+    pub fn set_material(self, material: Material) -> SmoothTriangle {
+        SmoothTriangle { material, ..self }
+    }
+
+    // `u` weights the second vertex and `v` the third one
+    pub fn normal_at_uv(&self, u: f64, v: f64) -> Tuple {
+        let n = add_tuple(
+            &add_tuple(&scale_tuple(&self.n2, u), &scale_tuple(&self.n3, v)),
+            &scale_tuple(&self.n1, 1.0 - u - v),
+        );
+        vector(n.0, n.1, n.2)
+    }
+
+    // barycentric coordinates of a point lying on the triangle
+    fn uv_at(&self, p: &Tuple) -> (f64, f64) {
+        let w = subtract_tuple(p, &self.p1);
+        let d00 = vector_dot_product(&self.e1, &self.e1);
+        let d01 = vector_dot_product(&self.e1, &self.e2);
+        let d11 = vector_dot_product(&self.e2, &self.e2);
+        let d20 = vector_dot_product(&w, &self.e1);
+        let d21 = vector_dot_product(&w, &self.e2);
+        let denominator = d00 * d11 - d01 * d01;
+        let u = (d11 * d20 - d01 * d21) / denominator;
+        let v = (d00 * d21 - d01 * d20) / denominator;
+        (u, v)
+    }
+}
+
+impl Shape for SmoothTriangle {
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn transform(&self) -> &Transformation {
+        &self.transform
+    }
+
+    fn parent_transform(&self) -> Option<&Transformation> {
+        self.parent_transform.as_ref()
+    }
+
+    fn set_parent_transform(&mut self, parent: &Matrix) {
+        self.parent_transform = Some(Transformation::make(parent.clone()));
+    }
+
+    fn end_transform(&self) -> Option<&Transformation> {
+        self.end_transform.as_ref()
+    }
+
+    fn parent_end_transform(&self) -> Option<&Transformation> {
+        self.parent_end_transform.as_ref()
+    }
+
+    fn set_parent_end_transform(&mut self, parent_end: Option<&Matrix>) {
+        self.parent_end_transform = parent_end.map(|m| Transformation::make(m.clone()));
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn material_mut(&mut self) -> Option<&mut Material> {
+        Some(&mut self.material)
+    }
+
+    fn set_transform_mut(&mut self, transform: Matrix) {
+        self.transform = Transformation::make(transform);
+    }
+
+    fn bounds(&self) -> BoundingBox {
+        BoundingBox::empty()
+            .add_point(&self.p1)
+            .add_point(&self.p2)
+            .add_point(&self.p3)
+    }
+
+    fn local_intersect(&self, local_ray: &Ray) -> Vec<Intersection<'_>> {
+        intersect_triangle(self.id, &self.p1, &self.e1, &self.e2, local_ray)
+    }
+
+    // without a hit the u/v are recovered from the point lying on the triangle
+    fn local_normal_at(&self, local_point: &Tuple) -> Tuple {
+        let (u, v) = self.uv_at(local_point);
+        self.normal_at_uv(u, v)
+    }
+
+    fn local_normal_at_hit(&self, local_point: &Tuple, hit: &Intersection) -> Tuple {
+        match (hit.u, hit.v) {
+            (Some(u), Some(v)) => self.normal_at_uv(u, v),
+            _ => self.local_normal_at(local_point),
+        }
+    }
+}
+
+// Möller–Trumbore algorithm
+fn intersect_triangle(
+    id: usize,
+    p1: &Tuple,
+    e1: &Tuple,
+    e2: &Tuple,
+    ray: &Ray,
+) -> Vec<Intersection<'static>> {
+    let dir_cross_e2 = vector_cross_product(&ray.direction, e2);
+    let det = vector_dot_product(e1, &dir_cross_e2);
+    // the ray is parallel to the triangle
+    if det.abs() < EPSILON {
+        return vec![];
+    }
+    let f = 1.0 / det;
+    let p1_to_origin = subtract_tuple(&ray.origin, p1);
+    let u = f * vector_dot_product(&p1_to_origin, &dir_cross_e2);
+    if !(0.0..=1.0).contains(&u) {
+        return vec![];
+    }
+    let origin_cross_e1 = vector_cross_product(&p1_to_origin, e1);
+    let v = f * vector_dot_product(&ray.direction, &origin_cross_e1);
+    if v < 0.0 || u + v > 1.0 {
+        return vec![];
+    }
+    let distance = f * vector_dot_product(e2, &origin_cross_e1);
+    vec![Intersection::new_with_uv(id, distance, u, v)]
+}
+
+#[cfg(test)]
+mod triangle_tests {
+    use crate::ray::Ray;
+    use crate::shape::Shape;
+    use crate::triangle::*;
+
+    fn default_triangle() -> Triangle {
+        Triangle::new(
+            1,
+            point(0.0, 1.0, 0.0),
+            point(-1.0, 0.0, 0.0),
+            point(1.0, 0.0, 0.0),
+        )
+    }
+
+    fn default_smooth_triangle() -> SmoothTriangle {
+        SmoothTriangle::new(
+            1,
+            (
+                point(0.0, 1.0, 0.0),
+                point(-1.0, 0.0, 0.0),
+                point(1.0, 0.0, 0.0),
+            ),
+            (
+                vector(0.0, 1.0, 0.0),
+                vector(-1.0, 0.0, 0.0),
+                vector(1.0, 0.0, 0.0),
+            ),
+        )
+    }
+
+    #[test]
+    fn constructing_triangle() {
+        let t = default_triangle();
+        assert_eq!(t.e1, vector(-1.0, -1.0, 0.0));
+        assert_eq!(t.e2, vector(1.0, -1.0, 0.0));
+        assert_eq!(t.normal, vector(0.0, 0.0, -1.0));
+    }
+
+    #[test]
+    fn normal_on_triangle_is_constant() {
+        let t = default_triangle();
+        assert_eq!(t.local_normal_at(&point(0.0, 0.5, 0.0)), t.normal);
+        assert_eq!(t.local_normal_at(&point(-0.5, 0.75, 0.0)), t.normal);
+        assert_eq!(t.local_normal_at(&point(0.5, 0.25, 0.0)), t.normal);
+    }
+
+    #[test]
+    fn intersecting_ray_parallel_to_triangle() {
+        let t = default_triangle();
+        let r = Ray::new(point(0.0, -1.0, -2.0), vector(0.0, 1.0, 0.0));
+        assert!(t.local_intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn ray_misses_triangle_edges() {
+        let t = default_triangle();
+        let r = Ray::new(point(1.0, 1.0, -2.0), vector(0.0, 0.0, 1.0));
+        assert!(t.local_intersect(&r).is_empty());
+        let r = Ray::new(point(-1.0, 1.0, -2.0), vector(0.0, 0.0, 1.0));
+        assert!(t.local_intersect(&r).is_empty());
+        let r = Ray::new(point(0.0, -1.0, -2.0), vector(0.0, 0.0, 1.0));
+        assert!(t.local_intersect(&r).is_empty());
+    }
+
+    #[test]
+    fn ray_strikes_triangle() {
+        let t = default_triangle();
+        let r = Ray::new(point(0.0, 0.5, -2.0), vector(0.0, 0.0, 1.0));
+        let xs = t.local_intersect(&r);
+        assert_eq!(xs.len(), 1);
+        assert_eq!(xs[0].distance, 2.0);
+    }
+
+    #[test]
+    fn intersection_with_smooth_triangle_stores_u_and_v() {
+        let t = default_smooth_triangle();
+        let r = Ray::new(point(-0.2, 0.3, -2.0), vector(0.0, 0.0, 1.0));
+        let xs = t.local_intersect(&r);
+        assert!((xs[0].u.unwrap() - 0.45).abs() < EPSILON);
+        assert!((xs[0].v.unwrap() - 0.25).abs() < EPSILON);
+    }
+
+    #[test]
+    fn smooth_triangle_interpolates_normal() {
+        let t = default_smooth_triangle();
+        let n = t.normal_at(&point(-0.2, 0.3, 0.0));
+        assert!((n.0 - -0.5547).abs() < 0.0001);
+        assert!((n.1 - 0.83205).abs() < 0.0001);
+        assert_eq!(n.2, 0.0);
+    }
+
+    #[test]
+    fn smooth_triangle_normal_uses_the_hit_u_and_v() {
+        let t = default_smooth_triangle();
+        let hit = Intersection::new_with_uv(1, 1.0, 0.45, 0.25);
+        // the point is ignored in favor of the u/v of the hit
+        let n = t.normal_at_hit(&point(0.0, 0.0, 0.0), &hit);
+        assert!((n.0 - -0.5547).abs() < 0.0001);
+        assert!((n.1 - 0.83205).abs() < 0.0001);
+        assert_eq!(n.2, 0.0);
+        // hits without u/v fall back on the point
+        let n = t.normal_at_hit(&point(-0.2, 0.3, 0.0), &Intersection::new(1, 1.0));
+        assert!(tuples_are_equal(&n, &t.normal_at(&point(-0.2, 0.3, 0.0))));
+    }
+
+    #[test]
+    fn smooth_triangle_normal_at_center_blends_all_vertices() {
+        let t = default_smooth_triangle();
+        let n = t.normal_at_uv(1.0 / 3.0, 1.0 / 3.0);
+        assert!((n.0).abs() < EPSILON);
+        assert!((n.1 - 1.0 / 3.0).abs() < EPSILON);
+        assert_eq!(n.2, 0.0);
+        assert_eq!(
+            vector_normalize(&n),
+            t.normal_at(&point(0.0, 1.0 / 3.0, 0.0))
+        );
+    }
+}
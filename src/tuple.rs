@@ -1,3 +1,5 @@
+use std::io::{Error, ErrorKind, Result};
+
 pub type Tuple = (f64, f64, f64, f64);
 
 pub fn tuples_are_equal(t1: &Tuple, t2: &Tuple) -> bool {
@@ -23,6 +25,28 @@ pub fn subtract_tuple(t1: &Tuple, t2: &Tuple) -> Tuple {
     (t1.0 - t2.0, t1.1 - t2.1, t1.2 - t2.2, t1.3 - t2.3)
 }
 
+// same as `add_tuple` but rejects adding two points
+pub fn checked_add_tuple(t1: &Tuple, t2: &Tuple) -> Result<Tuple> {
+    checked_tuple(add_tuple(t1, t2), "cannot add a point to a point")
+}
+
+// same as `subtract_tuple` but rejects subtracting a point from a vector
+pub fn checked_subtract_tuple(t1: &Tuple, t2: &Tuple) -> Result<Tuple> {
+    checked_tuple(
+        subtract_tuple(t1, t2),
+        "cannot subtract a point from a vector",
+    )
+}
+
+// the result of a valid operation is either a point or a vector
+fn checked_tuple(t: Tuple, message: &str) -> Result<Tuple> {
+    if tuple_is_point(&t) || tuple_is_vector(&t) {
+        Ok(t)
+    } else {
+        Err(Error::new(ErrorKind::InvalidInput, message))
+    }
+}
+
 pub fn negate_tuple(t1: &Tuple) -> Tuple {
     (-t1.0, -t1.1, -t1.2, -t1.3)
 }
@@ -52,9 +76,15 @@ pub fn vector_magnitude(v: &Tuple) -> f64 {
     (v.0.powi(2) + v.1.powi(2) + v.2.powi(2)).sqrt()
 }
 
+// a degenerate vector has no direction and normalizes to the zero vector instead of NaNs,
+// the guard uses the machine epsilon so that the normals of tiny triangles stay valid
 pub fn vector_normalize(v: &Tuple) -> Tuple {
     let mag = vector_magnitude(v);
-    (v.0 / mag, v.1 / mag, v.2 / mag, 0.0)
+    if mag < f64::EPSILON {
+        vector(0.0, 0.0, 0.0)
+    } else {
+        (v.0 / mag, v.1 / mag, v.2 / mag, 0.0)
+    }
 }
 
 pub fn vector_dot_product(t1: &Tuple, t2: &Tuple) -> f64 {
@@ -77,10 +107,43 @@ pub fn vector_reflect(v: &Tuple, normal: &Tuple) -> Tuple {
     subtract_tuple(v, &other)
 }
 
+// componentwise interpolation, `a` at t = 0.0 and `b` at t = 1.0
+pub fn lerp_tuple(a: &Tuple, b: &Tuple, t: f64) -> Tuple {
+    add_tuple(a, &scale_tuple(&subtract_tuple(b, a), t))
+}
+
+pub fn tuple_distance(a: &Tuple, b: &Tuple) -> f64 {
+    vector_magnitude(&subtract_tuple(a, b))
+}
+
 #[cfg(test)]
 mod tuple_tests {
     use crate::tuple::*;
 
+    #[test]
+    fn checked_arithmetic_rejects_invalid_combinations() {
+        let p = point(3.0, -2.0, 5.0);
+        let v = vector(-2.0, 3.0, 1.0);
+        assert_eq!(checked_add_tuple(&v, &v).unwrap(), vector(-4.0, 6.0, 2.0));
+        assert_eq!(checked_add_tuple(&p, &v).unwrap(), point(1.0, 1.0, 6.0));
+        assert_eq!(checked_add_tuple(&v, &p).unwrap(), point(1.0, 1.0, 6.0));
+        assert!(checked_add_tuple(&p, &p).is_err());
+
+        assert_eq!(
+            checked_subtract_tuple(&p, &p).unwrap(),
+            vector(0.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            checked_subtract_tuple(&p, &v).unwrap(),
+            point(5.0, -5.0, 4.0)
+        );
+        assert_eq!(
+            checked_subtract_tuple(&v, &v).unwrap(),
+            vector(0.0, 0.0, 0.0)
+        );
+        assert!(checked_subtract_tuple(&v, &p).is_err());
+    }
+
     #[test]
     fn is_vector() {
         assert!(tuple_is_vector(&(1.0, 2.0, -3.0, 0.0)));
@@ -185,6 +248,15 @@ mod tuple_tests {
         assert_eq!(vector_normalize(&t), (1.0 / tmp, 2.0 / tmp, 3.0 / tmp, 0.0))
     }
 
+    #[test]
+    fn normalize_zero_vector() {
+        let n = vector_normalize(&vector(0.0, 0.0, 0.0));
+        assert_eq!(n, vector(0.0, 0.0, 0.0));
+        assert!(!n.0.is_nan() && !n.1.is_nan() && !n.2.is_nan());
+        let tiny = vector_normalize(&vector(1e-9, 0.0, 0.0));
+        assert_eq!(tiny, vector(1.0, 0.0, 0.0));
+    }
+
     #[test]
     fn dot_product_of_vectors() {
         let v1 = vector(1.0, 2.0, 3.0);
@@ -219,4 +291,24 @@ mod tuple_tests {
             vector(1.0000000000000002, 0.0000000000000002220446049250313, 0.0)
         )
     }
+
+    #[test]
+    fn lerp_between_points() {
+        let a = point(1.0, 2.0, 3.0);
+        let b = point(3.0, -2.0, 4.0);
+        assert_eq!(lerp_tuple(&a, &b, 0.0), a);
+        assert_eq!(lerp_tuple(&a, &b, 1.0), b);
+        assert_eq!(lerp_tuple(&a, &b, 0.5), point(2.0, 0.0, 3.5));
+    }
+
+    #[test]
+    fn distance_between_points() {
+        let a = point(1.0, 2.0, 3.0);
+        let b = point(4.0, 6.0, 3.0);
+        assert_eq!(tuple_distance(&a, &b), 5.0);
+        assert_eq!(
+            tuple_distance(&a, &b),
+            vector_magnitude(&subtract_tuple(&b, &a))
+        );
+    }
 }
@@ -0,0 +1,446 @@
+use crate::canvas::Canvas;
+use crate::color::Color;
+use crate::tuple::*;
+use std::f64::consts::PI;
+use std::fs;
+use std::io::Result;
+
+// Projection of a point in pattern space onto a 2D (u, v) texture space, both in [0, 1].
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UvMap {
+    Spherical,
+    Planar,
+    Cylindrical,
+}
+
+impl UvMap {
+    pub fn map(&self, point: &Tuple) -> (f64, f64) {
+        match self {
+            UvMap::Spherical => spherical_map(point),
+            UvMap::Planar => planar_map(point),
+            UvMap::Cylindrical => cylindrical_map(point),
+        }
+    }
+}
+
+// u follows the longitude and v the latitude of the point around the origin
+pub fn spherical_map(point: &Tuple) -> (f64, f64) {
+    // azimuthal angle in -π < theta <= π, increasing clockwise seen from above
+    let theta = point.0.atan2(point.2);
+    let radius = vector_magnitude(&vector(point.0, point.1, point.2));
+    // polar angle in 0 <= phi <= π
+    let phi = (point.1 / radius).acos();
+    let raw_u = theta / (2.0 * PI);
+    // fix the direction of u so that it increases counter-clockwise seen from above
+    let u = 1.0 - (raw_u + 0.5);
+    // make v be 0 at the south pole and 1 at the north pole
+    let v = 1.0 - phi / PI;
+    (u, v)
+}
+
+// repeats every unit along x and z
+pub fn planar_map(point: &Tuple) -> (f64, f64) {
+    (positive_fract(point.0), positive_fract(point.2))
+}
+
+// u wraps around the y axis and v repeats every unit along it
+pub fn cylindrical_map(point: &Tuple) -> (f64, f64) {
+    let theta = point.0.atan2(point.2);
+    let raw_u = theta / (2.0 * PI);
+    let u = 1.0 - (raw_u + 0.5);
+    (u, positive_fract(point.1))
+}
+
+// Faces of the axis aligned cube from -1 to 1, in the order expected by `Pattern::new_cube_map`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Face {
+    Left,
+    Front,
+    Right,
+    Back,
+    Up,
+    Down,
+}
+
+// the face is given by the coordinate with the largest magnitude
+pub fn face_from_point(point: &Tuple) -> Face {
+    let coord = point.0.abs().max(point.1.abs()).max(point.2.abs());
+    if coord == point.0 {
+        Face::Right
+    } else if coord == -point.0 {
+        Face::Left
+    } else if coord == point.1 {
+        Face::Up
+    } else if coord == -point.1 {
+        Face::Down
+    } else if coord == point.2 {
+        Face::Front
+    } else {
+        Face::Back
+    }
+}
+
+// u and v of each face as seen from outside of the cube, with v going up
+pub fn cube_uv(point: &Tuple) -> (Face, f64, f64) {
+    let (x, y, z) = (point.0, point.1, point.2);
+    let face = face_from_point(point);
+    let (u, v) = match face {
+        Face::Front => (x + 1.0, y + 1.0),
+        Face::Back => (1.0 - x, y + 1.0),
+        Face::Left => (z + 1.0, y + 1.0),
+        Face::Right => (1.0 - z, y + 1.0),
+        Face::Up => (x + 1.0, 1.0 - z),
+        Face::Down => (x + 1.0, z + 1.0),
+    };
+    (face, u.rem_euclid(2.0) / 2.0, v.rem_euclid(2.0) / 2.0)
+}
+
+fn positive_fract(x: f64) -> f64 {
+    x - x.floor()
+}
+
+// Patterns defined in (u, v) texture space.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UvPattern {
+    Checkers(UvCheckers),
+    Image(ImageTexture),
+    AlignCheck(AlignCheck),
+}
+
+impl UvPattern {
+    pub fn uv_pattern_at(&self, u: f64, v: f64) -> Color {
+        match self {
+            UvPattern::Checkers(checkers) => checkers.uv_checkers_at(u, v),
+            UvPattern::Image(image) => image.uv_pattern_at(u, v),
+            UvPattern::AlignCheck(align) => align.uv_align_check_at(u, v),
+        }
+    }
+}
+
+// How an image texture is sampled between pixel centers.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Filter {
+    #[default]
+    Nearest,
+    // weighted average of the four surrounding pixels
+    Bilinear,
+}
+
+// Texture sampled from an image, u goes left to right and v bottom to top.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImageTexture {
+    canvas: Canvas,
+    filter: Filter,
+}
+
+impl ImageTexture {
+    pub fn new(canvas: Canvas) -> ImageTexture {
+        ImageTexture {
+            canvas,
+            filter: Filter::default(),
+        }
+    }
+
+    pub fn set_filter(self, filter: Filter) -> ImageTexture {
+        ImageTexture { filter, ..self }
+    }
+
+    pub fn load_ppm(filename: &str) -> Result<ImageTexture> {
+        let content = fs::read_to_string(filename)?;
+        Canvas::from_ppm(&content).map(ImageTexture::new)
+    }
+
+    // v is flipped as the first row of the canvas is the top of the image,
+    // an empty image has no pixel to sample and is black
+    pub fn uv_pattern_at(&self, u: f64, v: f64) -> Color {
+        if self.canvas.width == 0 || self.canvas.height == 0 {
+            return Color::default();
+        }
+        let u = u.clamp(0.0, 1.0);
+        let v = 1.0 - v.clamp(0.0, 1.0);
+        // continuous pixel coordinates, the pixel centers sit on integers
+        let x = u * (self.canvas.width - 1) as f64;
+        let y = v * (self.canvas.height - 1) as f64;
+        match self.filter {
+            Filter::Nearest => self.pixel(x.round() as usize, y.round() as usize),
+            Filter::Bilinear => {
+                let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+                // the neighbors are clamped to the last row and column of the image
+                let x1 = (x0 + 1).min(self.canvas.width - 1);
+                let y1 = (y0 + 1).min(self.canvas.height - 1);
+                let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+                let lerp = |a: Color, b: Color, t: f64| {
+                    a.multiply_value(1.0 - t).add(&b.multiply_value(t))
+                };
+                let top = lerp(self.pixel(x0, y0), self.pixel(x1, y0), fx);
+                let bottom = lerp(self.pixel(x0, y1), self.pixel(x1, y1), fx);
+                lerp(top, bottom, fy)
+            }
+        }
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> Color {
+        self.canvas.color_at(x, y).unwrap_or_default()
+    }
+}
+
+// `width` squares along u and `height` squares along v
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UvCheckers {
+    pub width: f64,
+    pub height: f64,
+    pub a: Color,
+    pub b: Color,
+}
+
+impl UvCheckers {
+    pub fn new(width: f64, height: f64, a: Color, b: Color) -> UvCheckers {
+        UvCheckers {
+            width,
+            height,
+            a,
+            b,
+        }
+    }
+
+    pub fn uv_checkers_at(&self, u: f64, v: f64) -> Color {
+        let u2 = (u * self.width).floor();
+        let v2 = (v * self.height).floor();
+        if (u2 + v2) % 2.0 == 0.0 {
+            self.a
+        } else {
+            self.b
+        }
+    }
+}
+
+// single color with a different square in each corner, shows how the faces of a cube map line up
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AlignCheck {
+    pub main: Color,
+    pub upper_left: Color,
+    pub upper_right: Color,
+    pub bottom_left: Color,
+    pub bottom_right: Color,
+}
+
+impl AlignCheck {
+    pub fn new(
+        main: Color,
+        upper_left: Color,
+        upper_right: Color,
+        bottom_left: Color,
+        bottom_right: Color,
+    ) -> AlignCheck {
+        AlignCheck {
+            main,
+            upper_left,
+            upper_right,
+            bottom_left,
+            bottom_right,
+        }
+    }
+
+    pub fn uv_align_check_at(&self, u: f64, v: f64) -> Color {
+        if v > 0.8 {
+            if u < 0.2 {
+                return self.upper_left;
+            } else if u > 0.8 {
+                return self.upper_right;
+            }
+        } else if v < 0.2 {
+            if u < 0.2 {
+                return self.bottom_left;
+            } else if u > 0.8 {
+                return self.bottom_right;
+            }
+        }
+        self.main
+    }
+}
+
+#[cfg(test)]
+mod uv_tests {
+    use crate::canvas::Canvas;
+    use crate::color::{Color, BLACK, WHITE};
+    use crate::tuple::*;
+    use crate::uv::*;
+    use std::f64::consts::FRAC_1_SQRT_2;
+
+    #[test]
+    fn checker_pattern_in_2d() {
+        let checkers = UvCheckers::new(2.0, 2.0, BLACK, WHITE);
+        assert_eq!(checkers.uv_checkers_at(0.0, 0.0), BLACK);
+        assert_eq!(checkers.uv_checkers_at(0.5, 0.0), WHITE);
+        assert_eq!(checkers.uv_checkers_at(0.0, 0.5), WHITE);
+        assert_eq!(checkers.uv_checkers_at(0.5, 0.5), BLACK);
+        assert_eq!(checkers.uv_checkers_at(1.0, 1.0), BLACK);
+    }
+
+    #[test]
+    fn image_texture_samples_nearest_pixel() {
+        let ppm = "P3
+10 10
+10
+0 0 0  1 1 1  2 2 2  3 3 3  4 4 4  5 5 5  6 6 6  7 7 7  8 8 8  9 9 9
+1 1 1  2 2 2  3 3 3  4 4 4  5 5 5  6 6 6  7 7 7  8 8 8  9 9 9  0 0 0
+2 2 2  3 3 3  4 4 4  5 5 5  6 6 6  7 7 7  8 8 8  9 9 9  0 0 0  1 1 1
+3 3 3  4 4 4  5 5 5  6 6 6  7 7 7  8 8 8  9 9 9  0 0 0  1 1 1  2 2 2
+4 4 4  5 5 5  6 6 6  7 7 7  8 8 8  9 9 9  0 0 0  1 1 1  2 2 2  3 3 3
+5 5 5  6 6 6  7 7 7  8 8 8  9 9 9  0 0 0  1 1 1  2 2 2  3 3 3  4 4 4
+6 6 6  7 7 7  8 8 8  9 9 9  0 0 0  1 1 1  2 2 2  3 3 3  4 4 4  5 5 5
+7 7 7  8 8 8  9 9 9  0 0 0  1 1 1  2 2 2  3 3 3  4 4 4  5 5 5  6 6 6
+8 8 8  9 9 9  0 0 0  1 1 1  2 2 2  3 3 3  4 4 4  5 5 5  6 6 6  7 7 7
+9 9 9  0 0 0  1 1 1  2 2 2  3 3 3  4 4 4  5 5 5  6 6 6  7 7 7  8 8 8";
+        let texture = ImageTexture::new(Canvas::from_ppm(ppm).unwrap());
+        let cases = vec![
+            (0.0, 0.0, 0.9),
+            (0.3, 0.0, 0.2),
+            (0.6, 0.3, 0.1),
+            (1.0, 1.0, 0.9),
+        ];
+        for (u, v, expected) in cases {
+            assert_eq!(
+                texture.uv_pattern_at(u, v),
+                Color::make(expected, expected, expected)
+            );
+        }
+    }
+
+    #[test]
+    fn empty_image_texture_is_black() {
+        for filter in [Filter::Nearest, Filter::Bilinear] {
+            let canvas = Canvas::from_ppm("P3\n0 0\n255\n").unwrap();
+            let texture = ImageTexture::new(canvas).set_filter(filter);
+            assert_eq!(texture.uv_pattern_at(0.5, 0.5), Color::default());
+        }
+    }
+
+    #[test]
+    fn bilinear_filter_blends_neighbor_pixels() {
+        let canvas = || {
+            let mut canvas = Canvas::make(3, 2);
+            canvas.write(1, 1, WHITE);
+            canvas.write(2, 1, WHITE);
+            canvas.write(2, 0, Color::make(0.5, 0.5, 0.5));
+            canvas
+        };
+        let nearest = ImageTexture::new(canvas());
+        let bilinear = ImageTexture::new(canvas()).set_filter(Filter::Bilinear);
+        // halfway between the black and white pixels of the bottom row
+        assert_eq!(nearest.uv_pattern_at(0.25, 0.0), WHITE);
+        assert_eq!(
+            bilinear.uv_pattern_at(0.25, 0.0),
+            Color::make(0.5, 0.5, 0.5)
+        );
+        // pixel centers are unchanged
+        assert_eq!(bilinear.uv_pattern_at(0.5, 0.0), WHITE);
+        // blends the four pixels in the middle of the image
+        let c = bilinear.uv_pattern_at(0.75, 0.5);
+        let expected = (0.0 + 0.5 + 1.0 + 1.0) / 4.0;
+        assert!((c.red - expected).abs() < 0.01, "{:?}", c);
+        // the edges are clamped
+        assert_eq!(
+            bilinear.uv_pattern_at(1.0, 1.0),
+            nearest.uv_pattern_at(1.0, 1.0)
+        );
+        assert_eq!(bilinear.uv_pattern_at(0.0, 0.0), BLACK);
+    }
+
+    #[test]
+    fn using_spherical_mapping_on_3d_point() {
+        let cases = vec![
+            (point(0.0, 0.0, -1.0), (0.0, 0.5)),
+            (point(1.0, 0.0, 0.0), (0.25, 0.5)),
+            (point(0.0, 0.0, 1.0), (0.5, 0.5)),
+            (point(-1.0, 0.0, 0.0), (0.75, 0.5)),
+            (point(0.0, 1.0, 0.0), (0.5, 1.0)),
+            (point(0.0, -1.0, 0.0), (0.5, 0.0)),
+        ];
+        for (p, expected) in cases {
+            assert_eq!(spherical_map(&p), expected);
+        }
+        let (u, v) = spherical_map(&point(FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.0));
+        assert_eq!(u, 0.25);
+        assert!((v - 0.75).abs() < 1e-12);
+    }
+
+    #[test]
+    fn using_planar_mapping_on_3d_point() {
+        let cases = vec![
+            (point(0.25, 0.0, 0.5), (0.25, 0.5)),
+            (point(0.25, 0.0, -0.25), (0.25, 0.75)),
+            (point(0.25, 0.5, -0.25), (0.25, 0.75)),
+            (point(1.25, 0.0, 0.5), (0.25, 0.5)),
+            (point(0.25, 0.0, -1.75), (0.25, 0.25)),
+            (point(1.0, 0.0, -1.0), (0.0, 0.0)),
+            (point(0.0, 0.0, 0.0), (0.0, 0.0)),
+        ];
+        for (p, expected) in cases {
+            assert_eq!(planar_map(&p), expected);
+        }
+    }
+
+    #[test]
+    fn using_cylindrical_mapping_on_3d_point() {
+        let cases = vec![
+            (point(0.0, 0.0, -1.0), (0.0, 0.0)),
+            (point(0.0, 0.5, -1.0), (0.0, 0.5)),
+            (point(0.0, 1.0, -1.0), (0.0, 0.0)),
+            (point(0.0, 0.5, 1.0), (0.5, 0.5)),
+            (point(-1.0, 0.25, 0.0), (0.75, 0.25)),
+        ];
+        for (p, expected) in cases {
+            assert_eq!(cylindrical_map(&p), expected);
+        }
+    }
+
+    #[test]
+    fn layout_of_align_check_pattern() {
+        let red = Color::make(1.0, 0.0, 0.0);
+        let yellow = Color::make(1.0, 1.0, 0.0);
+        let green = Color::make(0.0, 1.0, 0.0);
+        let cyan = Color::make(0.0, 1.0, 1.0);
+        let align = AlignCheck::new(WHITE, red, yellow, green, cyan);
+        assert_eq!(align.uv_align_check_at(0.5, 0.5), WHITE);
+        assert_eq!(align.uv_align_check_at(0.1, 0.9), red);
+        assert_eq!(align.uv_align_check_at(0.9, 0.9), yellow);
+        assert_eq!(align.uv_align_check_at(0.1, 0.1), green);
+        assert_eq!(align.uv_align_check_at(0.9, 0.1), cyan);
+    }
+
+    #[test]
+    fn identifying_the_face_of_a_cube_from_a_point() {
+        let cases = vec![
+            (point(-1.0, 0.5, -0.25), Face::Left),
+            (point(1.1, -0.75, 0.8), Face::Right),
+            (point(0.1, 0.6, 0.9), Face::Front),
+            (point(-0.7, 0.0, -2.0), Face::Back),
+            (point(0.5, 1.0, 0.9), Face::Up),
+            (point(-0.2, -1.3, 1.1), Face::Down),
+        ];
+        for (p, expected) in cases {
+            assert_eq!(face_from_point(&p), expected);
+        }
+    }
+
+    #[test]
+    fn uv_mapping_the_front_and_left_faces_of_a_cube() {
+        let cases = vec![
+            (point(-0.5, 0.5, 1.0), (Face::Front, 0.25, 0.75)),
+            (point(0.5, -0.5, 1.0), (Face::Front, 0.75, 0.25)),
+            (point(-1.0, 0.5, -0.5), (Face::Left, 0.25, 0.75)),
+            (point(-1.0, -0.5, 0.5), (Face::Left, 0.75, 0.25)),
+        ];
+        for (p, expected) in cases {
+            assert_eq!(cube_uv(&p), expected);
+        }
+    }
+}
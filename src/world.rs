@@ -1,143 +1,674 @@
+use crate::bounds::BoundingBox;
 use crate::color::*;
+use crate::epsilon::EPSILON;
+use crate::integrator::MAX_DEPTH;
 use crate::intersection::{Intersection, PreparedComputations};
 use crate::light::Light;
 use crate::material::Material;
 use crate::matrix::Matrix;
 use crate::ray::Ray;
-use crate::shape::Shape;
+use crate::sampler::{RandomSampler, Sampler, MAX_REJECTION_ATTEMPTS};
+use crate::shape::{Shape, ShapeEnum};
 use crate::sphere::Sphere;
+use crate::stats::RenderCounters;
 use crate::tuple::*;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::io::{Error, ErrorKind, Result};
 
-pub struct World {
+// exponential distance fog, the surface color fades toward `color` as the hit gets further away
+#[derive(Debug, Clone, Copy)]
+pub struct Fog {
+    pub color: Color,
+    pub density: f64,
+}
+
+impl Fog {
+    // share of the fog color in a hit seen from `distance`
+    pub fn factor(&self, distance: f64) -> f64 {
+        1.0 - (-self.density * distance).exp()
+    }
+}
+
+// color seen by a ray missing every object
+pub type Sky = dyn Fn(&Ray) -> Color + Send + Sync;
+
+// storage of the top level objects of a world, `Box<dyn Shape>` by default,
+// a `World<ShapeEnum>` intersects its objects with a match instead of a vtable call
+pub trait WorldObject: Send + Sync {
+    fn shape(&self) -> &dyn Shape;
+
+    fn intersections(&self, ray: &Ray) -> Vec<Intersection<'_>>;
+}
+
+impl WorldObject for Box<dyn Shape> {
+    fn shape(&self) -> &dyn Shape {
+        self.as_ref()
+    }
+
+    fn intersections(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        self.intersect(ray)
+    }
+}
+
+impl WorldObject for ShapeEnum {
+    fn shape(&self) -> &dyn Shape {
+        self
+    }
+
+    fn intersections(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        self.intersect(ray)
+    }
+}
+
+pub struct World<O = Box<dyn Shape>> {
     pub lights: Vec<Light>,
-    pub objects: Vec<Box<dyn Shape>>,
+    pub objects: Vec<O>,
+    // path of child positions leading to each object id, first occurrence wins
+    index: HashMap<usize, Vec<usize>>,
+    // (samples, max_distance) of the ambient occlusion pass, disabled by default
+    occlusion: Option<(usize, f64)>,
+    // transparent objects let part of the light through instead of casting full shadows
+    transparent_shadows: bool,
+    // blends the colors toward the fog color with the distance, disabled by default
+    fog: Option<Fog>,
+    // color of the rays missing every object, `sky` takes precedence when set
+    background: Color,
+    sky: Option<Box<Sky>>,
+    // counts the work done by the renders, see `Camera::render_with_stats`
+    pub counters: RenderCounters,
+}
+
+impl Default for World {
+    fn default() -> World {
+        World::new(
+            vec![Light::point_light(
+                point(-10.0, 10.0, -10.0),
+                Color::make(1.0, 1.0, 1.0),
+            )],
+            vec![
+                Box::new(Sphere::new(1).set_radius(1.0).set_material(Material::new(
+                    Color::make(0.8, 1.0, 0.6),
+                    0.7,
+                    0.2,
+                ))),
+                Box::new(Sphere::new(2).set_transform(Matrix::scaling(0.5, 0.5, 0.5))),
+            ],
+        )
+    }
 }
 
 impl World {
+    pub fn new(lights: Vec<Light>, objects: Vec<Box<dyn Shape>>) -> World {
+        World::from_objects(lights, objects)
+    }
+
     pub fn empty() -> World {
+        World::new(vec![], vec![])
+    }
+
+    // `builder` receives the next unused id and creates the shape with it, the id is returned,
+    // fails if the shape ignores the assigned id or if one of its children reuses an id of the world
+    pub fn add_shape<S: Shape + 'static>(
+        &mut self,
+        builder: impl FnOnce(usize) -> S,
+    ) -> Result<usize> {
+        let id = self.next_id();
+        let shape = builder(id);
+        if shape.id() != id {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("the shape must use the assigned id {}", id),
+            ));
+        }
+        self.insert_object(Box::new(shape))?;
+        Ok(id)
+    }
+}
+
+impl<O: WorldObject> World<O> {
+    // same as `World::new` for any storage of the objects, e.g. `Vec<ShapeEnum>`
+    pub fn from_objects(lights: Vec<Light>, objects: Vec<O>) -> World<O> {
+        let mut index = HashMap::new();
+        Self::index_objects(&objects, &mut vec![], &mut index);
         World {
-            lights: vec![],
-            objects: vec![],
+            lights,
+            objects,
+            index,
+            occlusion: None,
+            transparent_shadows: false,
+            fog: None,
+            background: BLACK,
+            sky: None,
+            counters: RenderCounters::default(),
+        }
+    }
+
+    // fails if the object or one of its descendants reuses the id of an object of the world
+    pub fn add_object(mut self, object: O) -> Result<World<O>> {
+        self.insert_object(object)?;
+        Ok(self)
+    }
+
+    // same as calling `add_object` for each object in order
+    pub fn add_objects(mut self, objects: Vec<O>) -> Result<World<O>> {
+        self.objects.reserve(objects.len());
+        for object in objects {
+            self.insert_object(object)?;
+        }
+        Ok(self)
+    }
+
+    // only the top level objects can be removed, along with their children,
+    // the removed object is returned and a missing id leaves the world unchanged
+    pub fn remove_object(&mut self, id: usize) -> Option<O> {
+        let position = self.objects.iter().position(|o| o.shape().id() == id)?;
+        let removed = self.objects.remove(position);
+        // the positions of the following objects changed
+        self.index.clear();
+        Self::index_objects(&self.objects, &mut vec![], &mut self.index);
+        Some(removed)
+    }
+
+    pub fn clear_objects(&mut self) {
+        self.objects.clear();
+        self.index.clear();
+    }
+
+    fn next_id(&self) -> usize {
+        self.index.keys().max().map_or(1, |max| max + 1)
+    }
+
+    fn insert_object(&mut self, object: O) -> Result<()> {
+        if let Some(id) = Self::ids(object.shape())
+            .into_iter()
+            .find(|id| self.index.contains_key(id))
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("an object with the id {} is already in the world", id),
+            ));
+        }
+        let mut path = vec![self.objects.len()];
+        Self::index_object(object.shape(), &mut path, &mut self.index);
+        self.objects.push(object);
+        Ok(())
+    }
+
+    fn ids(object: &dyn Shape) -> Vec<usize> {
+        let mut ids = vec![object.id()];
+        ids.extend(object.children().iter().flat_map(|c| Self::ids(c.as_ref())));
+        ids
+    }
+
+    // records the objects in depth first order, matching `find_object`
+    fn index_objects<T: WorldObject>(
+        objects: &[T],
+        path: &mut Vec<usize>,
+        index: &mut HashMap<usize, Vec<usize>>,
+    ) {
+        for (position, o) in objects.iter().enumerate() {
+            path.push(position);
+            Self::index_object(o.shape(), path, index);
+            path.pop();
         }
     }
 
-    pub fn add_object(self, object: Box<dyn Shape>) -> World {
-        let mut objects: Vec<Box<dyn Shape>> = Vec::new();
-        self.objects.into_iter().for_each(|o| objects.push(o));
-        objects.push(object);
-        World { objects, ..self }
+    fn index_object(
+        object: &dyn Shape,
+        path: &mut Vec<usize>,
+        index: &mut HashMap<usize, Vec<usize>>,
+    ) {
+        index.entry(object.id()).or_insert_with(|| path.clone());
+        Self::index_objects(object.children(), path, index);
     }
 
-    pub fn set_light(self, light: Light) -> World {
+    pub fn set_light(self, light: Light) -> World<O> {
         World {
             lights: vec![light],
             ..self
         }
     }
 
-    pub fn set_lights(self, lights: Vec<Light>) -> World {
+    pub fn set_lights(self, lights: Vec<Light>) -> World<O> {
         World { lights, ..self }
     }
 
-    pub fn default() -> World {
+    // shadows get lighter as the transparency of the blockers increases
+    pub fn set_transparent_shadows(self, transparent_shadows: bool) -> World<O> {
         World {
-            lights: vec![Light::point_light(
-                point(-10.0, 10.0, -10.0),
-                Color::make(1.0, 1.0, 1.0),
-            )],
-            objects: vec![
-                Box::new(Sphere::new(1).set_radius(1.0).set_material(Material::new(
-                    Color::make(0.8, 1.0, 0.6),
-                    0.7,
-                    0.2,
-                ))),
-                Box::new(
-                    Sphere::new(2)
-                        .set_radius(0.5)
-                        .set_transform(Matrix::scaling(0.5, 0.5, 0.5)),
-                ),
-            ],
+            transparent_shadows,
+            ..self
+        }
+    }
+
+    // darkens the ambient term of the hits surrounded by geometry closer than `max_distance`
+    pub fn set_ambient_occlusion(self, samples: usize, max_distance: f64) -> World<O> {
+        World {
+            occlusion: Some((samples, max_distance)),
+            ..self
+        }
+    }
+
+    pub fn set_fog(self, color: Color, density: f64) -> World<O> {
+        World {
+            fog: Some(Fog { color, density }),
+            ..self
+        }
+    }
+
+    pub fn set_background(self, background: Color) -> World<O> {
+        World { background, ..self }
+    }
+
+    // computes the color of the missing rays from their direction, e.g. a gradient sky
+    pub fn set_sky(self, sky: impl Fn(&Ray) -> Color + Send + Sync + 'static) -> World<O> {
+        World {
+            sky: Some(Box::new(sky)),
+            ..self
+        }
+    }
+
+    pub fn background_at(&self, ray: &Ray) -> Color {
+        match &self.sky {
+            Some(sky) => sky(ray),
+            None => self.background,
         }
     }
 
-    pub fn intersect_with_ray(&self, ray: &Ray) -> Vec<Intersection> {
-        let mut intersections = Vec::new();
-        self.objects.iter().for_each(|o| {
-            o.intersect(ray)
-                .into_iter()
-                .filter(|i| i.distance > 0.0)
-                .for_each(|i| intersections.push(i))
+    // only the intersections in front of the ray origin
+    pub fn intersect_with_ray(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let mut intersections = self.intersect_all(ray);
+        intersections.retain(|i| i.distance > 0.0);
+        intersections
+    }
+
+    // every intersection sorted by distance, including the ones behind the ray origin
+    // which are required to track the refractive containers
+    pub fn intersect_all(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let mut intersections: Vec<Intersection> = self
+            .objects
+            .iter()
+            .flat_map(|o| {
+                o.intersections(ray)
+                    .into_iter()
+                    .map(|i| i.with_object(o.shape()))
+            })
+            .collect();
+        self.counters.add_intersections(intersections.len());
+        intersections.sort_by(|a, b| {
+            a.distance
+                .partial_cmp(&b.distance)
+                .unwrap_or(Ordering::Equal)
         });
-        intersections.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
         intersections
     }
 
-    pub fn shade_hit(&self, comps: &PreparedComputations) -> Color {
+    // intersections in front of the ray origin closer than `max_distance` sorted by distance,
+    // the hits beyond are dropped per object before any sorting happens
+    pub fn intersect_with_ray_bounded(
+        &self,
+        ray: &Ray,
+        max_distance: f64,
+    ) -> Vec<Intersection<'_>> {
+        let mut intersections: Vec<Intersection> = Vec::new();
+        for o in &self.objects {
+            let xs = o.intersections(ray);
+            self.counters.add_intersections(xs.len());
+            intersections.extend(
+                xs.into_iter()
+                    .filter(|i| i.distance > 0.0 && i.distance < max_distance)
+                    .map(|i| i.with_object(o.shape())),
+            );
+        }
+        intersections.sort_by(|a, b| {
+            a.distance
+                .partial_cmp(&b.distance)
+                .unwrap_or(Ordering::Equal)
+        });
+        intersections
+    }
+
+    // world space box around every object, infinite shapes such as unbounded planes are left out
+    // so that the result can be used to frame the scene
+    pub fn bounds(&self) -> BoundingBox {
+        self.objects
+            .iter()
+            .map(|o| o.shape().parent_space_bounds())
+            .filter(|b| b.is_finite())
+            .fold(BoundingBox::empty(), |acc, b| acc.merge(&b))
+    }
+
+    // searches the objects nested in groups as well
+    pub fn object(&self, id: usize) -> Option<&dyn Shape> {
+        self.indexed_object(id)
+            .or_else(|| Self::find_object(&self.objects, id))
+    }
+
+    // only the top level objects can be modified, groups propagate their transform to their children
+    pub fn object_mut(&mut self, id: usize) -> Option<&mut O> {
+        self.objects.iter_mut().find(|o| o.shape().id() == id)
+    }
+
+    // the index can be stale if `objects` was modified directly, so the result is checked
+    fn indexed_object(&self, id: usize) -> Option<&dyn Shape> {
+        let (first, rest) = self.index.get(&id)?.split_first()?;
+        let mut shape = self.objects.get(*first)?.shape();
+        for position in rest {
+            shape = shape.children().get(*position)?.as_ref();
+        }
+        if shape.id() == id {
+            Some(shape)
+        } else {
+            None
+        }
+    }
+
+    fn find_object<T: WorldObject>(objects: &[T], id: usize) -> Option<&dyn Shape> {
+        objects.iter().find_map(|o| {
+            if o.shape().id() == id {
+                Some(o.shape())
+            } else {
+                Self::find_object(o.shape().children(), id)
+            }
+        })
+    }
+
+    // `remaining` limits the recursion depth of reflections and refractions
+    pub fn shade_hit(&self, comps: &PreparedComputations, remaining: usize) -> Color {
         if self.lights.is_empty() {
             Color::default()
         } else {
-            let shape = self
-                .objects
-                .iter()
-                .find(|&o| o.id() == comps.object_id)
-                .unwrap();
+            let shape = comps.object;
+            let occlusion = self.occlusion.map(|(samples, max_distance)| {
+                1.0 - self.ambient_occlusion(comps, samples, max_distance)
+            });
             // adding color for each light
-            self.lights
+            let surface = self
+                .lights
                 .iter()
                 .map(|l| {
-                    l.lighting(
-                        shape.material(),
-                        shape.transform(),
+                    let lighting = |light_intensity| {
+                        l.lighting(
+                            shape.material(),
+                            shape.transform(),
+                            &comps.over_point,
+                            &comps.eyev,
+                            &comps.normalv,
+                            light_intensity,
+                        )
+                    };
+                    // without any light reaching the point only the ambient term is left
+                    let ambient =
+                        || l.ambient(shape.material(), shape.transform(), &comps.over_point);
+                    let shadow = self.shadow_fraction_at(
                         &comps.over_point,
-                        &comps.eyev,
-                        &comps.normalv,
-                        self.is_shadowed(&comps.over_point, l),
-                    )
+                        l,
+                        comps.time,
+                        &mut RandomSampler,
+                    );
+                    let color = if shadow == 1.0 {
+                        ambient()
+                    } else {
+                        lighting(1.0 - shadow)
+                    };
+                    match occlusion {
+                        Some(occluded) => color.subtract(&ambient().multiply_value(occluded)),
+                        None => color,
+                    }
                 })
-                .fold(Color::default(), |acc, c| acc.add(&c))
+                .fold(Color::default(), |acc, c| acc.add(&c));
+            let reflected = self.reflected_color(comps, remaining);
+            let refracted = self.refracted_color(comps, remaining);
+            let material = shape.material();
+            if material.reflective > 0.0 && material.transparency > 0.0 {
+                // combine reflection and refraction using the Fresnel effect
+                let reflectance = comps.schlick();
+                surface
+                    .add(&reflected.multiply_value(reflectance))
+                    .add(&refracted.multiply_value(1.0 - reflectance))
+            } else {
+                surface.add(&reflected).add(&refracted)
+            }
         }
     }
 
+    // follows up to MAX_DEPTH reflections and refractions
     pub fn color_at(&self, ray: &Ray) -> Color {
-        let intersections = self.intersect_with_ray(ray);
-        if intersections.is_empty() {
-            Color::default()
+        self.color_at_with_depth(ray, MAX_DEPTH)
+    }
+
+    // the reflected and refracted colors are black once `remaining` reaches 0
+    pub fn color_at_with_depth(&self, ray: &Ray, remaining: usize) -> Color {
+        self.color_and_coverage_at(ray, remaining).0
+    }
+
+    // same as `color_at_with_depth`, also telling whether the ray hit an object
+    pub fn color_and_coverage_at(&self, ray: &Ray, remaining: usize) -> (Color, bool) {
+        let intersections = self.intersect_all(ray);
+        let color = match intersections.iter().find(|i| i.distance > 0.0) {
+            // an infinitely distant background disappears behind any fog
+            None => match self.fog {
+                Some(fog) if fog.density > 0.0 => fog.color,
+                _ => self.background_at(ray),
+            },
+            Some(hit) => {
+                let comps = Intersection::prepare_computations(hit, ray, self, &intersections);
+                let color = self.shade_hit(&comps, remaining);
+                match self.fog {
+                    None => color,
+                    Some(fog) => {
+                        // the ray direction is not always normalized
+                        let distance = hit.distance * vector_magnitude(&ray.direction);
+                        let factor = fog.factor(distance);
+                        color
+                            .multiply_value(1.0 - factor)
+                            .add(&fog.color.multiply_value(factor))
+                    }
+                }
+            }
+        };
+        (color, intersections.iter().any(|i| i.distance > 0.0))
+    }
+
+    pub fn reflected_color(&self, comps: &PreparedComputations, remaining: usize) -> Color {
+        let reflective = comps.object.material().reflective;
+        match comps.reflectv {
+            // stop bouncing between mirrors when the recursion budget is exhausted
+            Some(_) if remaining > 0 && reflective > 0.0 => {
+                // the incoming ray is rebuilt from the eye vector and bounces off the over point
+                let eye_ray = Ray::new(comps.point, negate_tuple(&comps.eyev)).set_time(comps.time);
+                let reflect_ray = eye_ray.reflect(&comps.over_point, &comps.normalv);
+                let color = self.color_at_with_depth(&reflect_ray, remaining - 1);
+                color.multiply_value(reflective)
+            }
+            _ => Color::default(),
+        }
+    }
+
+    pub fn refracted_color(&self, comps: &PreparedComputations, remaining: usize) -> Color {
+        let transparency = comps.object.material().transparency;
+        if remaining == 0 || transparency == 0.0 {
+            return Color::default();
+        }
+        // Snell's law: sin(theta_i) * n1 = sin(theta_t) * n2
+        let n_ratio = comps.n1 / comps.n2;
+        let cos_i = vector_dot_product(&comps.eyev, &comps.normalv);
+        let sin2_t = n_ratio.powi(2) * (1.0 - cos_i.powi(2));
+        if sin2_t > 1.0 {
+            // total internal reflection
+            return Color::default();
+        }
+        let cos_t = (1.0 - sin2_t).sqrt();
+        let direction = subtract_tuple(
+            &scale_tuple(&comps.normalv, n_ratio * cos_i - cos_t),
+            &scale_tuple(&comps.eyev, n_ratio),
+        );
+        let refract_ray = Ray::new(comps.under_point, direction).set_time(comps.time);
+        self.color_at_with_depth(&refract_ray, remaining - 1)
+            .multiply_value(transparency)
+    }
+
+    // fraction of `samples` rays leaving the hit in the hemisphere around its normal
+    // without meeting any geometry closer than `max_distance`, 1.0 for a fully open point
+    pub fn ambient_occlusion(
+        &self,
+        comps: &PreparedComputations,
+        samples: usize,
+        max_distance: f64,
+    ) -> f64 {
+        if samples == 0 {
+            return 1.0;
+        }
+        let blocked = (0..samples)
+            .filter(|_| {
+                let direction = random_unit_vector(&mut RandomSampler, &comps.normalv);
+                // flip the directions pointing below the surface into the hemisphere
+                let direction = if vector_dot_product(&direction, &comps.normalv) < 0.0 {
+                    negate_tuple(&direction)
+                } else {
+                    direction
+                };
+                self.is_blocked(&comps.over_point, &direction, max_distance, comps.time)
+            })
+            .count();
+        1.0 - blocked as f64 / samples as f64
+    }
+
+    // fraction of the light blocked before reaching the point, hard shadows are either 0.0 or 1.0
+    // unless transparent shadows are enabled, lights with a radius average several jittered shadow rays
+    pub fn shadow_fraction(&self, point: &Tuple, light: &Light) -> f64 {
+        self.shadow_fraction_at(point, light, 0.0, &mut RandomSampler)
+    }
+
+    // fraction of the light reaching the point, `sampler` drives the jittering of soft shadows
+    pub fn intensity_at(&self, point: &Tuple, light: &Light, sampler: &mut dyn Sampler) -> f64 {
+        1.0 - self.shadow_fraction_at(point, light, 0.0, sampler)
+    }
+
+    // shadow rays are cast at the time of the shaded ray to see the blockers where they were
+    fn shadow_fraction_at(
+        &self,
+        point: &Tuple,
+        light: &Light,
+        time: f64,
+        sampler: &mut dyn Sampler,
+    ) -> f64 {
+        if light.casts_soft_shadows() {
+            let transmitted: f64 = (0..light.shadow_samples)
+                .map(|_| {
+                    let to_light = subtract_tuple(&light.jittered_position(sampler), point);
+                    self.transmittance(
+                        point,
+                        &vector_normalize(&to_light),
+                        vector_magnitude(&to_light),
+                        time,
+                    )
+                })
+                .sum();
+            1.0 - transmitted / light.shadow_samples as f64
         } else {
-            let comps = Intersection::prepare_computations(&intersections[0], ray, self);
-            self.shade_hit(&comps)
+            1.0 - self.transmittance(
+                point,
+                &light.direction_from(point),
+                light.distance_from(point),
+                time,
+            )
         }
     }
 
+    // fraction of the light going through the objects between the point and the light,
+    // each surface crossed lets through its transparency when transparent shadows are enabled
+    fn transmittance(&self, point: &Tuple, direction: &Tuple, distance: f64, time: f64) -> f64 {
+        if !self.transparent_shadows {
+            return if self.is_blocked(point, direction, distance, time) {
+                0.0
+            } else {
+                1.0
+            };
+        }
+        self.counters.add_shadow_ray();
+        let r = Ray::new(*point, *direction).set_time(time);
+        let mut transmitted = 1.0;
+        for i in self.intersect_with_ray(&r) {
+            if i.distance >= distance || transmitted == 0.0 {
+                break;
+            }
+            let material = self.hit_object(&i).unwrap().material();
+            if material.casts_shadow {
+                transmitted *= material.transparency;
+            }
+        }
+        transmitted
+    }
+
     pub fn is_shadowed(&self, point: &Tuple, light: &Light) -> bool {
-        // measure distance from the point to the light
-        let v = subtract_tuple(&light.position, point);
+        // measure distance from the point to the light, infinite for directional lights
+        let distance = light.distance_from(point);
+        let direction = light.direction_from(point);
+        self.is_blocked(point, &direction, distance, 0.0)
+    }
+
+    // whether an object lies between the point and the given position of a light
+    pub fn is_shadowed_from(&self, point: &Tuple, light_position: &Tuple) -> bool {
+        let v = subtract_tuple(light_position, point);
         let distance = vector_magnitude(&v);
-        let direction = vector_normalize(&v);
+        self.is_blocked(point, &vector_normalize(&v), distance, 0.0)
+    }
 
+    fn is_blocked(&self, point: &Tuple, direction: &Tuple, distance: f64, time: f64) -> bool {
         // create a ray from point toward the light
-        let r = Ray::new(*point, direction);
+        let r = Ray::new(*point, *direction).set_time(time);
+        self.counters.add_shadow_ray();
 
-        // intersect the world with that ray
-        let intersections = self.intersect_with_ray(&r);
+        // the point is in the shadow if a hit lies between the point and the light source,
+        // ignoring the objects which do not cast shadows
+        self.intersect_with_ray_bounded(&r, distance)
+            .iter()
+            .any(|i| self.hit_object(i).is_none_or(|o| o.material().casts_shadow))
+    }
 
-        // the point is in the shadow if the hit lies between the point and the light source
-        let hit = Intersection::hit(intersections);
-        matches!(hit, Some((_, d)) if d < distance)
+    // the shape carried by the intersection, looked up by id otherwise
+    fn hit_object<'a>(&'a self, i: &Intersection<'a>) -> Option<&'a dyn Shape> {
+        i.object.or_else(|| self.object(i.object_id))
     }
 }
 
+// uniformly distributed on the unit sphere, rejection sampling within the unit ball,
+// `fallback` is returned when the sampler keeps missing the ball
+fn random_unit_vector(sampler: &mut dyn Sampler, fallback: &Tuple) -> Tuple {
+    for _ in 0..MAX_REJECTION_ATTEMPTS {
+        let v = vector(
+            sampler.next_f64() * 2.0 - 1.0,
+            sampler.next_f64() * 2.0 - 1.0,
+            sampler.next_f64() * 2.0 - 1.0,
+        );
+        let magnitude = vector_magnitude(&v);
+        if magnitude > EPSILON && magnitude <= 1.0 {
+            return scale_tuple(&v, 1.0 / magnitude);
+        }
+    }
+    *fallback
+}
+
 #[cfg(test)]
 mod world_tests {
-    use super::World;
+    use super::{random_unit_vector, World};
     use crate::color::*;
+    use crate::cube::Cube;
+    use crate::group::Group;
+    use crate::integrator::MAX_DEPTH;
     use crate::intersection::Intersection;
     use crate::light::Light;
     use crate::material::Material;
     use crate::matrix::Matrix;
+    use crate::plane::Plane;
     use crate::ray::Ray;
+    use crate::sampler::SequenceSampler;
     use crate::shape::Shape;
     use crate::sphere::Sphere;
     use crate::tuple::*;
+    use std::io::ErrorKind;
 
     #[test]
     fn creating_empty_world() {
@@ -157,9 +688,7 @@ mod world_tests {
             0.7,
             0.2,
         ));
-        let s2 = Sphere::new(2)
-            .set_radius(0.5)
-            .set_transform(Matrix::scaling(0.5, 0.5, 0.5));
+        let s2 = Sphere::new(2).set_transform(Matrix::scaling(0.5, 0.5, 0.5));
         assert_eq!(world.objects[0].id(), s1.id());
         assert_eq!(world.objects[1].id(), s2.id());
     }
@@ -171,18 +700,108 @@ mod world_tests {
         let intersections = w.intersect_with_ray(&r);
         assert_eq!(intersections.len(), 4);
         assert_eq!(intersections[0].distance, 4.0);
-        assert_eq!(intersections[1].distance, 4.646446609406726);
-        assert_eq!(intersections[2].distance, 5.353553390593274);
+        assert_eq!(intersections[1].distance, 4.5);
+        assert_eq!(intersections[2].distance, 5.5);
         assert_eq!(intersections[3].distance, 6.0);
     }
 
+    #[test]
+    fn intersect_all_keeps_hits_behind_the_ray_origin() {
+        let w = World::empty().add_object(Box::new(Sphere::new(1))).unwrap();
+        let r = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0));
+        let intersections = w.intersect_all(&r);
+        assert_eq!(intersections.len(), 2);
+        assert_eq!(intersections[0].distance, -1.0);
+        assert_eq!(intersections[1].distance, 1.0);
+        let intersections = w.intersect_with_ray(&r);
+        assert_eq!(intersections.len(), 1);
+        assert_eq!(intersections[0].distance, 1.0);
+    }
+
+    #[test]
+    fn removing_objects() {
+        let mut w = World::empty()
+            .add_objects(vec![
+                Box::new(Sphere::new(1)),
+                Box::new(Cube::new(2)),
+                Box::new(Plane::new(3)),
+            ])
+            .unwrap();
+        let removed = w.remove_object(2).unwrap();
+        assert_eq!(removed.id(), 2);
+        assert_eq!(w.objects.len(), 2);
+        assert!(w.object(2).is_none());
+        assert_eq!(w.object(1).unwrap().id(), 1);
+        assert_eq!(w.object(3).unwrap().id(), 3);
+        // removing a missing id does nothing
+        assert!(w.remove_object(2).is_none());
+        assert_eq!(w.objects.len(), 2);
+        // the id can be used again
+        let mut w = w.add_object(Box::new(Cube::new(2))).unwrap();
+        assert_eq!(w.object(2).unwrap().id(), 2);
+
+        w.clear_objects();
+        assert!(w.objects.is_empty());
+        assert!(w.object(1).is_none());
+        assert_eq!(w.add_shape(Sphere::new).unwrap(), 1);
+    }
+
+    #[test]
+    fn intersections_carry_the_shape_they_hit() {
+        let child = Sphere::new(5).set_material(Material::new(RED, 0.9, 0.0));
+        let group = Group::new(4)
+            .set_transform(Matrix::translation(0.0, 0.0, 5.0))
+            .add_child(Box::new(child));
+        let w = World::default().add_object(Box::new(group)).unwrap();
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = w.intersect_with_ray(&r);
+        assert_eq!(xs.len(), 6);
+        for i in &xs {
+            assert_eq!(i.object.unwrap().id(), i.object_id);
+        }
+        // the nested sphere is reached without looking it up in the world
+        let nested = xs.iter().find(|i| i.object_id == 5).unwrap();
+        assert_eq!(nested.object.unwrap().material().color, RED);
+        let comps = Intersection::prepare_computations(nested, &r, &w, &xs);
+        assert_eq!(comps.object.id(), 5);
+        assert_eq!(w.shade_hit(&comps, MAX_DEPTH).blue, 0.0);
+    }
+
+    #[test]
+    fn mutating_object_material_changes_its_color() {
+        let mut w = World::default();
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let before = w.color_at(&r);
+        w.object_mut(1).unwrap().material_mut().unwrap().ambient = 1.0;
+        let after = w.color_at(&r);
+        assert_eq!(
+            after.subtract(&before),
+            Color::make(0.8, 1.0, 0.6).multiply_value(0.9)
+        );
+        assert!(w.object_mut(42).is_none());
+    }
+
+    #[test]
+    fn mutating_object_transform_moves_it() {
+        let mut w = World::default();
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        w.object_mut(1)
+            .unwrap()
+            .set_transform_mut(Matrix::translation(0.0, 0.0, 1.0));
+        assert_eq!(w.intersect_with_ray(&r)[0].distance, 4.5);
+        w.object_mut(2)
+            .unwrap()
+            .set_transform_mut(Matrix::translation(10.0, 0.0, 0.0));
+        assert_eq!(w.intersect_with_ray(&r)[0].distance, 5.0);
+    }
+
     #[test]
     fn shade_at_intersection() {
         let w = World::default();
         let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
         let intersection = Intersection::new(w.objects[0].id(), 4.0);
-        let comps = Intersection::prepare_computations(&intersection, &r, &w);
-        let color = w.shade_hit(&comps);
+        let comps = Intersection::prepare_computations(&intersection, &r, &w, &[intersection]);
+        let color = w.shade_hit(&comps, MAX_DEPTH);
         assert_eq!(
             color,
             Color::make(0.38066116930395194, 0.4758264616299399, 0.2854958769779639)
@@ -195,12 +814,11 @@ mod world_tests {
         let w = World::default().set_light(light);
         let r = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0));
         let intersection = Intersection::new(w.objects[1].id(), 0.5);
-        let comps = Intersection::prepare_computations(&intersection, &r, &w);
-        let color = w.shade_hit(&comps);
+        let comps = Intersection::prepare_computations(&intersection, &r, &w, &[intersection]);
+        let color = w.shade_hit(&comps, MAX_DEPTH);
         assert_eq!(
             color,
-            // Color::make(0.9049844720832575, 0.9049844720832575, 0.9049844720832575) what?
-            Color::make(0.1, 0.1, 0.1)
+            Color::make(0.9049841500886814, 0.9049841500886814, 0.9049841500886814)
         );
     }
 
@@ -212,6 +830,18 @@ mod world_tests {
         assert_eq!(color, Color::default());
     }
 
+    #[test]
+    fn world_color_for_degenerate_ray_direction() {
+        let w = World::default();
+        // bypasses the debug assertion of `Ray::new`, the sphere distances are NaN
+        let r = Ray {
+            origin: point(0.0, 0.0, -5.0),
+            direction: vector_normalize(&vector(0.0, 0.0, 0.0)),
+            time: 0.0,
+        };
+        assert_eq!(w.color_at(&r), Color::default());
+    }
+
     #[test]
     fn world_color_when_ray_hits() {
         let w = World::default();
@@ -223,6 +853,23 @@ mod world_tests {
         );
     }
 
+    #[test]
+    fn world_bounds_span_every_finite_object() {
+        let w = World::empty()
+            .add_objects(vec![
+                Box::new(Sphere::new(1).set_transform(Matrix::translation(-3.0, 0.0, 0.0))),
+                Box::new(Sphere::new(2).set_transform(
+                    Matrix::translation(2.0, 4.0, 1.0).multiply(&Matrix::scaling(2.0, 2.0, 2.0)),
+                )),
+                Box::new(Plane::new(3)),
+            ])
+            .unwrap();
+        let bounds = w.bounds();
+        assert_eq!(bounds.min, point(-4.0, -1.0, -1.0));
+        assert_eq!(bounds.max, point(4.0, 6.0, 3.0));
+        assert!(!World::empty().bounds().is_finite());
+    }
+
     #[test]
     fn no_shadow_when_nothing_colinear_with_point_and_light() {
         let w = World::default();
@@ -239,6 +886,19 @@ mod world_tests {
         assert!(w.is_shadowed(&p, l));
     }
 
+    #[test]
+    fn no_shadow_from_an_object_not_casting_shadows() {
+        let mut w = World::default();
+        let p = point(10.0, -10.0, 10.0);
+        assert!(w.is_shadowed(&p, &w.lights[0]));
+        for id in [1, 2] {
+            let material = w.object_mut(id).unwrap().material_mut().unwrap();
+            material.casts_shadow = false;
+        }
+        assert!(!w.is_shadowed(&p, &w.lights[0]));
+        assert_eq!(w.shadow_fraction(&p, &w.lights[0]), 0.0);
+    }
+
     #[test]
     fn no_shadow_when_an_object_is_behind_the_light() {
         let w = World::default();
@@ -255,6 +915,145 @@ mod world_tests {
         assert!(!w.is_shadowed(&p, l));
     }
 
+    #[test]
+    fn blocker_beyond_the_light_is_ignored() {
+        let light = Light::point_light(point(0.0, 0.0, -5.0), WHITE);
+        let w = World::empty()
+            .add_objects(vec![Box::new(
+                Sphere::new(1).set_transform(Matrix::translation(0.0, 0.0, -10.0)),
+            )])
+            .unwrap();
+        assert!(!w.is_shadowed(&point(0.0, 0.0, 0.0), &light));
+        let r = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, -1.0));
+        assert!(w.intersect_with_ray_bounded(&r, 5.0).is_empty());
+        assert_eq!(w.intersect_with_ray(&r).len(), 2);
+    }
+
+    #[test]
+    fn blocker_before_the_light_reports_shadowed() {
+        let light = Light::point_light(point(0.0, 0.0, -10.0), WHITE);
+        let w = World::empty()
+            .add_objects(vec![Box::new(
+                Sphere::new(1).set_transform(Matrix::translation(0.0, 0.0, -5.0)),
+            )])
+            .unwrap();
+        assert!(w.is_shadowed(&point(0.0, 0.0, 0.0), &light));
+        let r = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, -1.0));
+        let xs = w.intersect_with_ray_bounded(&r, 10.0);
+        assert_eq!(xs.len(), 2);
+        assert_eq!(xs[0].distance, 4.0);
+        assert_eq!(xs[1].distance, 6.0);
+    }
+
+    #[test]
+    fn directional_light_shadows_reach_infinity() {
+        let w = World::default();
+        let sun = Light::directional(vector(0.0, -1.0, 0.0), WHITE);
+        assert!(w.is_shadowed(&point(0.0, -1000.0, 0.0), &sun));
+        assert!(!w.is_shadowed(&point(10.0, -10.0, 0.0), &sun));
+        // objects below the point never block the sun
+        assert!(!w.is_shadowed(&point(0.0, 10.0, 0.0), &sun));
+    }
+
+    #[test]
+    fn light_without_radius_casts_hard_shadows() {
+        let w = World::default();
+        let l = w.lights.first().unwrap();
+        let cases = vec![
+            (point(0.0, 10.0, 0.0), 0.0),
+            (point(10.0, -10.0, 10.0), 1.0),
+            (point(-20.0, 20.0, -20.0), 0.0),
+            (point(-2.0, 2.0, -2.0), 0.0),
+        ];
+        for (p, expected) in cases {
+            assert_eq!(w.shadow_fraction(&p, l), expected);
+        }
+    }
+
+    #[test]
+    fn light_with_radius_casts_soft_shadows() {
+        // a slab covering the half space x < 0 between the point and the light
+        let w = World::empty()
+            .add_object(Box::new(Cube::new(1).set_transform(
+                Matrix::translation(-5.0, 5.0, 0.0).multiply(&Matrix::scaling(5.0, 0.1, 5.0)),
+            )))
+            .unwrap();
+        let light = Light::point_light(point(0.0, 10.0, 0.0), WHITE).set_soft_shadows(1.0, 64);
+        let fraction = w.shadow_fraction(&point(0.0, 0.0, 0.0), &light);
+        assert!(fraction > 0.0 && fraction < 1.0, "{}", fraction);
+        // fully lit and fully shadowed points
+        assert_eq!(w.shadow_fraction(&point(5.0, 0.0, 0.0), &light), 0.0);
+        assert_eq!(w.shadow_fraction(&point(-5.0, 0.0, 0.0), &light), 1.0);
+    }
+
+    #[test]
+    fn ambient_occlusion_is_lower_near_contact_points() {
+        // unit sphere resting on the floor at the origin
+        let w = World::empty()
+            .add_object(Box::new(Plane::new(1)))
+            .unwrap()
+            .add_object(Box::new(
+                Sphere::new(2).set_transform(Matrix::translation(0.0, 1.0, 0.0)),
+            ))
+            .unwrap();
+        let occlusion_below = |x: f64| {
+            let r = Ray::new(point(x, 5.0, 0.0), vector(0.0, -1.0, 0.0));
+            let xs = w.intersect_all(&r);
+            let comps = Intersection::prepare_computations(&xs[0], &r, &w, &xs);
+            w.ambient_occlusion(&comps, 256, 2.0)
+        };
+        let contact = occlusion_below(1.1);
+        let open = occlusion_below(10.0);
+        assert_eq!(open, 1.0);
+        assert!(contact < open, "{}", contact);
+    }
+
+    #[test]
+    fn transparent_blockers_cast_lighter_shadows() {
+        let blocker = |transparency| {
+            Sphere::new(1)
+                .set_transform(Matrix::translation(0.0, 5.0, 0.0))
+                .set_material(Material::default().set_transparency(transparency))
+        };
+        let light = Light::point_light(point(0.0, 10.0, 0.0), WHITE);
+        let p = point(0.0, 0.0, 0.0);
+        let glass = World::empty()
+            .add_object(Box::new(blocker(0.5)))
+            .unwrap()
+            .set_transparent_shadows(true);
+        // the ray crosses the surface of the sphere twice
+        assert_eq!(glass.shadow_fraction(&p, &light), 0.75);
+        let fraction = glass.shadow_fraction(&p, &light.set_soft_shadows(0.5, 16));
+        assert!(fraction > 0.0 && fraction < 1.0, "{}", fraction);
+        let opaque = World::empty()
+            .add_object(Box::new(blocker(0.0)))
+            .unwrap()
+            .set_transparent_shadows(true);
+        let light = Light::point_light(point(0.0, 10.0, 0.0), WHITE);
+        assert_eq!(opaque.shadow_fraction(&p, &light), 1.0);
+        // disabled by default
+        let glass = World::empty().add_object(Box::new(blocker(0.5))).unwrap();
+        assert_eq!(glass.shadow_fraction(&p, &light), 1.0);
+    }
+
+    #[test]
+    fn intensity_with_a_known_sequence_of_jittered_lights() {
+        // a slab covering the half space x < 0 between the point and the light
+        let w = World::empty()
+            .add_object(Box::new(Cube::new(1).set_transform(
+                Matrix::translation(-5.0, 5.0, 0.0).multiply(&Matrix::scaling(5.0, 0.1, 5.0)),
+            )))
+            .unwrap();
+        let light = Light::point_light(point(0.0, 10.0, 0.0), WHITE).set_soft_shadows(1.0, 2);
+        // offsets of -0.5 and 0.5 along x, the slab blocks the first one
+        let mut sampler = SequenceSampler::new(vec![0.25, 0.5, 0.5, 0.75, 0.5, 0.5]);
+        let p = point(0.0, 0.0, 0.0);
+        assert_eq!(w.intensity_at(&p, &light, &mut sampler), 0.5);
+        // samples outside of the unit sphere are rejected
+        let mut sampler = SequenceSampler::new(vec![1.0, 1.0, 1.0, 0.25, 0.5, 0.5, 0.25, 0.5, 0.5]);
+        assert_eq!(w.intensity_at(&p, &light, &mut sampler), 0.0);
+    }
+
     #[test]
     fn shade_it_intersection_in_the_shadow() {
         let light = Light::point_light(point(0.0, 0.0, -10.0), Color::make(1.0, 1.0, 1.0));
@@ -263,12 +1062,428 @@ mod world_tests {
         let w = World::empty()
             .set_light(light)
             .add_object(Box::new(s1))
-            .add_object(Box::new(s2));
+            .unwrap()
+            .add_object(Box::new(s2))
+            .unwrap();
 
         let r = Ray::new(point(0.0, 0.0, 5.0), vector(0.0, 0.0, 1.0));
         let intersection = Intersection::new(w.objects[1].id(), 4.0);
-        let comps = Intersection::prepare_computations(&intersection, &r, &w);
-        let color = w.shade_hit(&comps);
+        let comps = Intersection::prepare_computations(&intersection, &r, &w, &[intersection]);
+        let color = w.shade_hit(&comps, MAX_DEPTH);
         assert_eq!(color, Color::make(0.1, 0.1, 0.1));
     }
+
+    #[test]
+    fn shadowed_point_only_gets_the_ambient_term() {
+        let light = Light::point_light(point(0.0, 0.0, -10.0), Color::make(0.9, 0.8, 0.7));
+        let material = Material::new(Color::make(0.3, 0.6, 0.9), 0.9, 0.9);
+        let w = World::empty()
+            .set_light(light)
+            .add_object(Box::new(Sphere::new(1)))
+            .unwrap()
+            .add_object(Box::new(
+                Sphere::new(2)
+                    .set_transform(Matrix::translation(0.0, 0.0, 10.0))
+                    .set_material(material),
+            ))
+            .unwrap();
+        let r = Ray::new(point(0.0, 0.0, 5.0), vector(0.0, 0.0, 1.0));
+        let intersection = Intersection::new(2, 4.0);
+        let comps = Intersection::prepare_computations(&intersection, &r, &w, &[intersection]);
+        let color = w.shade_hit(&comps, MAX_DEPTH);
+        let shape = w.object(2).unwrap();
+        let unoptimized = w.lights[0].lighting(
+            shape.material(),
+            shape.transform(),
+            &comps.over_point,
+            &comps.eyev,
+            &comps.normalv,
+            0.0,
+        );
+        let expected = Color::make(0.3, 0.6, 0.9)
+            .multiply(&Color::make(0.9, 0.8, 0.7))
+            .multiply_value(0.1);
+        // compared bit for bit as the color equality is approximate
+        for (a, b) in [(color, unoptimized), (color, expected)] {
+            assert_eq!(a.red.to_bits(), b.red.to_bits());
+            assert_eq!(a.green.to_bits(), b.green.to_bits());
+            assert_eq!(a.blue.to_bits(), b.blue.to_bits());
+        }
+    }
+
+    #[test]
+    fn reflected_color_for_non_reflective_material() {
+        let w = World::default();
+        let r = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 0.0, 1.0));
+        let intersection = Intersection::new(w.objects[1].id(), 1.0);
+        let comps = Intersection::prepare_computations(&intersection, &r, &w, &[intersection]);
+        let color = w.reflected_color(&comps, MAX_DEPTH);
+        assert_eq!(color, Color::default());
+    }
+
+    fn world_with_reflective_floor() -> World {
+        let floor = Plane::new(3)
+            .set_transform(Matrix::translation(0.0, -1.0, 0.0))
+            .set_material(Material::default().set_reflective(0.5));
+        World::default().add_object(Box::new(floor)).unwrap()
+    }
+
+    #[test]
+    fn reflected_color_for_reflective_material() {
+        let w = world_with_reflective_floor();
+        let value = 2.0_f64.sqrt() / 2.0;
+        let r = Ray::new(point(0.0, 0.0, -3.0), vector(0.0, -value, value));
+        let intersection = Intersection::new(3, 2.0_f64.sqrt());
+        let comps = Intersection::prepare_computations(&intersection, &r, &w, &[intersection]);
+        let color = w.reflected_color(&comps, MAX_DEPTH);
+        assert_eq!(
+            color,
+            Color::make(
+                0.19033075703583227,
+                0.23791344629479033,
+                0.14274806777687418
+            )
+        );
+    }
+
+    #[test]
+    fn shade_hit_with_reflective_material() {
+        let w = world_with_reflective_floor();
+        let value = 2.0_f64.sqrt() / 2.0;
+        let r = Ray::new(point(0.0, 0.0, -3.0), vector(0.0, -value, value));
+        let intersection = Intersection::new(3, 2.0_f64.sqrt());
+        let comps = Intersection::prepare_computations(&intersection, &r, &w, &[intersection]);
+        let color = w.shade_hit(&comps, MAX_DEPTH);
+        assert_eq!(
+            color,
+            Color::make(0.8767561153398953, 0.9243388045988534, 0.8291734260809372)
+        );
+    }
+
+    #[test]
+    fn missing_ray_returns_the_background() {
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 1.0, 0.0));
+        assert_eq!(World::default().color_at(&r), BLACK);
+        let w = World::default().set_background(Color::make(0.2, 0.4, 0.9));
+        assert_eq!(w.color_at(&r), Color::make(0.2, 0.4, 0.9));
+        // the hits are not affected
+        let hit = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(&hit), World::default().color_at(&hit));
+    }
+
+    #[test]
+    fn missing_ray_returns_the_sky_color() {
+        let w = World::default()
+            .set_background(RED)
+            .set_sky(|r: &Ray| WHITE.multiply_value(r.direction.1.max(0.0)));
+        let up = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 1.0, 0.0));
+        assert_eq!(w.color_at(&up), WHITE);
+        let down = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, -1.0, 0.0));
+        assert_eq!(w.color_at(&down), BLACK);
+    }
+
+    #[test]
+    fn fog_fades_distant_hits_toward_its_color() {
+        let fog = Color::make(0.5, 0.5, 0.5);
+        let w = World::default().set_fog(fog, 0.2);
+        let clear = World::default();
+        let near = Ray::new(point(0.0, 0.0, -2.0), vector(0.0, 0.0, 1.0));
+        let far = Ray::new(point(0.0, 0.0, -20.0), vector(0.0, 0.0, 1.0));
+        // both rays hit the same spot of the outer sphere
+        assert_eq!(clear.color_at(&near), clear.color_at(&far));
+        let distance_to_fog = |c: Color| {
+            let d = c.subtract(&fog);
+            d.red.abs() + d.green.abs() + d.blue.abs()
+        };
+        assert!(distance_to_fog(w.color_at(&far)) < distance_to_fog(w.color_at(&near)));
+        // misses take the fog color
+        let miss = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 1.0, 0.0));
+        assert_eq!(w.color_at(&miss), fog);
+    }
+
+    #[test]
+    fn fog_without_density_leaves_colors_unchanged() {
+        let w = World::default().set_fog(Color::make(0.5, 0.5, 0.5), 0.0);
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(&r), World::default().color_at(&r));
+    }
+
+    #[test]
+    fn color_at_with_mutually_reflective_surfaces() {
+        let light = Light::point_light(point(0.0, 0.0, 0.0), Color::make(1.0, 1.0, 1.0));
+        let lower = Plane::new(1)
+            .set_transform(Matrix::translation(0.0, -1.0, 0.0))
+            .set_material(Material::default().set_reflective(1.0));
+        let upper = Plane::new(2)
+            .set_transform(Matrix::translation(0.0, 1.0, 0.0))
+            .set_material(Material::default().set_reflective(1.0));
+        let w = World::empty()
+            .set_light(light)
+            .add_object(Box::new(lower))
+            .unwrap()
+            .add_object(Box::new(upper))
+            .unwrap();
+        let r = Ray::new(point(0.0, 0.0, 0.0), vector(0.0, 1.0, 0.0));
+        // terminates thanks to the recursion limit
+        let color = w.color_at(&r);
+        assert!(color.red.is_finite());
+        assert!(color.green.is_finite());
+        assert!(color.blue.is_finite());
+    }
+
+    #[test]
+    fn color_at_with_depth_stops_bouncing_when_no_depth_remains() {
+        let w = world_with_reflective_floor();
+        let value = 2.0_f64.sqrt() / 2.0;
+        let r = Ray::new(point(0.0, 0.0, -3.0), vector(0.0, -value, value));
+        let without_reflection = w.color_at_with_depth(&r, 0);
+        let with_reflection = w.color_at_with_depth(&r, 1);
+        assert_eq!(
+            with_reflection.subtract(&without_reflection),
+            Color::make(
+                0.19033075703583227,
+                0.23791344629479033,
+                0.14274806777687418
+            )
+        );
+        assert_eq!(w.color_at(&r), w.color_at_with_depth(&r, MAX_DEPTH));
+    }
+
+    #[test]
+    fn reflected_color_at_maximum_recursive_depth() {
+        let w = world_with_reflective_floor();
+        let value = 2.0_f64.sqrt() / 2.0;
+        let r = Ray::new(point(0.0, 0.0, -3.0), vector(0.0, -value, value));
+        let intersection = Intersection::new(3, 2.0_f64.sqrt());
+        let comps = Intersection::prepare_computations(&intersection, &r, &w, &[intersection]);
+        let color = w.reflected_color(&comps, 0);
+        assert_eq!(color, Color::default());
+    }
+
+    fn glass_material() -> Material {
+        Material::default()
+            .set_transparency(1.0)
+            .set_refractive_index(1.5)
+    }
+
+    #[test]
+    fn refracted_color_with_opaque_surface() {
+        let w = World::default();
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = vec![Intersection::new(1, 4.0), Intersection::new(1, 6.0)];
+        let comps = Intersection::prepare_computations(&xs[0], &r, &w, &xs);
+        assert_eq!(w.refracted_color(&comps, MAX_DEPTH), Color::default());
+    }
+
+    #[test]
+    fn refracted_color_at_maximum_recursive_depth() {
+        let w = World::empty()
+            .set_light(Light::point_light(point(-10.0, 10.0, -10.0), WHITE))
+            .add_object(Box::new(Sphere::new(1).set_material(glass_material())))
+            .unwrap();
+        let r = Ray::new(point(0.0, 0.0, -5.0), vector(0.0, 0.0, 1.0));
+        let xs = vec![Intersection::new(1, 4.0), Intersection::new(1, 6.0)];
+        let comps = Intersection::prepare_computations(&xs[0], &r, &w, &xs);
+        assert_eq!(w.refracted_color(&comps, 0), Color::default());
+    }
+
+    #[test]
+    fn refracted_color_under_total_internal_reflection() {
+        let w = World::empty()
+            .set_light(Light::point_light(point(-10.0, 10.0, -10.0), WHITE))
+            .add_object(Box::new(Sphere::new(1).set_material(glass_material())))
+            .unwrap();
+        let value = 2.0_f64.sqrt() / 2.0;
+        let r = Ray::new(point(0.0, 0.0, value), vector(0.0, 1.0, 0.0));
+        let xs = vec![Intersection::new(1, -value), Intersection::new(1, value)];
+        // we are inside the sphere, so look at the second intersection
+        let comps = Intersection::prepare_computations(&xs[1], &r, &w, &xs);
+        assert_eq!(w.refracted_color(&comps, MAX_DEPTH), Color::default());
+    }
+
+    fn world_with_glass_floor_and_ball(floor_material: Material) -> World {
+        let floor = Plane::new(3)
+            .set_transform(Matrix::translation(0.0, -1.0, 0.0))
+            .set_material(floor_material);
+        let ball = Sphere::new(4)
+            .set_transform(Matrix::translation(0.0, -3.5, -0.5))
+            .set_material(Material {
+                ambient: 0.5,
+                ..Material::new(RED, 0.9, 0.9)
+            });
+        World::default()
+            .add_object(Box::new(floor))
+            .unwrap()
+            .add_object(Box::new(ball))
+            .unwrap()
+    }
+
+    #[test]
+    fn shade_hit_with_transparent_material() {
+        let w = world_with_glass_floor_and_ball(
+            Material::default()
+                .set_transparency(0.5)
+                .set_refractive_index(1.5),
+        );
+        let value = 2.0_f64.sqrt() / 2.0;
+        let r = Ray::new(point(0.0, 0.0, -3.0), vector(0.0, -value, value));
+        let xs = vec![Intersection::new(3, 2.0_f64.sqrt())];
+        let comps = Intersection::prepare_computations(&xs[0], &r, &w, &xs);
+        let color = w.shade_hit(&comps, MAX_DEPTH);
+        assert_eq!(
+            color,
+            Color::make(0.936425358304063, 0.686425358304063, 0.686425358304063)
+        );
+    }
+
+    #[test]
+    fn shade_hit_with_reflective_transparent_material() {
+        let w = world_with_glass_floor_and_ball(
+            Material::default()
+                .set_reflective(0.5)
+                .set_transparency(0.5)
+                .set_refractive_index(1.5),
+        );
+        let value = 2.0_f64.sqrt() / 2.0;
+        let r = Ray::new(point(0.0, 0.0, -3.0), vector(0.0, -value, value));
+        let xs = vec![Intersection::new(3, 2.0_f64.sqrt())];
+        let comps = Intersection::prepare_computations(&xs[0], &r, &w, &xs);
+        let color = w.shade_hit(&comps, MAX_DEPTH);
+        assert_eq!(
+            color,
+            Color::make(0.9339151166246789, 0.6964342040561993, 0.6924306657553447)
+        );
+    }
+
+    fn sphere_position(i: usize) -> (f64, f64) {
+        ((i % 10) as f64 * 3.0 - 15.0, (i / 10) as f64 * 3.0 - 15.0)
+    }
+
+    fn world_with_spheres(count: usize) -> World {
+        (1..=count).fold(World::default(), |w, i| {
+            let (x, y) = sphere_position(i);
+            w.add_object(Box::new(
+                Sphere::new(i + 100).set_transform(Matrix::translation(x, y, 10.0)),
+            ))
+            .unwrap()
+        })
+    }
+
+    #[test]
+    fn adding_shapes_assigns_distinct_ids() {
+        let mut w = World::default();
+        let mut ids: Vec<usize> = (0..50).map(|_| w.add_shape(Sphere::new).unwrap()).collect();
+        ids.extend((0..50).map(|_| w.add_shape(Cube::new).unwrap()));
+        let mut unique = ids.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), ids.len());
+        // the default world already uses the ids 1 and 2
+        assert!(!ids.contains(&1) && !ids.contains(&2));
+        for id in ids {
+            assert_eq!(w.object(id).map(|o| o.id()), Some(id));
+        }
+    }
+
+    #[test]
+    fn adding_a_shape_ignoring_the_assigned_id_fails() {
+        let mut w = World::default();
+        let err = w.add_shape(|_| Sphere::new(7)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert!(w.object(7).is_none());
+    }
+
+    #[test]
+    fn adding_objects_in_bulk_keeps_their_order() {
+        let objects: Vec<Box<dyn Shape>> = vec![
+            Box::new(Plane::new(5)),
+            Box::new(Sphere::new(3)),
+            Box::new(Cube::new(4)),
+        ];
+        let w = World::default().add_objects(objects).unwrap();
+        let ids: Vec<usize> = w.objects.iter().map(|o| o.id()).collect();
+        assert_eq!(ids, vec![1, 2, 5, 3, 4]);
+        assert_eq!(w.object(4).map(|o| o.id()), Some(4));
+        let duplicates: Vec<Box<dyn Shape>> = vec![Box::new(Plane::new(6)), Box::new(Cube::new(6))];
+        assert!(World::empty().add_objects(duplicates).is_err());
+    }
+
+    #[test]
+    fn adding_a_duplicate_id_errors() {
+        let w = World::default();
+        assert!(w.add_object(Box::new(Sphere::new(2))).is_err());
+        // ids nested in groups collide as well
+        let group = Group::new(3).add_child(Box::new(Sphere::new(1)));
+        assert!(World::default().add_object(Box::new(group)).is_err());
+        assert!(World::default()
+            .add_object(Box::new(Sphere::new(3)))
+            .is_ok());
+    }
+
+    #[test]
+    fn object_lookup_finds_every_object() {
+        let group = Group::new(300).add_child(Box::new(Sphere::new(301)));
+        let w = world_with_spheres(100).add_object(Box::new(group)).unwrap();
+        for id in (101..=200).chain([1, 2, 300, 301]) {
+            assert_eq!(w.object(id).map(|o| o.id()), Some(id));
+        }
+        assert!(w.object(42).is_none());
+    }
+
+    #[test]
+    fn shading_many_objects_matches_unindexed_lookup() {
+        let indexed = world_with_spheres(100);
+        // objects pushed directly are missing from the index and found by searching
+        let mut searched = World::empty().set_light(Light::point_light(
+            point(-10.0, 10.0, -10.0),
+            Color::make(1.0, 1.0, 1.0),
+        ));
+        world_with_spheres(100)
+            .objects
+            .into_iter()
+            .for_each(|o| searched.objects.push(o));
+        for i in 1..=100 {
+            let (x, y) = sphere_position(i);
+            let r = Ray::new(point(x, y, -5.0), vector(0.0, 0.0, 1.0));
+            assert_eq!(indexed.color_at(&r), searched.color_at(&r));
+        }
+    }
+
+    #[test]
+    fn color_at_hits_shape_nested_in_group() {
+        let material = || Material::new(Color::make(0.8, 1.0, 0.6), 0.7, 0.2);
+        let light = || Light::point_light(point(-10.0, 10.0, -10.0), Color::make(1.0, 1.0, 1.0));
+        let group = Group::new(2)
+            .set_transform(Matrix::translation(0.0, 0.0, 5.0))
+            .add_child(Box::new(Sphere::new(3).set_material(material())));
+        let w = World::empty()
+            .set_light(light())
+            .add_object(Box::new(group))
+            .unwrap();
+        assert_eq!(w.object(3).map(|o| o.id()), Some(3));
+
+        // the same sphere placed directly in the world must look identical
+        let reference = World::empty()
+            .set_light(light())
+            .add_object(Box::new(
+                Sphere::new(3)
+                    .set_transform(Matrix::translation(0.0, 0.0, 5.0))
+                    .set_material(material()),
+            ))
+            .unwrap();
+        let r = Ray::new(point(0.5, 0.5, 0.0), vector(0.0, 0.0, 1.0));
+        assert_eq!(w.color_at(&r), reference.color_at(&r));
+    }
+
+    #[test]
+    fn random_unit_vector_falls_back_when_the_sampler_misses_the_ball() {
+        let normal = vector(0.0, 1.0, 0.0);
+        let mut sampler = SequenceSampler::new(vec![1.0]);
+        assert_eq!(random_unit_vector(&mut sampler, &normal), normal);
+        let mut sampler = SequenceSampler::new(vec![0.75, 0.5, 0.5]);
+        assert_eq!(
+            random_unit_vector(&mut sampler, &normal),
+            vector(1.0, 0.0, 0.0)
+        );
+    }
 }